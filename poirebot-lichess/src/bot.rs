@@ -1,44 +1,257 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
+use crate::client::{retry_rate_limited, retry_rate_limited_within, LichessClient};
 use crate::licorice::client::Lichess;
-use crate::licorice::models::board::{BoardState, Challenge, Challengee, Event, GameFull, GameID};
+use crate::licorice::models::board::{
+    BoardState, Challenge, Challengee, Event, GameFull, GameID, GameState,
+};
 use crate::licorice::models::user::User;
 use anyhow::Context;
+use clap::ArgMatches;
+use std::convert::TryInto;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::oneshot;
 use tokio_stream::StreamExt;
 
-use crate::licorice::models::game::Player;
+use crate::licorice::models::game::{Clock as TimeControl, Game, Player};
+use poirebot::book::OpeningBook;
+use poirebot::eco;
 use poirebot::game::pieces::Color;
 use poirebot::game::{Board, Move};
-use poirebot::genius::Brain;
-use std::time::SystemTime;
+use poirebot::genius::{Brain, Evaluation, SearchResult};
+use poirebot::time::{move_budget, stockfish_level_budget, without_overhead, Clock, TimeClass};
+use poirebot::uci::EngineInfo;
+use std::time::{Duration, Instant, SystemTime};
 
 /// The world containing all games.
 #[derive(Default)]
 struct World {
     /// Tasks: (game ID, sender)
     games: HashMap<String, UnboundedSender<Message>>,
+    /// Challengers of the incoming challenges: (challenge ID, username)
+    challengers: HashMap<String, String>,
+    /// Number of games started in this session against each challenger: (username, count)
+    games_per_opponent: HashMap<String, usize>,
+    /// Opponents of the games, as last seen by their tasks: (game ID, opponent)
+    opponents: Arc<Mutex<HashMap<String, Opponent>>>,
+    /// Number of games finished in this session
+    games_played: usize,
+}
+
+impl World {
+    /// Counts a game against its challenger, if it started from an incoming challenge.
+    fn record_game_start(&mut self, game_id: &str) {
+        if let Some(username) = self.challengers.remove(game_id) {
+            *self.games_per_opponent.entry(username).or_insert(0) += 1;
+        }
+    }
+
+    /// Whether the user has already played the maximum number of games in this session.
+    fn has_reached_game_limit(&self, username: &str, max_games: Option<usize>) -> bool {
+        match max_games {
+            Some(max_games) => {
+                self.games_per_opponent.get(username).copied().unwrap_or(0) >= max_games
+            }
+            None => false,
+        }
+    }
+
+    /// The number of games in progress (not counting the unresolved challenges).
+    fn active_games(&self) -> usize {
+        self.games
+            .keys()
+            .filter(|id| !self.challengers.contains_key(*id))
+            .count()
+    }
+
+    /// Summarizes the activity of the bot for the heartbeat log, e.g.
+    /// `Alive for 1h 02m 03s: 2 active games, 5 games played`.
+    fn heartbeat_summary(&self, uptime: Duration) -> String {
+        let secs = uptime.as_secs();
+        format!(
+            "Alive for {}h {:02}m {:02}s: {} active games, {} games played",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            self.active_games(),
+            self.games_played
+        )
+    }
+
+    /// Whether the user already has the maximum number of unresolved challenges.
+    fn has_reached_pending_limit(&self, username: &str, max_pending: usize) -> bool {
+        self.challengers
+            .values()
+            .filter(|challenger| *challenger == username)
+            .count()
+            >= max_pending
+    }
 }
 
 /// Messages to pass to and from tasks.
 #[derive(Debug, Clone)]
 enum Message {
     /// Instruct to process the challenge (can accept or reject).
-    NewChallenge(Challenge),
+    NewChallenge(Box<Challenge>),
     /// Instruct to process the new game.
     NewGame,
     /// Game/challenge is aborted.
     Abort,
     /// Game/challenge is aborted.
     BoardChat(String, String),
-    /// When a move comes through. The boolean is for whether the game is over.
-    Move(Move, Color, bool),
-    /// (re)Set the board (initial FEN, UCI moves, own color)
-    SetBoard(String, Vec<Move>, Color),
+    /// When a move comes through, with all the moves since the start of the game and the color
+    /// of the last move. The boolean is for whether the game is over.
+    Move(Vec<Move>, Color, bool, GameClocks),
+    /// (re)Set the board (initial FEN, UCI moves, own color, clocks if the game has any,
+    /// Stockfish level if the opponent is Stockfish, whether the opponent is a BOT account)
+    SetBoard(
+        String,
+        Vec<Move>,
+        Color,
+        Option<GameClocks>,
+        Option<u8>,
+        bool,
+    ),
     /// Handle when someone requests a draw
     DrawOffer(Color),
+    /// The game is over, with White's score (1 for a win, 0.5 for a draw, 0 for a loss).
+    GameResult(f32),
+    /// The last state of the game, once it is over.
+    GameOver(Box<GameState>),
+}
+
+/// The maximum evaluation (in centipawns, either way) of a position to offer or claim a draw.
+const DRAW_OFFER_MAX_EVAL: i32 = 25;
+
+/// The maximum material imbalance (in centipawns) of a pawnless endgame to consider it drawn, e.g.
+/// rook against bishop.
+const DRAWN_ENDGAME_MAX_IMBALANCE: i32 = 200;
+
+/// When to offer, accept or claim a draw with the bot's moves. Each rule is independent, and the
+/// draw is offered when any of them applies.
+///
+/// Lichess has no separate claim: offering a draw when the position can be claimed (or when the
+/// opponent offers one) ends the game right away.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrawPolicy {
+    /// Accept the opponent's draw offer when the bot's evaluation is below this (in centipawns).
+    pub accept_draw_below_cp: Option<i32>,
+    /// Offer a draw in a balanced endgame without pawns (see `DRAWN_ENDGAME_MAX_IMBALANCE`).
+    pub offer_draw_in_drawn_endgame: bool,
+    /// Claim a draw when the bot's move repeats a balanced position for the third time.
+    pub claim_repetition: bool,
+    /// Claim a draw when the bot's move reaches the 50-move rule in a balanced position.
+    pub claim_fifty_move: bool,
+}
+
+impl DrawPolicy {
+    /// The reason to offer a draw with the bot's move, if any, given the board after the move,
+    /// whether it repeats the position for the third time, the evaluation for the bot, and whether
+    /// the opponent has a standing draw offer.
+    fn draw_reason(
+        &self,
+        outcome: &Board,
+        threefold: bool,
+        eval: Evaluation,
+        opponent_offers_draw: bool,
+    ) -> Option<&'static str> {
+        let balanced =
+            matches!(eval, Evaluation::Score(score) if score.abs() <= DRAW_OFFER_MAX_EVAL);
+        let accept = match (self.accept_draw_below_cp, eval) {
+            (Some(_), Evaluation::Worst) => true,
+            (Some(max), Evaluation::Score(score)) => score < max,
+            _ => false,
+        };
+
+        if opponent_offers_draw && accept {
+            Some("accepting the offer")
+        } else if self.claim_repetition && threefold && balanced {
+            Some("repetition")
+        } else if self.claim_fifty_move && outcome.halfmove_clock >= 100 && balanced {
+            Some("50-move rule")
+        } else if self.offer_draw_in_drawn_endgame && is_drawn_endgame(outcome) && balanced {
+            Some("drawn endgame")
+        } else {
+            None
+        }
+    }
+}
+
+/// How the bot behaves towards its opponent in a game, which depends on whether the opponent is
+/// another bot (see `Config::profile`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameProfile {
+    /// Whether to write in the chat: move times, opening names and replies to commands.
+    pub chat: bool,
+    /// See `Config::move_delay`.
+    pub move_delay: (Duration, Duration),
+    /// When to offer, accept or claim a draw.
+    pub draw_policy: DrawPolicy,
+}
+
+/// Whether the endgame has no pawns left, and not enough extra material for either side to win.
+fn is_drawn_endgame(board: &Board) -> bool {
+    board.is_endgame()
+        && board.get_pawns(Color::White).is_empty()
+        && board.get_pawns(Color::Black).is_empty()
+        && board.material_balance_cp(Color::White).abs() <= DRAWN_ENDGAME_MAX_IMBALANCE
+}
+
+/// The number of moves (plies) after which Lichess no longer lets a game be aborted.
+const ABORTABLE_PLIES: usize = 2;
+
+/// How often an idle game task checks its watchdog.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tracks the last time a game advanced, to detect opponents that abandon the game.
+#[derive(Debug, Clone, Copy, Default)]
+struct Watchdog {
+    /// The last time a board event advanced the game (if it started).
+    last_activity: Option<Instant>,
+}
+
+impl Watchdog {
+    /// Records that the game advanced at the given time.
+    fn feed(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+    }
+
+    /// Whether the game hasn't advanced for longer than the timeout, at the given time.
+    /// Games that haven't started yet never expire.
+    fn has_expired(&self, now: Instant, timeout: Duration) -> bool {
+        match self.last_activity {
+            Some(last_activity) => now.saturating_duration_since(last_activity) > timeout,
+            None => false,
+        }
+    }
+}
+
+/// The clocks of both players in a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GameClocks {
+    white: Clock,
+    black: Clock,
+}
+
+impl GameClocks {
+    /// Reads the clocks from the game state sent by Lichess (times are in milliseconds).
+    fn from_state(state: &GameState) -> Self {
+        Self {
+            white: Clock::from_millis(state.wtime.into(), state.winc.into()),
+            black: Clock::from_millis(state.btime.into(), state.binc.into()),
+        }
+    }
+
+    /// Get the clock of the given color.
+    fn get(&self, color: Color) -> &Clock {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
 }
 
 /// Configures the bot.
@@ -56,52 +269,744 @@ pub struct Config {
     pub stockfish_max: u8,
     /// Whether to only accept from users the bot account follows.
     pub following_only: bool,
+    /// File to append finished game results to (if applicable).
+    pub results_log: Option<PathBuf>,
+    /// Whether to accept rated challenges.
+    pub accept_rated: bool,
+    /// Whether to accept casual challenges.
+    pub accept_casual: bool,
+    /// Whether to decline correspondence and unlimited challenges.
+    pub no_correspondence: bool,
+    /// The think time per move in games without a clock.
+    pub no_clock_think_time: Duration,
+    /// The fixed think time per move, overriding the clock-based budget (if applicable).
+    pub think_time: Option<Duration>,
+    /// The time to reserve from every move budget for the move to reach Lichess.
+    pub move_overhead: Duration,
+    /// The range of the delay to wait for before sending a move, to feel less instant (a zero
+    /// range disables it). It never goes over the move budget.
+    pub move_delay: (Duration, Duration),
+    /// The maximum number of games to play against the same challenger in a session (if any).
+    pub max_games_per_opponent: Option<usize>,
+    /// The maximum number of unresolved challenges from the same challenger.
+    pub max_pending_challenges: usize,
+    /// File to append the bot's evaluated positions to, with the game result (if applicable).
+    pub eval_log: Option<PathBuf>,
+    /// The share of the move budget to spend against each Stockfish level (1 to 8).
+    pub stockfish_budget_factors: [f32; 8],
+    /// How long to wait for an opponent's first move before aborting the game (if applicable).
+    pub idle_abort_timeout: Option<Duration>,
+    /// The interval between the heartbeat logs, if enabled.
+    pub heartbeat: Option<Duration>,
+    /// The time control categories to accept challenges for (games with a clock only).
+    pub accepted_time_classes: Vec<TimeClass>,
+    /// The identity of the engine, as shown in the chat.
+    pub engine: EngineInfo,
+    /// When to offer, accept or claim a draw.
+    pub draw_policy: DrawPolicy,
+    /// The contempt (in centipawns) against other bots, if they get their own profile (see
+    /// `Config::profile`).
+    pub bot_opponent_contempt: Option<i32>,
+    /// Whether to abort the game instead of resigning when failing to move, while it can still
+    /// be aborted.
+    pub abort_on_error: bool,
+    /// The opening book to play from, if any.
+    pub book: Option<Arc<OpeningBook>>,
+    /// Whether to search during the opponent's turn, assuming it plays the expected reply.
+    pub ponder: bool,
+    /// The number of moves at the start of the game to search deeper, and by how many plies (if
+    /// enabled).
+    pub opening_extension: Option<(usize, usize)>,
+    /// The maximum number of nodes to search for each move, in addition to the time (if any).
+    pub max_nodes: Option<u64>,
+    /// Whether to announce the name of the opening in the chat, once it is known.
+    pub announce_opening: bool,
+    /// Whether to log the position and the moves of the game along with its errors.
+    pub log_fen_on_error: bool,
+    /// The options of the challenges sent to users.
+    pub challenge: ChallengeOptions,
 }
 
-async fn find_and_send_move(
-    lichess: Arc<Lichess>,
+impl Config {
+    /// Builds the configuration from the arguments of the `start` command, for the bot account
+    /// with the given username.
+    pub fn from_args(args: &ArgMatches, username: &str) -> anyhow::Result<Self> {
+        let stockfish: Option<(u8, u8)> = if let Some(stockfish_arg) = args.value_of("stockfish") {
+            let pattern = regex::Regex::new(r"(\d+)(?:-(\d+))?").unwrap();
+            let matches = pattern
+                .captures(stockfish_arg)
+                .with_context(|| "Failed to parse stockfish arg")?;
+
+            let stockfish_min = matches
+                .get(1)
+                .with_context(|| {
+                    "Invalid stockfish arg, use --stockfish=1 or -stockfish=1-8 for example"
+                })?
+                .as_str();
+            let stockfish_min = stockfish_min
+                .parse()
+                .with_context(|| "Invalid stockfish level")?;
+            let stockfish_max = matches
+                .get(2)
+                .map_or(Ok(stockfish_min), |m| m.as_str().parse())
+                .with_context(|| "Invalid max stockfish level")?;
+            Some((stockfish_min, stockfish_max))
+        } else {
+            None
+        };
+
+        let no_clock_think_time = match args.value_of("no-clock-think-time") {
+            Some(secs) => Duration::from_secs(
+                secs.parse()
+                    .with_context(|| "Invalid think time for games without a clock")?,
+            ),
+            None => poirebot::time::DEFAULT_THINK_TIME,
+        };
+
+        let think_time = args
+            .value_of("think-time-ms")
+            .map(|millis| millis.parse().map(Duration::from_millis))
+            .transpose()
+            .with_context(|| "Invalid think time")?;
+        if think_time == Some(Duration::ZERO) {
+            return Err(anyhow::Error::msg("The think time must be positive"));
+        }
+
+        let move_overhead = match args.value_of("move-overhead-ms") {
+            Some(millis) => {
+                Duration::from_millis(millis.parse().with_context(|| "Invalid move overhead")?)
+            }
+            None => poirebot::time::DEFAULT_MOVE_OVERHEAD,
+        };
+
+        let delay_arg = |name: &str| {
+            args.value_of(name)
+                .map(|millis| millis.parse().map(Duration::from_millis))
+                .transpose()
+                .with_context(|| format!("Invalid --{}", name))
+        };
+        let min_move_delay = delay_arg("min-move-delay-ms")?;
+        let max_move_delay = delay_arg("max-move-delay-ms")?;
+        let move_delay = match (min_move_delay, max_move_delay) {
+            (None, None) => (Duration::ZERO, Duration::ZERO),
+            (min, max) => {
+                let min = min.unwrap_or_default();
+                let max = max.unwrap_or(min);
+                if min > max {
+                    return Err(anyhow::Error::msg(
+                        "The minimum move delay is longer than the maximum",
+                    ));
+                }
+                (min, max)
+            }
+        };
+
+        let stockfish_budget_factors = match args.value_of("stockfish-budget-factors") {
+            Some(factors) => {
+                let factors = factors
+                    .split(',')
+                    .map(|f| f.trim().parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .with_context(|| "Invalid Stockfish budget factor")?;
+                if factors.iter().any(|f| !(*f > 0.0 && *f <= 1.0)) {
+                    return Err(anyhow::Error::msg(
+                        "Stockfish budget factors must be shares of the budget, above 0 and up to 1",
+                    ));
+                }
+                factors.as_slice().try_into().with_context(|| {
+                    "Expected 8 Stockfish budget factors, one for each level (1 to 8)"
+                })?
+            }
+            None => poirebot::time::STOCKFISH_BUDGET_FACTORS,
+        };
+
+        let accepted_time_classes = match args.value_of("time-classes") {
+            Some(classes) => classes
+                .split(',')
+                .map(|class| class.parse())
+                .collect::<anyhow::Result<Vec<TimeClass>>>()
+                .with_context(|| "Invalid time classes")?,
+            None => TimeClass::ALL.to_vec(),
+        };
+
+        let idle_abort_timeout = args
+            .value_of("abort-idle-after")
+            .map(|secs| secs.parse().map(Duration::from_secs))
+            .transpose()
+            .with_context(|| "Invalid idle timeout")?;
+
+        let heartbeat = args
+            .value_of("heartbeat-secs")
+            .map(|secs| secs.parse().map(Duration::from_secs))
+            .transpose()
+            .with_context(|| "Invalid heartbeat interval")?
+            .filter(|interval| !interval.is_zero());
+
+        let draw_policy = DrawPolicy {
+            accept_draw_below_cp: args
+                .value_of("accept-draw-below")
+                .map(|cp| cp.parse())
+                .transpose()
+                .with_context(|| "Invalid evaluation to accept draws below")?,
+            offer_draw_in_drawn_endgame: args.is_present("offer-draw-in-drawn-endgame"),
+            claim_repetition: args.is_present("offer-draw-on-repetition"),
+            claim_fifty_move: args.is_present("claim-fifty-move"),
+        };
+
+        let bot_opponent_contempt = args
+            .value_of("bot-opponent-contempt")
+            .map(|cp| cp.parse())
+            .transpose()
+            .with_context(|| "Invalid contempt against bots")?;
+
+        let max_games_per_opponent = args
+            .value_of("max-games-per-opponent")
+            .map(|max| max.parse())
+            .transpose()
+            .with_context(|| "Invalid maximum number of games per opponent")?;
+
+        let max_pending_challenges = args
+            .value_of("max-pending-challenges")
+            .unwrap()
+            .parse()
+            .with_context(|| "Invalid maximum number of pending challenges")?;
+
+        let opening_extension = match args.value_of("opening-extension") {
+            Some(plies) => {
+                let plies = plies
+                    .parse()
+                    .with_context(|| "Invalid number of opening extension plies")?;
+                let moves = args
+                    .value_of("opening-extension-moves")
+                    .unwrap()
+                    .parse()
+                    .with_context(|| "Invalid number of opening extension moves")?;
+                Some((moves, plies)).filter(|_| plies > 0)
+            }
+            None => None,
+        };
+
+        let max_nodes = args
+            .value_of("max-nodes")
+            .map(|nodes| nodes.parse())
+            .transpose()
+            .with_context(|| "Invalid maximum number of nodes")?;
+
+        let book = args
+            .value_of("book")
+            .map(|path| OpeningBook::load(Path::new(path)))
+            .transpose()?
+            .map(|book| {
+                info!("Loaded opening book with {} positions", book.len());
+                Arc::new(book)
+            });
+
+        Ok(Self {
+            no_accept: args.is_present("no-accept"),
+            username: username.to_owned(),
+            rematch: args.is_present("rematch"),
+            stockfish: stockfish.map_or(0, |s| s.0),
+            stockfish_max: stockfish.map_or(0, |s| s.1),
+            following_only: args.is_present("following-only"),
+            results_log: args.value_of("results-log").map(PathBuf::from),
+            accept_rated: !args.is_present("casual-only"),
+            accept_casual: !args.is_present("rated-only"),
+            no_correspondence: args.is_present("no-correspondence"),
+            no_clock_think_time,
+            think_time,
+            move_overhead,
+            move_delay,
+            max_games_per_opponent,
+            max_pending_challenges,
+            eval_log: args.value_of("eval-log").map(PathBuf::from),
+            stockfish_budget_factors,
+            idle_abort_timeout,
+            heartbeat,
+            accepted_time_classes,
+            engine: args
+                .value_of("engine-name")
+                .map_or_else(EngineInfo::default, EngineInfo::with_name),
+            draw_policy,
+            bot_opponent_contempt,
+            abort_on_error: args.is_present("abort-on-error"),
+            ponder: args.is_present("ponder"),
+            opening_extension,
+            max_nodes,
+            announce_opening: args.is_present("announce-opening"),
+            log_fen_on_error: args.is_present("log-fen-on-error"),
+            challenge: ChallengeOptions::from_args(args)?,
+            book,
+        })
+    }
+
+    /// The profile to play with against the opponent. Against humans, it follows the
+    /// configuration. Against other bots, if a contempt is configured, the niceties are pointless:
+    /// the bot doesn't chat, wait or offer draws, and only accepts a draw when its evaluation is
+    /// below minus the contempt.
+    pub fn profile(&self, opponent_is_bot: bool) -> GameProfile {
+        match self.bot_opponent_contempt {
+            Some(contempt) if opponent_is_bot => GameProfile {
+                chat: false,
+                move_delay: (Duration::ZERO, Duration::ZERO),
+                draw_policy: DrawPolicy {
+                    accept_draw_below_cp: Some(-contempt),
+                    ..DrawPolicy::default()
+                },
+            },
+            _ => GameProfile {
+                chat: true,
+                move_delay: self.move_delay,
+                draw_policy: self.draw_policy.clone(),
+            },
+        }
+    }
+}
+
+/// A position evaluated by the bot during a game, kept to tune the evaluation once the result
+/// is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EvalSample {
+    /// The position before the bot's move.
+    fen: String,
+    /// The search evaluation, in centipawns from White's perspective.
+    eval: i32,
+}
+
+impl EvalSample {
+    /// Creates a sample from the search evaluation, which is from the bot's perspective.
+    /// Forced mates don't have a centipawn score, so they are not sampled.
+    fn new(board: &Board, bot_color: Color, eval: Evaluation) -> Option<Self> {
+        let eval = match eval {
+            Evaluation::Score(score) => score,
+            _ => return None,
+        };
+        Some(Self {
+            fen: board.to_fen(),
+            eval: if bot_color.is_white() { eval } else { -eval },
+        })
+    }
+
+    /// Formats the sample as a CSV row (without line ending): FEN, evaluation, White's score.
+    fn to_csv_row(&self, white_score: f32) -> String {
+        format!("{},{},{}", self.fen, self.eval, white_score)
+    }
+}
+
+/// The summary of a finished game, from the bot's perspective.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The Lichess game ID.
+    pub game_id: String,
+    /// The opponent's username (or Stockfish level).
+    pub opponent: String,
+    /// The opponent's rating, if any.
+    pub opponent_rating: Option<u16>,
+    /// The time control, as `initial+increment` in seconds (`-` when unlimited).
+    pub time_control: String,
+    /// The result for the bot: `win`, `loss`, `draw`, or the game status if it didn't finish.
+    pub result: String,
+    /// The number of (full) moves played.
+    pub moves: usize,
+}
+
+impl GameRecord {
+    /// Summarizes an exported game for the given bot username.
+    pub fn from_game(game: &Game, bot_username: &str) -> Self {
+        let bot_color = if is_player(&game.players.white, bot_username) {
+            "white"
+        } else {
+            "black"
+        };
+
+        let (opponent, opponent_rating) = match get_opponent(game, bot_username) {
+            Player::Entity(entity) => (
+                entity
+                    .user
+                    .as_ref()
+                    .map(|u| u.username.clone())
+                    .unwrap_or_else(|| "Anonymous".into()),
+                Some(entity.rating),
+            ),
+            Player::StockFish(stockfish) => {
+                (format!("Stockfish level {}", stockfish.ai_level), None)
+            }
+        };
+
+        let time_control = game
+            .clock
+            .as_ref()
+            .map(|clock| {
+                format!(
+                    "{}+{}",
+                    clock.initial.unwrap_or(0),
+                    clock.increment.unwrap_or(0)
+                )
+            })
+            .unwrap_or_else(|| "-".into());
+
+        let result = match &game.winner {
+            Some(winner) if winner == bot_color => "win".into(),
+            Some(_) => "loss".into(),
+            None if game.status == "aborted" || game.status == "noStart" => game.status.clone(),
+            None => "draw".into(),
+        };
+
+        let plies = game
+            .moves
+            .as_ref()
+            .map_or(0, |moves| moves.split_whitespace().count());
+
+        Self {
+            game_id: game.id.clone(),
+            opponent,
+            opponent_rating,
+            time_control,
+            result,
+            moves: plies.div_ceil(2),
+        }
+    }
+
+    /// Formats the record as a tab-separated line (without line ending).
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.game_id,
+            self.opponent,
+            self.opponent_rating
+                .map_or_else(|| "-".into(), |r| r.to_string()),
+            self.time_control,
+            self.result,
+            self.moves
+        )
+    }
+}
+
+/// How a finished game ended, from the bot's perspective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    WinByMate,
+    WinByResign,
+    WinByTimeout,
+    LossByMate,
+    LossByResign,
+    LossByTimeout,
+    DrawByAgreement,
+    DrawByStalemate,
+    DrawByRepetition,
+    DrawBy50,
+    DrawByInsufficient,
+    Aborted,
+}
+
+impl GameOutcome {
+    /// Determines the outcome of a game from its last state, and its initial position.
+    /// Returns `None` if the game isn't over, or ended in an unknown way.
+    pub fn from_state(state: &GameState, bot_color: Color, initial: Board) -> Option<Self> {
+        let moves = state
+            .moves
+            .split_whitespace()
+            .map(Move::from_pure_notation)
+            .collect::<Vec<Move>>();
+        Self::from_status(
+            &state.status,
+            state.winner.as_deref(),
+            bot_color,
+            initial,
+            &moves,
+        )
+    }
+
+    /// Determines the outcome of a game from its Lichess status and winner (`white` or `black`).
+    ///
+    /// Lichess reports every other draw as `draw`, so the final position (after replaying the
+    /// moves) tells whether it was forced by the rules or agreed upon.
+    pub fn from_status(
+        status: &str,
+        winner: Option<&str>,
+        bot_color: Color,
+        initial: Board,
+        moves: &[Move],
+    ) -> Option<Self> {
+        let bot_won = winner.map(|winner| winner == color_name(bot_color));
+        let outcome = match (status, bot_won) {
+            ("mate", Some(true)) => GameOutcome::WinByMate,
+            ("mate", Some(false)) => GameOutcome::LossByMate,
+            ("resign", Some(true)) => GameOutcome::WinByResign,
+            ("resign", Some(false)) => GameOutcome::LossByResign,
+            ("outoftime" | "timeout", Some(true)) => GameOutcome::WinByTimeout,
+            ("outoftime" | "timeout", Some(false)) => GameOutcome::LossByTimeout,
+            ("outoftime" | "timeout" | "draw", None) => classify_draw(initial, moves),
+            ("stalemate", _) => GameOutcome::DrawByStalemate,
+            ("aborted" | "noStart", _) => GameOutcome::Aborted,
+            _ => return None,
+        };
+        Some(outcome)
+    }
+}
+
+/// Tells apart the draws reported by Lichess: by the rules (insufficient material, 50-move rule,
+/// threefold repetition) or by agreement.
+fn classify_draw(initial: Board, moves: &[Move]) -> GameOutcome {
+    let mut board = initial;
+    let mut history = vec![board.zobrist_hash()];
+    for m in moves {
+        board.apply_move(*m);
+        history.push(board.zobrist_hash());
+    }
+
+    let repetitions = history
+        .iter()
+        .filter(|hash| **hash == board.zobrist_hash())
+        .count();
+    if board.has_insufficient_material() {
+        GameOutcome::DrawByInsufficient
+    } else if board.halfmove_clock >= 100 {
+        GameOutcome::DrawBy50
+    } else if repetitions >= 3 {
+        GameOutcome::DrawByRepetition
+    } else {
+        GameOutcome::DrawByAgreement
+    }
+}
+
+/// The name of a color, as used by Lichess.
+fn color_name(color: Color) -> &'static str {
+    if color.is_white() {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+/// Describes the last of the moves played from the board for the log, e.g. `Nf3 (quiet)` or
+/// `Bxf7+ (capture, check)`.
+fn describe_last_move(mut board: Board, moves: &[Move]) -> String {
+    let (last, played) = match moves.split_last() {
+        Some(split) => split,
+        None => return String::new(),
+    };
+    for m in played {
+        board.apply_move(*m);
+    }
+    let san = board.to_san(*last);
+    format!("{} ({})", san, board.apply_move_with_info(*last))
+}
+
+/// Searches for the bot's move and sends it to Lichess. If sending it is rate limited, it is only
+/// retried within the time left before flagging (if there is a clock).
+///
+/// Returns `None` without searching if the bot has no legal move: the game is already over by
+/// checkmate or stalemate, and resigning would turn a stalemate into a loss.
+async fn find_and_send_move<L: LichessClient>(
+    lichess: Arc<L>,
     game_id: &str,
     brain: &mut Brain,
-) -> anyhow::Result<()> {
-    let (sensor, recv) = oneshot::channel::<Option<Move>>();
+    budget: Duration,
+    time_to_flag: Option<Duration>,
+    profile: &GameProfile,
+    opponent_offers_draw: bool,
+) -> anyhow::Result<Option<SearchResult>> {
+    let board = brain.board();
+    if board.legal_move_count(brain.color) == 0 {
+        if board.is_in_check(brain.color) {
+            info!("({}) Checkmated, waiting for the game to end", game_id);
+        } else {
+            info!("({}) Stalemate, waiting for the game to end", game_id);
+        }
+        return Ok(None);
+    }
+
+    let (sensor, recv) = oneshot::channel::<Option<SearchResult>>();
     let current_time = SystemTime::now();
-    brain.choose_move(sensor);
+    brain.choose_move(sensor, budget);
 
-    let m = recv
+    let result = recv
         .await
         .with_context(|| "communication failure")?
         .with_context(|| "ran out of moves")?;
 
     let duration = current_time.elapsed().unwrap();
-    lichess
-        .write_in_bot_chat(
-            game_id,
-            "player",
-            format!(
-                "Move generation took {} seconds",
-                (duration.as_secs_f32() * 10000.0).round() / 10000.0
+    if profile.chat {
+        lichess
+            .write_in_bot_chat(
+                game_id,
+                "player",
+                format!(
+                    "Move generation took {} seconds",
+                    (duration.as_secs_f32() * 10000.0).round() / 10000.0
+                )
+                .as_str(),
             )
-            .as_str(),
-        )
-        .await
-        .unwrap_or(());
+            .await
+            .unwrap_or(());
+    }
 
-    lichess
-        .make_a_bot_move(game_id, m.to_pure_notation().as_str(), false)
-        .await
-        .with_context(|| "Failed to dispatch move to Lichess")
+    let draw_reason = profile.draw_policy.draw_reason(
+        &brain.board().with_move(result.best),
+        brain.is_threefold_repetition(result.best),
+        result.eval,
+        opponent_offers_draw,
+    );
+    if let Some(reason) = draw_reason {
+        info!("({}) Offering a draw ({})", game_id, reason);
+    }
+    let offer_draw = draw_reason.is_some();
+
+    let delay = move_delay(
+        profile.move_delay,
+        game_seed(game_id) ^ brain.plies_played() as u64,
+        budget.saturating_sub(duration),
+    );
+    if !delay.is_zero() {
+        debug!("({}) Waiting {:?} before moving", game_id, delay);
+        tokio::time::sleep(delay).await;
+    }
+
+    // Backing off from a rate limit mustn't take longer than the time left on the clock
+    let uci = result.best.to_pure_notation();
+    let send = || lichess.make_a_bot_move(game_id, &uci, offer_draw);
+    match time_to_flag {
+        Some(time) => {
+            let left = time.saturating_sub(current_time.elapsed().unwrap_or_default());
+            retry_rate_limited_within(left, send).await
+        }
+        None => retry_rate_limited(send).await,
+    }
+    .with_context(|| "Failed to dispatch move to Lichess")?;
+    Ok(Some(result))
+}
+
+/// The delay before sending a move: picked in the range with the seed, but no longer than what is
+/// left of the move budget.
+fn move_delay(range: (Duration, Duration), seed: u64, remaining: Duration) -> Duration {
+    let (min, max) = range;
+    // Mix the bits of the seed, so that close seeds give different delays
+    let mixed = (seed ^ (seed >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    let span = (max - min).as_millis() as u64;
+    let delay = min + Duration::from_millis((mixed >> 32) % (span + 1));
+    delay.min(remaining)
+}
+
+/// Whether to abort the game rather than resign it after failing to move, given the number of
+/// moves (plies) played.
+fn should_abort_on_error(config: &Config, plies: usize) -> bool {
+    config.abort_on_error && plies < ABORTABLE_PLIES
+}
+
+/// Aborts or resigns the game after failing to move (see `should_abort_on_error`).
+async fn give_up<L: LichessClient>(lichess: &L, game_id: &str, config: &Config, plies: usize) {
+    if should_abort_on_error(config, plies) {
+        warn!("({}) Aborting the game instead of resigning", game_id);
+        retry_rate_limited(|| lichess.abort_bot_game(game_id))
+            .await
+            .unwrap_or(());
+    } else {
+        retry_rate_limited(|| lichess.resign_bot_game(game_id))
+            .await
+            .unwrap_or(());
+    }
 }
 
 /// Task that handles new game state messages.
-async fn message_loop(
+/// Searches for the bot's move with the budget of its clock, and sends it (see
+/// `find_and_send_move`). Records the expected reply to ponder on, and the evaluation sample if
+/// they are logged.
+///
+/// On error, the move couldn't be played and the game should be given up.
+#[allow(clippy::too_many_arguments)]
+async fn play_move<L: LichessClient>(
+    lichess: Arc<L>,
+    game_id: &str,
+    brain: &mut Brain,
+    config: &Config,
+    profile: &GameProfile,
+    clocks: Option<&GameClocks>,
+    stockfish_level: Option<u8>,
+    opponent_offers_draw: bool,
+    ponder_line: &mut Option<(Move, Move)>,
+    eval_samples: &mut Vec<EvalSample>,
+) -> anyhow::Result<()> {
+    let budget = think_budget(config, clocks, brain.color, stockfish_level);
+    let time_left = time_to_flag(config, clocks, brain.color);
+    let result = find_and_send_move(
+        lichess,
+        game_id,
+        brain,
+        budget,
+        time_left,
+        profile,
+        opponent_offers_draw,
+    )
+    .await?;
+    if let Some(result) = result {
+        *ponder_line = result.pv.get(1).map(|reply| (result.best, *reply));
+        if config.eval_log.is_some() {
+            eval_samples.extend(EvalSample::new(brain.board(), brain.color, result.eval));
+        }
+    }
+    Ok(())
+}
+
+async fn message_loop<L: LichessClient>(
     game_id: GameID,
     recv: &mut UnboundedReceiver<Message>,
-    lichess: Arc<Lichess>,
+    lichess: Arc<L>,
     config: &Config,
+    snapshot: &Mutex<GameSnapshot>,
 ) {
-    let mut brain = Brain::new(Board::default(), Color::White); // Temporary value
+    // Temporary position, until the board is set
+    let mut brain = Brain::new(Board::default(), Color::White)
+        .with_book(config.book.clone())
+        .with_opening_seed(game_seed(&game_id.id));
+    brain = brain.with_max_nodes(config.max_nodes);
+    if let Some((moves, plies)) = config.opening_extension {
+        brain = brain.with_opening_extension(moves, plies);
+    }
+    let mut clocks: Option<GameClocks> = None;
+    let mut stockfish_level: Option<u8> = None;
+    let mut eval_samples: Vec<EvalSample> = Vec::new();
+    let mut watchdog = Watchdog::default();
+    // The bot's last chosen move, with the expected reply to ponder on
+    let mut ponder_line: Option<(Move, Move)> = None;
+    // Whether the opening was announced (or can't be named anymore)
+    let mut opening_announced = false;
+    // Whether the opponent has a standing draw offer, until the bot moves
+    let mut opponent_offers_draw = false;
+    // Whether the board was set, and the latest moves that came before it (applied along with it)
+    let mut board_set = false;
+    let mut early_moves: Option<Vec<Move>> = None;
+    // The profile depends on the opponent, which is known once the board is set
+    let mut profile = config.profile(false);
+
+    loop {
+        if let Ok(mut snapshot) = snapshot.lock() {
+            *snapshot = GameSnapshot::new(&brain);
+        }
+
+        let message = match tokio::time::timeout(WATCHDOG_INTERVAL, recv.recv()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(_) => {
+                // Only live games can be abandoned, and Lichess only lets us abort before the
+                // opponent's first move
+                let abandoned = match config.idle_abort_timeout {
+                    Some(timeout) => {
+                        clocks.is_some()
+                            && !brain.is_own_turn()
+                            && brain.opponent_last_move.is_none()
+                            && watchdog.has_expired(Instant::now(), timeout)
+                    }
+                    None => false,
+                };
+                if abandoned {
+                    warn!("({}) Opponent never moved, aborting", game_id.id);
+                    lichess.abort_bot_game(&game_id.id).await.unwrap_or(());
+                    break;
+                }
+                continue;
+            }
+        };
 
-    while let Some(message) = recv.recv().await {
         debug!("({}) message loop: {:?}", &game_id.id, message);
         match message {
             Message::NewChallenge(challenge) => {
@@ -111,24 +1016,27 @@ async fn message_loop(
                     challenge.id, challenger_name,
                 );
 
-                let accepted = accept_or_decline_challenge(&challenge, lichess.clone(), &config)
+                let decision = accept_or_decline_challenge(&challenge, lichess.clone(), config)
                     .await
-                    .with_context(|| "Failed to accept/decline challenge")
-                    .unwrap_or_else(|e| {
+                    .with_context(|| "Failed to accept/decline challenge");
+                match decision {
+                    Ok(ChallengeDecision::Accept) => {
+                        info!(
+                            "Challenge accepted: {} (other: {})",
+                            challenge.id, challenger_name,
+                        );
+                    }
+                    Ok(ChallengeDecision::Decline(reason)) => {
+                        info!(
+                            "Challenge declined: {} (other: {}, reason: {:?})",
+                            challenge.id, challenger_name, reason
+                        );
+                        break;
+                    }
+                    Err(e) => {
                         error!("{:?}", e);
-                        false
-                    });
-                if accepted {
-                    info!(
-                        "Challenge accepted: {} (other: {})",
-                        challenge.id, challenger_name,
-                    );
-                } else {
-                    info!(
-                        "Challenge declined: {} (other: {})",
-                        challenge.id, challenger_name,
-                    );
-                    break;
+                        break;
+                    }
                 }
             }
             Message::NewGame => {
@@ -141,161 +1049,504 @@ async fn message_loop(
             }
             Message::BoardChat(username, message) => {
                 info!("({})\t\t{}\t\t{}", game_id.id, username, message);
-                if message == ".version" {
+                if message == ".version" && profile.chat {
                     lichess
                         .write_in_bot_chat(
                             &game_id.id,
                             "player",
-                            format!("Poirebot version: {}", clap::crate_version!()).as_str(),
+                            format!("{} version: {}", config.engine.name, config.engine.version)
+                                .as_str(),
                         )
                         .await
                         .unwrap_or(());
                 }
             }
-            Message::Move(m, color, game_over) => {
+            Message::Move(moves, color, game_over, move_clocks) => {
+                // The brain doesn't know the initial position nor its color yet
+                if !board_set {
+                    debug!(
+                        "({}) Holding {} plies until the board is set",
+                        game_id.id,
+                        moves.len()
+                    );
+                    early_moves = Some(moves);
+                    continue;
+                }
+
+                watchdog.feed(Instant::now());
+                // Games without a clock (correspondence, unlimited) keep the fixed think time
+                if clocks.is_some() {
+                    clocks = Some(move_clocks);
+                }
+
+                // The state may repeat moves already applied, or catch up on missed ones
+                let previous = *brain.board();
+                let new_moves = match brain.sync_moves(&moves) {
+                    Ok(new_moves) => new_moves,
+                    Err(e) => {
+                        // Lichess only lets us abort the first moves: resign after them, rather
+                        // than leaving the game to run out of time
+                        error!("({}) {:?}{}", game_id.id, e, error_context(config, &brain));
+                        give_up(lichess.as_ref(), &game_id.id, config, moves.len()).await;
+                        break;
+                    }
+                };
+                let m = match new_moves.last() {
+                    Some(m) => *m,
+                    None => {
+                        debug!("Ignored repeated moves ({} plies)", moves.len());
+                        continue;
+                    }
+                };
+                if new_moves.len() > 1 {
+                    info!("({}) Caught up on {} moves", game_id.id, new_moves.len());
+                }
+
+                if config.announce_opening
+                    && profile.chat
+                    && !opening_announced
+                    && *brain.initial_board() == Board::default()
+                    && eco::is_out_of_book(&moves)
+                {
+                    opening_announced = true;
+                    if let Some(name) = eco::opening_name(&moves) {
+                        lichess
+                            .write_in_bot_chat(
+                                &game_id.id,
+                                "player",
+                                format!("Opening: {}", name).as_str(),
+                            )
+                            .await
+                            .unwrap_or(());
+                    }
+                }
+
+                // When the position was reset, the new moves start from the initial board
+                let before = if new_moves.len() == moves.len() {
+                    *brain.initial_board()
+                } else {
+                    previous
+                };
+                let description = describe_last_move(before, &new_moves);
+
                 let bot_move = color == brain.color;
                 if bot_move {
-                    if brain.last_move == Some(m) {
-                        debug!("Ignored repeated bot move: {}", m.to_pure_notation());
-                        continue;
+                    debug!("Bot moved: {}", description);
+                    if let Some((best, reply)) = ponder_line.take() {
+                        if config.ponder && best == m {
+                            brain.ponder(reply);
+                        }
                     }
-                    debug!("Bot moved: {}", m.to_pure_notation());
-                    brain.own_move(m);
                 } else {
-                    if brain.opponent_last_move == Some(m) {
-                        debug!("Ignored repeated opponent move: {}", m.to_pure_notation());
-                        continue;
+                    debug!("Opponent ({}) moved: {}", game_id.id, description);
+                    if brain.pondered_move() == Some(m) {
+                        brain.ponder_hit();
+                    } else {
+                        brain.ponder_miss();
                     }
-                    debug!("Opponent ({}) moved: {}", game_id.id, m.to_pure_notation());
-                    brain.opponent_move(m);
 
                     if game_over {
                         break;
                     }
 
-                    if let Err(e) =
-                        find_and_send_move(lichess.clone(), &game_id.id, &mut brain).await
+                    if let Err(e) = play_move(
+                        lichess.clone(),
+                        &game_id.id,
+                        &mut brain,
+                        config,
+                        &profile,
+                        clocks.as_ref(),
+                        stockfish_level,
+                        std::mem::take(&mut opponent_offers_draw),
+                        &mut ponder_line,
+                        &mut eval_samples,
+                    )
+                    .await
                     {
-                        error!("{:?}", e);
-                        lichess.resign_bot_game(&game_id.id).await.unwrap_or(());
+                        error!("{:?}{}", e, error_context(config, &brain));
+                        give_up(lichess.as_ref(), &game_id.id, config, brain.plies_played()).await;
                         break;
                     }
                 }
             }
-            Message::SetBoard(fen, moves, own_color) => {
-                let mut board = Board::from_fen(&fen).expect("lichess sent invalid fen");
-                moves.iter().for_each(|m| board.apply_move(m.to_owned()));
-                brain = Brain::new(board, own_color);
-
-                let bots_turn = match own_color {
-                    Color::Black => moves.len() % 2 == 1,
-                    Color::White => moves.len() % 2 == 0,
-                };
+            Message::SetBoard(fen, moves, own_color, game_clocks, opponent_level, opponent_bot) => {
+                clocks = game_clocks;
+                stockfish_level = opponent_level;
+                profile = config.profile(opponent_bot);
+                if opponent_bot && config.bot_opponent_contempt.is_some() {
+                    info!("({}) Playing against a bot", game_id.id);
+                }
+                watchdog.feed(Instant::now());
 
-                let (last_move, opp_last_move) = if bots_turn {
-                    (
-                        moves.get(moves.len().wrapping_sub(2)).map(Move::to_owned),
-                        moves.last().map(Move::to_owned),
-                    )
-                } else {
-                    (
-                        moves.last().map(Move::to_owned),
-                        moves.get(moves.len().wrapping_sub(2)).map(Move::to_owned),
-                    )
+                // Moves that arrived first may be ahead of the state in the full game
+                board_set = true;
+                let moves = match early_moves.take() {
+                    Some(early) if early.len() > moves.len() && early.starts_with(&moves) => early,
+                    _ => moves,
                 };
-                brain.last_move = last_move;
-                brain.opponent_last_move = opp_last_move;
 
-                if bots_turn {
-                    if let Err(e) =
-                        find_and_send_move(lichess.clone(), &game_id.id, &mut brain).await
+                if let Err(e) = brain.set_position(&fen, &moves, own_color) {
+                    error!("({}) {:?}{}", game_id.id, e, error_context(config, &brain));
+                    give_up(lichess.as_ref(), &game_id.id, config, moves.len()).await;
+                    break;
+                }
+                // When resuming a game (e.g. after a reconnect), the opening was announced when it
+                // left the book
+                opening_announced = eco::is_out_of_book(&moves);
+
+                if brain.is_own_turn() {
+                    if let Err(e) = play_move(
+                        lichess.clone(),
+                        &game_id.id,
+                        &mut brain,
+                        config,
+                        &profile,
+                        clocks.as_ref(),
+                        stockfish_level,
+                        std::mem::take(&mut opponent_offers_draw),
+                        &mut ponder_line,
+                        &mut eval_samples,
+                    )
+                    .await
                     {
-                        error!("{:?}", e);
-                        lichess.resign_bot_game(&game_id.id).await.unwrap_or(());
+                        error!("{:?}{}", e, error_context(config, &brain));
+                        give_up(lichess.as_ref(), &game_id.id, config, brain.plies_played()).await;
                         break;
                     }
                 }
             }
-            Message::DrawOffer(_) => {
-                // Ignore draw offers right now
-                // Note: this gets declined automatically when the other player/bot moves
+            Message::DrawOffer(color) => {
+                debug!("({}) Draw offered by {:?}", game_id.id, color);
+                // Answered with the bot's next move (see `DrawPolicy`), which otherwise declines it
+                if color != brain.color {
+                    opponent_offers_draw = true;
+                }
+            }
+            Message::GameOver(state) => {
+                let outcome = GameOutcome::from_state(&state, brain.color, *brain.initial_board());
+                match outcome {
+                    Some(outcome) => info!("({}) Game over: {:?}", game_id.id, outcome),
+                    None => warn!("({}) Unknown game ending: {}", game_id.id, state.status),
+                }
+            }
+            Message::GameResult(white_score) => {
+                if let (Some(path), false) = (&config.eval_log, eval_samples.is_empty()) {
+                    let rows = eval_samples
+                        .drain(..)
+                        .map(|sample| format!("{}\n", sample.to_csv_row(white_score)))
+                        .collect::<String>();
+                    if let Err(e) = append_to_file(path, &rows).await {
+                        error!("({}) Failed to write to eval log: {:?}", game_id.id, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The time budget to think for the bot's next move.
+/// Against Stockfish, the budget is scaled with its level. The move overhead is always reserved.
+///
+/// A fixed think time (`--think-time-ms`) is used instead, unless the remaining clock (without the
+/// move overhead) is shorter.
+fn think_budget(
+    config: &Config,
+    clocks: Option<&GameClocks>,
+    color: Color,
+    stockfish_level: Option<u8>,
+) -> Duration {
+    if let Some(think_time) = config.think_time {
+        return match time_to_flag(config, clocks, color) {
+            Some(time_left) => think_time.min(time_left),
+            None => think_time,
+        };
+    }
+    let budget = move_budget(clocks.map(|c| c.get(color)), config.no_clock_think_time);
+    let budget = match stockfish_level {
+        Some(level) => stockfish_level_budget(budget, level, &config.stockfish_budget_factors),
+        None => budget,
+    };
+    without_overhead(budget, config.move_overhead)
+}
+
+/// The time the bot has to play its move before flagging: its remaining clock, without the move
+/// overhead. `None` if the game has no clock.
+fn time_to_flag(config: &Config, clocks: Option<&GameClocks>, color: Color) -> Option<Duration> {
+    clocks.map(|clocks| without_overhead(clocks.get(color).remaining, config.move_overhead))
+}
+
+/// The reason for declining a challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclineReason {
+    /// The bot is not accepting challenges (`--no-accept`).
+    NoAccept,
+    /// The challenge is correspondence or unlimited.
+    Correspondence,
+    /// The time control category is not accepted.
+    TimeControl,
+    /// The variant is not standard chess.
+    Variant,
+    /// The challenge is rated, but the bot only plays casual games.
+    Rated,
+    /// The challenge is casual, but the bot only plays rated games.
+    Casual,
+    /// The challenger is not followed by the bot (`--following-only`).
+    NotFollowing,
+    /// Enough games were played against the challenger.
+    GameLimit,
+    /// The challenger has another challenge pending.
+    PendingLimit,
+}
+
+impl DeclineReason {
+    /// The message sent to the challenger when declining.
+    pub fn message(&self) -> &'static str {
+        match self {
+            DeclineReason::NoAccept | DeclineReason::NotFollowing => {
+                "Sorry, I cannot play under these conditions."
+            }
+            DeclineReason::Correspondence => "Sorry, I don't play correspondence games.",
+            DeclineReason::TimeControl => "Sorry, I don't play this time control.",
+            DeclineReason::Variant => "Sorry, I only play standard chess.",
+            DeclineReason::Rated => "Sorry, I only play casual games.",
+            DeclineReason::Casual => "Sorry, I only play rated games.",
+            DeclineReason::GameLimit => "Sorry, we have played enough games for now.",
+            DeclineReason::PendingLimit => {
+                "Sorry, please wait for your other challenge to be resolved."
             }
         }
     }
 }
 
+/// The outcome of filtering a challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeDecision {
+    Accept,
+    Decline(DeclineReason),
+}
+
+/// Filters a challenge against the configuration.
+///
+/// Note: this doesn't check `--following-only`, which requires a request to Lichess; see
+/// `accept_or_decline_challenge`.
+fn decide_challenge(challenge: &Challenge, config: &Config) -> ChallengeDecision {
+    let reason = if config.no_accept {
+        Some(DeclineReason::NoAccept)
+    } else {
+        speed_decline_reason(&challenge.speed, config)
+            .or_else(|| {
+                time_class_decline_reason(challenge_time_class(&challenge.time_control), config)
+            })
+            .or_else(|| variant_decline_reason(&challenge.variant.key))
+            .or_else(|| rated_mode_decline_reason(challenge.rated, config))
+    };
+    match reason {
+        Some(reason) => ChallengeDecision::Decline(reason),
+        None => ChallengeDecision::Accept,
+    }
+}
+
 /// Decides to accept or decline the challenge and sends the response.
-/// Returns the new game if accepted, false otherwise.
-async fn accept_or_decline_challenge(
+/// Returns the decision that was sent.
+async fn accept_or_decline_challenge<L: LichessClient>(
     challenge: &Challenge,
-    lichess: Arc<Lichess>,
+    lichess: Arc<L>,
     config: &Config,
-) -> anyhow::Result<bool> {
+) -> anyhow::Result<ChallengeDecision> {
     let challenger = challenge.challenger.as_ref().unwrap();
 
-    // TODO: Determine acceptable modes/time.
-    let accept = {
-        if config.no_accept {
-            false
-        } else if config.following_only
-            && !is_following(lichess.clone(), &config.username, &challenger.username)
-                .await
-                .unwrap_or(false)
+    let decision = match decide_challenge(challenge, config) {
+        ChallengeDecision::Accept
+            if config.following_only
+                && !lichess
+                    .is_following(&config.username, &challenger.username)
+                    .await
+                    .unwrap_or(false) =>
         {
-            debug!(
-                "Declining challenge by {} because they are not followed",
-                &challenger.username
-            );
-            false
-        } else {
-            true
+            ChallengeDecision::Decline(DeclineReason::NotFollowing)
         }
+        decision => decision,
     };
 
-    if accept {
-        lichess
+    match decision {
+        ChallengeDecision::Accept => lichess
             .challenge_accept(&challenge.id)
             .await
-            .map(|_| true)
-            .with_context(|| "Failed to accept challenge")
-    } else {
-        lichess
-            .challenge_decline(
-                &challenge.id,
-                Some("Sorry, I cannot play under these conditions."),
-            )
+            .map(|_| decision)
+            .with_context(|| "Failed to accept challenge"),
+        ChallengeDecision::Decline(reason) => decline_challenge(&challenge.id, reason, lichess)
             .await
-            .map(|_| false)
-            .with_context(|| "Failed to decline challenge")
+            .map(|_| decision),
     }
 }
 
-async fn is_following(
-    lichess: Arc<Lichess>,
-    bot_username: &str,
-    username: &str,
-) -> anyhow::Result<bool> {
-    let mut stream = lichess
-        .get_followings(bot_username)
+/// Declines the challenge with the message of the given reason.
+async fn decline_challenge<L: LichessClient>(
+    challenge_id: &str,
+    reason: DeclineReason,
+    lichess: Arc<L>,
+) -> anyhow::Result<()> {
+    lichess
+        .challenge_decline(challenge_id, Some(reason.message()))
         .await
-        .with_context(|| "Failed to get followings")?;
-    while let Some(user) = stream.next().await {
-        if let Ok(user) = user {
-            if user.username == username {
-                return Ok(true);
-            } else {
-                continue;
-            }
+        .map(|_| ())
+        .with_context(|| "Failed to decline challenge")
+}
+
+/// Returns the reason to decline a challenge because of its mode (rated or casual), if any.
+fn rated_mode_decline_reason(rated: bool, config: &Config) -> Option<DeclineReason> {
+    if rated && !config.accept_rated {
+        Some(DeclineReason::Rated)
+    } else if !rated && !config.accept_casual {
+        Some(DeclineReason::Casual)
+    } else {
+        None
+    }
+}
+
+/// Returns the category of a challenge's time control, if it has a clock.
+fn challenge_time_class(time_control: &TimeControl) -> Option<TimeClass> {
+    if time_control.r#type.as_deref() != Some("clock") {
+        return None;
+    }
+    let initial = time_control.limit.map(u32::from).or(time_control.initial)?;
+    let increment = time_control.increment.unwrap_or(0).into();
+    Some(TimeClass::from_clock(initial, increment))
+}
+
+/// Returns the reason to decline a challenge because of its time control category, if any.
+/// Challenges without a clock are handled by `speed_decline_reason` instead.
+fn time_class_decline_reason(
+    time_class: Option<TimeClass>,
+    config: &Config,
+) -> Option<DeclineReason> {
+    match time_class {
+        Some(time_class) if !config.accepted_time_classes.contains(&time_class) => {
+            Some(DeclineReason::TimeControl)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the reason to decline a challenge because of its speed, if any.
+fn speed_decline_reason(speed: &str, config: &Config) -> Option<DeclineReason> {
+    if (speed == "correspondence" || speed == "unlimited") && config.no_correspondence {
+        Some(DeclineReason::Correspondence)
+    } else {
+        None
+    }
+}
+
+/// Returns the reason to decline a challenge because of its variant, if any.
+/// Only standard chess is played, possibly from a custom position.
+fn variant_decline_reason(variant: &str) -> Option<DeclineReason> {
+    if variant == "standard" || variant == "fromPosition" {
+        None
+    } else {
+        Some(DeclineReason::Variant)
+    }
+}
+
+/// The last known state of a game, to report when its task panics.
+#[derive(Debug, Clone, Default)]
+struct GameSnapshot {
+    /// The position (FEN).
+    fen: String,
+    /// The moves played, in pure coordinate notation.
+    moves: String,
+    /// The number of plies played.
+    plies: usize,
+}
+
+impl GameSnapshot {
+    fn new(brain: &Brain) -> Self {
+        Self {
+            fen: brain.board().to_fen(),
+            moves: brain
+                .moves()
+                .iter()
+                .map(Move::to_pure_notation)
+                .collect::<Vec<String>>()
+                .join(" "),
+            plies: brain.plies_played(),
+        }
+    }
+
+    /// Describes the state for the logs, e.g. `FEN: <fen>, moves: e2e4 e7e5`.
+    fn describe(&self) -> String {
+        format!("FEN: {}, moves: {}", self.fen, self.moves)
+    }
+}
+
+/// The state of the game to log along with an error, if enabled (see `log_fen_on_error`), e.g.
+/// ` (FEN: <fen>, moves: e2e4 e7e5)`; empty otherwise.
+fn error_context(config: &Config, brain: &Brain) -> String {
+    if config.log_fen_on_error {
+        format!(" ({})", GameSnapshot::new(brain).describe())
+    } else {
+        String::new()
+    }
+}
+
+/// Spawns the message loop of a game in its own task. If the loop panics, the panic is logged
+/// with the last known position, and the game is given up (see `give_up`) instead of hanging.
+fn spawn_message_loop<L: LichessClient>(
+    game_id: GameID,
+    mut recv: UnboundedReceiver<Message>,
+    lichess: Arc<L>,
+    config: Config,
+) {
+    let snapshot = Arc::new(Mutex::new(GameSnapshot::default()));
+    let task = {
+        let (game_id, lichess, config, snapshot) = (
+            game_id.clone(),
+            lichess.clone(),
+            config.clone(),
+            snapshot.clone(),
+        );
+        async move { message_loop(game_id, &mut recv, lichess, &config, &snapshot).await }
+    };
+    tokio::spawn(run_isolated(task, move |panic| async move {
+        let snapshot = snapshot
+            .lock()
+            .map(|snapshot| snapshot.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone());
+        error!(
+            "({}) Game task panicked: {} ({})",
+            game_id.id,
+            panic,
+            snapshot.describe()
+        );
+        give_up(lichess.as_ref(), &game_id.id, &config, snapshot.plies).await;
+    }));
+}
+
+/// Runs the task in its own tokio task, so that a panic doesn't take down the caller. If it
+/// panics, `recover` is called with the panic message.
+async fn run_isolated<T, F, R>(task: T, recover: F)
+where
+    T: std::future::Future<Output = ()> + Send + 'static,
+    F: FnOnce(String) -> R,
+    R: std::future::Future<Output = ()>,
+{
+    if let Err(e) = tokio::spawn(task).await {
+        if e.is_panic() {
+            let panic = e.into_panic();
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+            recover(message).await;
         }
     }
-    Ok(false)
 }
 
 /// Handles a new challenge by creating a new task with communication channel.
-async fn handle_new_challenge(
+async fn handle_new_challenge<L: LichessClient>(
     challenge: Challenge,
     world: &mut World,
-    lichess: Arc<Lichess>,
+    lichess: Arc<L>,
     config: &Config,
 ) -> anyhow::Result<()> {
     let game_id = challenge.id.clone();
@@ -306,43 +1557,58 @@ async fn handle_new_challenge(
         return Ok(());
     }
 
-    let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+    let limit = if world.has_reached_game_limit(&challenger, config.max_games_per_opponent) {
+        Some(DeclineReason::GameLimit)
+    } else if world.has_reached_pending_limit(&challenger, config.max_pending_challenges) {
+        Some(DeclineReason::PendingLimit)
+    } else {
+        None
+    };
+    if let Some(reason) = limit {
+        info!(
+            "Challenge declined: {} (other: {}, reason: {:?})",
+            game_id, challenger, reason
+        );
+        return decline_challenge(&game_id, reason, lichess).await;
+    }
+    world.challengers.insert(game_id.clone(), challenger);
+
+    let (sender, recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
     world.games.insert(game_id.clone(), sender.clone());
 
-    let game_id = GameID { id: game_id };
-    let config = config.clone();
-    tokio::spawn(async move { message_loop(game_id, &mut recv, lichess.clone(), &config).await });
+    spawn_message_loop(GameID { id: game_id }, recv, lichess, config.clone());
 
     sender
-        .send(Message::NewChallenge(challenge))
+        .send(Message::NewChallenge(Box::new(challenge)))
         .unwrap_or_else(|e| error!("Failed to dispatch NewChallenge: {:?}", e));
 
     Ok(())
 }
 
 /// Handles a new game by creating a new task with communication channel.
-async fn handle_new_game(
+async fn handle_new_game<L: LichessClient>(
     game_id: GameID,
     world: &mut World,
-    lichess: Arc<Lichess>,
+    lichess: Arc<L>,
     config: &Config,
 ) -> anyhow::Result<()> {
     let id = game_id.clone().id;
     let (config_a, config_b) = (config.clone(), config.clone());
     let (lichess_a, lichess_b) = (lichess.clone(), lichess.clone());
 
+    world.record_game_start(&id);
+
     // If there is already a challenge task, abort it
     if world.games.contains_key(&id) {
         abort_task(&id, world).await;
     }
 
-    let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+    let (sender, recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
     // Replaces any existing communication
     world.games.insert(id.clone(), sender.clone());
+    let opponents = world.opponents.clone();
 
-    tokio::spawn(
-        async move { message_loop(game_id, &mut recv, lichess_a.clone(), &config_a).await },
-    );
+    spawn_message_loop(game_id, recv, lichess_a, config_a);
 
     sender
         .send(Message::NewGame)
@@ -359,8 +1625,21 @@ async fn handle_new_game(
         match event_stream {
             Ok(mut event_stream) => {
                 while let Some(event) = event_stream.next().await {
-                    if let Ok(event) = event {
-                        dispatch_board_event(&sender, &id, event, &config_b).await;
+                    match event {
+                        Ok(event) => {
+                            // Remember the opponent, in case the game can't be exported for a
+                            // rematch
+                            if let BoardState::GameFull(game_full) = &event {
+                                let opponent =
+                                    Opponent::from_game_full(game_full, &config_b.username);
+                                opponents.lock().unwrap().insert(id.clone(), opponent);
+                            }
+                            dispatch_board_event(&sender, &id, event, &config_b).await;
+                        }
+                        // The client models require the moves: a state where they are null or
+                        // missing can't be deserialized, and is skipped. The next state catches up
+                        // on the moves (see `parse_moves`).
+                        Err(e) => warn!("({}) Ignored invalid board event: {:?}", id, e),
                     }
                 }
                 info!("Stopped receiving events from board loop: {}", &id);
@@ -392,61 +1671,112 @@ async fn dispatch_board_event(
             if state.state.status == "started" {
                 let is_white = is_bot_white(&state, &config.username);
                 let color = if is_white { Color::White } else { Color::Black };
+                let (stockfish_level, opponent_bot) =
+                    match if is_white { &state.black } else { &state.white } {
+                        Challengee::StockFish(stockfish) => (Some(stockfish.ai_level), false),
+                        Challengee::LightUser(user) => (None, user.title.as_deref() == Some("BOT")),
+                    };
 
+                let clocks = state
+                    .clock
+                    .as_ref()
+                    .map(|_| GameClocks::from_state(&state.state));
                 let initial_fen = state.initial_fen;
-                let moves = state
-                    .state
-                    .moves
-                    .split(' ')
-                    .filter(|s| !s.is_empty())
-                    .map(Move::from_pure_notation)
-                    .collect::<Vec<Move>>();
+                let moves = parse_moves(&state.state.moves);
 
                 sender
-                    .send(Message::SetBoard(initial_fen, moves, color))
+                    .send(Message::SetBoard(
+                        initial_fen,
+                        moves,
+                        color,
+                        clocks,
+                        stockfish_level,
+                        opponent_bot,
+                    ))
                     .unwrap_or(());
+
+                // A draw offer may be standing when the game is resumed
+                if let Some(color) = draw_offer(&state.state) {
+                    sender.send(Message::DrawOffer(color)).unwrap_or(());
+                }
             } else {
                 warn!("Unhandled board status: {}", state.state.status);
             }
         }
         BoardState::GameState(state) => {
             if state.status == "started" {
-                if state.bdraw {
-                    sender.send(Message::DrawOffer(Color::Black)).unwrap_or(());
-                } else if state.wdraw {
-                    sender.send(Message::DrawOffer(Color::White)).unwrap_or(());
+                if let Some(color) = draw_offer(&state) {
+                    sender.send(Message::DrawOffer(color)).unwrap_or(());
                 }
 
-                let moves = state
-                    .moves
-                    .split(' ')
-                    .filter(|s| !s.is_empty())
-                    .map(Move::from_pure_notation)
-                    .collect::<Vec<Move>>();
-
-                let last_move_color = if moves.len() % 2 == 1 {
-                    Color::White
-                } else {
-                    Color::Black
-                };
-
-                let last_move = moves.last().unwrap().to_owned();
+                // No move was played yet (or the state has none): there is no new move
+                let moves = parse_moves(&state.moves);
+                if moves.is_empty() {
+                    return;
+                }
+                let last_move_color = color_to_move(moves.len()).opposite();
 
                 // TODO: Handle draw
                 let game_over = state.winner.is_some();
 
                 sender
-                    .send(Message::Move(last_move, last_move_color, game_over))
+                    .send(Message::Move(
+                        moves,
+                        last_move_color,
+                        game_over,
+                        GameClocks::from_state(&state),
+                    ))
                     .unwrap_or(());
             } else {
-                warn!("Unhandled board status: {}", state.status);
+                sender
+                    .send(Message::GameOver(Box::new(state)))
+                    .unwrap_or(());
             }
         }
     }
 }
 
+/// The seed to vary the bot's opening moves in a game: the FNV-1a hash of the game ID, so that
+/// games vary but can be reproduced.
+fn game_seed(game_id: &str) -> u64 {
+    game_id.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Parses the moves of a game state (in UCI notation, separated by spaces). An empty or blank list
+/// means that no move was played yet.
+///
+/// States with null or missing moves never get here: they fail to deserialize in the client.
+fn parse_moves(moves: &str) -> Vec<Move> {
+    moves
+        .split_whitespace()
+        .map(Move::from_pure_notation)
+        .collect()
+}
+
+/// Returns the color to move after the given number of moves (plies) from the starting position.
+///
+/// The turn is derived from the full move list sent by Lichess, rather than tracked across
+/// events, so that it can't drift when an event is missed.
+fn color_to_move(plies: usize) -> Color {
+    Color::from_index(plies % 2)
+}
+
+/// Returns the color of the player offering a draw in the game state, if any.
+fn draw_offer(state: &GameState) -> Option<Color> {
+    if state.bdraw {
+        Some(Color::Black)
+    } else if state.wdraw {
+        Some(Color::White)
+    } else {
+        None
+    }
+}
+
 /// Dispatches the 'Abort' message to the game, closing it.
 async fn abort_task(game_id: &str, world: &mut World) {
+    world.challengers.remove(game_id);
     if let Some(sender) = world.games.get(game_id) {
         sender.send(Message::Abort).unwrap_or(());
         world.games.remove(game_id);
@@ -482,11 +1812,31 @@ async fn process_incoming_event(
         }
 
         Event::GameFinish { game } => {
+            match log_game_result(config, lichess.clone(), &game.id).await {
+                Ok(finished) => {
+                    // Let the task write its evaluation samples before it is closed
+                    if let (Some(sender), Some(score)) =
+                        (world.games.get(&game.id), white_score(&finished))
+                    {
+                        sender.send(Message::GameResult(score)).unwrap_or(());
+                    }
+                }
+                Err(e) => error!("Failed to log game result: {:?}", e),
+            }
             abort_task(&game.id, world).await;
+            world.games_played += 1;
+            let last_opponent = world.opponents.lock().unwrap().remove(&game.id);
             if config.rematch {
-                send_rematch(&config, lichess.clone(), &game.id)
-                    .await
-                    .with_context(|| "Failed to send rematch")?;
+                // Export retries sleep, so don't hold up the incoming events meanwhile
+                let config = config.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = send_rematch(&config, lichess, &game.id, last_opponent)
+                        .await
+                        .with_context(|| "Failed to send rematch")
+                    {
+                        error!("{:?}", e);
+                    }
+                });
             }
             Ok(())
         }
@@ -505,15 +1855,129 @@ fn is_bot_white(game_full: &GameFull, bot_username: &str) -> bool {
     }
 }
 
-pub async fn send_user_challenge(lichess: Arc<Lichess>, username: String) -> anyhow::Result<()> {
-    // TODO: Pass some of these from the CLI
-    let options = [
-        ("rated", "false"),
-        ("clock.limit", "300"),
-        ("clock.increment", "0"),
-        ("color", "random"),
-        ("variant", "standard"),
-    ];
+/// The maximum initial time of a challenge's clock, in seconds.
+const MAX_CHALLENGE_CLOCK_LIMIT: u32 = 10_800;
+
+/// The maximum increment of a challenge's clock, in seconds.
+const MAX_CHALLENGE_CLOCK_INCREMENT: u32 = 60;
+
+/// The options of the challenges sent by the bot (`--challenge`, `--stockfish`, and rematches).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeOptions {
+    /// Whether the game is rated.
+    pub rated: bool,
+    /// The initial time on the clock, in seconds.
+    pub clock_limit: u32,
+    /// The time added to the clock after each move, in seconds.
+    pub clock_increment: u32,
+    /// The bot's color: `white`, `black` or `random`.
+    pub color: String,
+}
+
+impl Default for ChallengeOptions {
+    fn default() -> Self {
+        Self {
+            rated: false,
+            clock_limit: 300,
+            clock_increment: 0,
+            color: "random".into(),
+        }
+    }
+}
+
+impl ChallengeOptions {
+    /// Builds the options from the arguments of the `start` command, with the defaults for the
+    /// missing ones. Combinations that Lichess would refuse are rejected.
+    pub fn from_args(args: &ArgMatches) -> anyhow::Result<Self> {
+        let defaults = Self::default();
+        let clock_limit = args
+            .value_of("clock-limit")
+            .map(|secs| secs.parse())
+            .transpose()
+            .with_context(|| "Invalid clock limit")?
+            .unwrap_or(defaults.clock_limit);
+        let clock_increment = args
+            .value_of("clock-increment")
+            .map(|secs| secs.parse())
+            .transpose()
+            .with_context(|| "Invalid clock increment")?
+            .unwrap_or(defaults.clock_increment);
+        let options = Self {
+            rated: args.is_present("rated"),
+            clock_limit,
+            clock_increment,
+            color: args
+                .value_of("color")
+                .map(str::to_lowercase)
+                .unwrap_or(defaults.color),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Checks that Lichess would accept a challenge with these options.
+    fn validate(&self) -> anyhow::Result<()> {
+        if !["white", "black", "random"].contains(&self.color.as_str()) {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid color: {} (use white, black or random)",
+                self.color
+            )));
+        }
+        if self.rated && self.color != "random" {
+            return Err(anyhow::Error::msg(
+                "Rated challenges must have a random color",
+            ));
+        }
+        if self.clock_limit > MAX_CHALLENGE_CLOCK_LIMIT {
+            return Err(anyhow::Error::msg(format!(
+                "Clock limit is too long: {}s (at most {}s)",
+                self.clock_limit, MAX_CHALLENGE_CLOCK_LIMIT
+            )));
+        }
+        if self.clock_increment > MAX_CHALLENGE_CLOCK_INCREMENT {
+            return Err(anyhow::Error::msg(format!(
+                "Clock increment is too long: {}s (at most {}s)",
+                self.clock_increment, MAX_CHALLENGE_CLOCK_INCREMENT
+            )));
+        }
+        if self.clock_limit == 0 && self.clock_increment == 0 {
+            return Err(anyhow::Error::msg(
+                "The clock needs a limit or an increment",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The form parameters of the challenge request.
+    fn to_form(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("rated", self.rated.to_string()),
+            ("clock.limit", self.clock_limit.to_string()),
+            ("clock.increment", self.clock_increment.to_string()),
+            ("color", self.color.clone()),
+            ("variant", "standard".into()),
+        ]
+    }
+
+    /// The form parameters of the challenge request to Stockfish. Games against Stockfish can't be
+    /// rated, so that option is left out.
+    fn to_stockfish_form(&self) -> Vec<(&'static str, String)> {
+        let mut form = self.to_form();
+        form.retain(|(key, _)| *key != "rated");
+        form
+    }
+}
+
+pub async fn send_user_challenge(
+    lichess: Arc<Lichess>,
+    username: String,
+    options: &ChallengeOptions,
+) -> anyhow::Result<()> {
+    let form = options.to_form();
+    let options = form
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect::<Vec<(&str, &str)>>();
     lichess
         .challenge_create(&username, Some(&options))
         .await
@@ -527,15 +1991,16 @@ pub async fn send_user_challenge(lichess: Arc<Lichess>, username: String) -> any
         })
 }
 
-pub async fn send_stockfish_challenge(lichess: Arc<Lichess>, level: u8) -> anyhow::Result<()> {
-    // TODO: Pass some of these from the CLI
-    let options = [
-        ("rated", "false"),
-        ("clock.limit", "300"),
-        ("clock.increment", "0"),
-        ("color", "random"),
-        ("variant", "standard"),
-    ];
+pub async fn send_stockfish_challenge(
+    lichess: Arc<Lichess>,
+    level: u8,
+    options: &ChallengeOptions,
+) -> anyhow::Result<()> {
+    let form = options.to_stockfish_form();
+    let options = form
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect::<Vec<(&str, &str)>>();
     lichess
         .challenge_stockfish(level, Some(&options))
         .await
@@ -548,40 +2013,210 @@ pub async fn send_stockfish_challenge(lichess: Arc<Lichess>, level: u8) -> anyho
         })
 }
 
+/// Sends a rematch to the opponent of the game. If the game can't be exported, even after
+/// retrying, the last known opponent of the game (if any) is challenged instead.
 pub async fn send_rematch(
     config: &Config,
     lichess: Arc<Lichess>,
     game_id: &str,
+    last_opponent: Option<Opponent>,
 ) -> anyhow::Result<()> {
-    let game = lichess
-        .export_one_game_json(&game_id, None)
-        .await
-        .with_context(|| "Failed to fetch game")?;
-    // Determine opponent
-    let opponent = match &game.players.white {
-        Player::Entity(e) => {
-            if e.user.as_ref().unwrap().username == config.username {
-                game.players.black
-            } else {
-                game.players.white
+    let mut game = Err(anyhow::Error::msg("The game was not exported"));
+    for attempt in 1..=REMATCH_EXPORT_ATTEMPTS {
+        game = lichess
+            .export_one_game_json(game_id, None)
+            .await
+            .with_context(|| "Failed to fetch game");
+        match &game {
+            Ok(_) => break,
+            Err(e) if attempt < REMATCH_EXPORT_ATTEMPTS => {
+                warn!("Retrying rematch export (attempt {}): {:?}", attempt, e);
+                tokio::time::sleep(REMATCH_RETRY_DELAY).await;
             }
+            Err(_) => {}
         }
-        Player::StockFish(_) => game.players.white,
-    };
-    match opponent {
-        Player::Entity(human) => {
-            send_user_challenge(lichess.clone(), human.user.unwrap().username).await
+    }
+
+    match rematch_opponent(game, last_opponent, &config.username)? {
+        Opponent::User(username) => {
+            send_user_challenge(lichess.clone(), username, &config.challenge).await
         }
-        Player::StockFish(stockfish) => {
+        Opponent::Stockfish(level) => {
             send_stockfish_challenge(
                 lichess.clone(),
-                (stockfish.ai_level + 1).clamp(stockfish.ai_level, config.stockfish_max),
+                rematch_stockfish_level(level, config.stockfish_max),
+                &config.challenge,
             )
             .await
         }
     }
 }
 
+/// The number of times to try exporting a game before sending a rematch.
+const REMATCH_EXPORT_ATTEMPTS: usize = 3;
+
+/// The delay between the attempts to export a game before sending a rematch.
+const REMATCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The opponent of a game, to send a rematch to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Opponent {
+    /// A Lichess user, by username.
+    User(String),
+    /// Stockfish, at the given level.
+    Stockfish(u8),
+}
+
+impl Opponent {
+    /// The opponent of the bot in an exported game, if it isn't anonymous.
+    fn from_game(game: &Game, bot_username: &str) -> Option<Self> {
+        match get_opponent(game, bot_username) {
+            Player::Entity(entity) => entity
+                .user
+                .as_ref()
+                .map(|user| Opponent::User(user.username.clone())),
+            Player::StockFish(stockfish) => Some(Opponent::Stockfish(stockfish.ai_level)),
+        }
+    }
+
+    /// The opponent of the bot in a game, from its full state.
+    fn from_game_full(game_full: &GameFull, bot_username: &str) -> Self {
+        let opponent = if is_bot_white(game_full, bot_username) {
+            &game_full.black
+        } else {
+            &game_full.white
+        };
+        match opponent {
+            Challengee::LightUser(user) => Opponent::User(user.username.clone()),
+            Challengee::StockFish(stockfish) => Opponent::Stockfish(stockfish.ai_level),
+        }
+    }
+}
+
+/// The Stockfish level of a rematch: one level up, but no higher than the maximum level (at least
+/// 1). The game may have been played above the maximum, e.g. when resumed with another range.
+fn rematch_stockfish_level(level: u8, max_level: u8) -> u8 {
+    level.saturating_add(1).min(max_level.max(1))
+}
+
+/// Picks the opponent to send a rematch to: the one of the exported game, or the last known one
+/// if the game couldn't be exported.
+fn rematch_opponent(
+    game: anyhow::Result<Game>,
+    last_opponent: Option<Opponent>,
+    bot_username: &str,
+) -> anyhow::Result<Opponent> {
+    match game {
+        Ok(game) => Opponent::from_game(&game, bot_username)
+            .or(last_opponent)
+            .with_context(|| "Cannot send a rematch to an anonymous opponent"),
+        Err(e) => match last_opponent {
+            Some(opponent) => {
+                warn!(
+                    "Sending rematch to the last known opponent ({:?}): {:?}",
+                    opponent, e
+                );
+                Ok(opponent)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Logs the result of a finished game, and appends it to the results file if configured.
+/// Returns the exported game.
+async fn log_game_result(
+    config: &Config,
+    lichess: Arc<Lichess>,
+    game_id: &str,
+) -> anyhow::Result<Game> {
+    let game = lichess
+        .export_one_game_json(game_id, None)
+        .await
+        .with_context(|| "Failed to fetch game")?;
+    let record = GameRecord::from_game(&game, &config.username);
+    info!("Game result: {}", record.to_line());
+    if let Some(outcome) = game_outcome(&game, &config.username) {
+        info!("Game outcome: {:?}", outcome);
+    }
+
+    if let Some(path) = &config.results_log {
+        append_to_file(path, &format!("{}\n", record.to_line()))
+            .await
+            .with_context(|| "Failed to write to results log")?;
+    }
+    Ok(game)
+}
+
+/// Appends the text to the file, creating it if needed.
+async fn append_to_file(path: &Path, text: &str) -> anyhow::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    file.write_all(text.as_bytes())
+        .await
+        .with_context(|| format!("Failed to write to file: {}", path.display()))
+}
+
+/// Determines the outcome of an exported game for the given bot username, replaying its moves
+/// (in SAN) to tell draws apart.
+fn game_outcome(game: &Game, bot_username: &str) -> Option<GameOutcome> {
+    let bot_color = if is_player(&game.players.white, bot_username) {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let initial = match game.initial_fen.as_deref() {
+        Some(fen) => Board::from_fen(fen).ok()?,
+        None => Board::default(),
+    };
+
+    let mut board = initial;
+    let mut moves = Vec::new();
+    for san in game.moves.as_deref().unwrap_or("").split_whitespace() {
+        let m = board.from_san(san).ok()?;
+        board.apply_move(m);
+        moves.push(m);
+    }
+    GameOutcome::from_status(
+        &game.status,
+        game.winner.as_deref(),
+        bot_color,
+        initial,
+        &moves,
+    )
+}
+
+/// White's score in a finished game (1 for a win, 0.5 for a draw, 0 for a loss), if it was played.
+fn white_score(game: &Game) -> Option<f32> {
+    match game.winner.as_deref() {
+        Some("white") => Some(1.0),
+        Some(_) => Some(0.0),
+        None if game.status == "aborted" || game.status == "noStart" => None,
+        None => Some(0.5),
+    }
+}
+
+/// Whether the player is the user with the given username.
+fn is_player(player: &Player, username: &str) -> bool {
+    match player {
+        Player::Entity(e) => e.user.as_ref().is_some_and(|u| u.username == username),
+        Player::StockFish(_) => false,
+    }
+}
+
+/// Determines the opponent of the given user in the game.
+fn get_opponent<'a>(game: &'a Game, username: &str) -> &'a Player {
+    if is_player(&game.players.white, username) {
+        &game.players.black
+    } else {
+        &game.players.white
+    }
+}
+
 pub async fn abort_games(lichess: Arc<Lichess>) -> anyhow::Result<()> {
     info!("Resigning all live games...");
     for game in lichess
@@ -599,6 +2234,22 @@ pub async fn abort_games(lichess: Arc<Lichess>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Spawns the tasks for games that are already in progress, so that the bot resumes playing them.
+async fn resume_ongoing_games<L: LichessClient>(
+    game_ids: Vec<String>,
+    world: &mut World,
+    lichess: Arc<L>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    for id in game_ids {
+        info!("Resuming game: {}", id);
+        handle_new_game(GameID { id }, world, lichess.clone(), config)
+            .await
+            .with_context(|| "Failed to resume game")?;
+    }
+    Ok(())
+}
+
 pub async fn start_bot(lichess: Arc<Lichess>, config: Config) -> anyhow::Result<()> {
     let mut event_stream = lichess
         .stream_incoming_events()
@@ -608,8 +2259,30 @@ pub async fn start_bot(lichess: Arc<Lichess>, config: Config) -> anyhow::Result<
     ascii_art(&config);
 
     let mut world = World::default();
-    while let Some(event) = event_stream.next().await {
-        if let Ok(event) = event {
+
+    // Games in progress aren't replayed by the event stream, so adopt them now
+    match lichess.get_ongoing_games(50).await {
+        Ok(games) => {
+            let game_ids = games.into_iter().map(|game| game.game_id).collect();
+            resume_ongoing_games(game_ids, &mut world, lichess.clone(), &config).await?;
+        }
+        Err(e) => warn!("Failed to get ongoing games: {:?}", e),
+    }
+
+    let started = Instant::now();
+    let mut heartbeat = config
+        .heartbeat
+        .map(|period| tokio::time::interval_at((started + period).into(), period));
+
+    loop {
+        let event = tokio::select! {
+            event = event_stream.next() => event,
+            _ = next_heartbeat(&mut heartbeat) => {
+                info!("{}", world.heartbeat_summary(started.elapsed()));
+                continue;
+            }
+        };
+        if let Some(Ok(event)) = event {
             if let Err(e) =
                 process_incoming_event(event, &mut world, lichess.clone(), &config).await
             {
@@ -624,6 +2297,16 @@ pub async fn start_bot(lichess: Arc<Lichess>, config: Config) -> anyhow::Result<
     Ok(())
 }
 
+/// Waits for the next tick of the heartbeat, or forever if it is disabled.
+async fn next_heartbeat(heartbeat: &mut Option<tokio::time::Interval>) {
+    match heartbeat {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 #[rustfmt::skip]
 fn ascii_art(config: &Config) {
     let challenge_message = if config.no_accept {
@@ -641,6 +2324,12 @@ fn ascii_art(config: &Config) {
     info!(r"");
 }
 
+/// Whether the account is a BOT account, which is required to use the bot endpoints (see
+/// `upgrade_bot_account`).
+pub fn is_bot_account(user: &User) -> bool {
+    user.title.as_deref() == Some("BOT")
+}
+
 pub async fn upgrade_bot_account(lichess: Arc<Lichess>, user: &User) -> anyhow::Result<()> {
     warn!("Upgrading account {} to a BOT account...", user.username);
     lichess
@@ -648,3 +2337,1198 @@ pub async fn upgrade_bot_account(lichess: Arc<Lichess>, user: &User) -> anyhow::
         .await
         .with_context(|| "Upgrade failed")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockLichess;
+
+    fn test_config() -> Config {
+        Config {
+            username: "poirebot".into(),
+            no_accept: false,
+            rematch: false,
+            stockfish: 0,
+            stockfish_max: 0,
+            following_only: false,
+            results_log: None,
+            accept_rated: true,
+            accept_casual: true,
+            no_correspondence: false,
+            no_clock_think_time: poirebot::time::DEFAULT_THINK_TIME,
+            think_time: None,
+            move_overhead: poirebot::time::DEFAULT_MOVE_OVERHEAD,
+            move_delay: (Duration::ZERO, Duration::ZERO),
+            max_games_per_opponent: None,
+            max_pending_challenges: 1,
+            eval_log: None,
+            stockfish_budget_factors: poirebot::time::STOCKFISH_BUDGET_FACTORS,
+            idle_abort_timeout: None,
+            heartbeat: None,
+            accepted_time_classes: TimeClass::ALL.to_vec(),
+            engine: EngineInfo::default(),
+            draw_policy: DrawPolicy::default(),
+            bot_opponent_contempt: None,
+            abort_on_error: false,
+            book: None,
+            ponder: false,
+            opening_extension: None,
+            max_nodes: None,
+            announce_opening: false,
+            log_fen_on_error: false,
+            challenge: ChallengeOptions::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_ongoing_games() {
+        let lichess = Arc::new(MockLichess::default());
+        let mut world = World::default();
+        let game_ids = vec!["q7ZvsdUF".to_owned(), "oRgrCk2z".to_owned()];
+
+        resume_ongoing_games(game_ids, &mut world, lichess.clone(), &test_config())
+            .await
+            .unwrap();
+
+        assert_eq!(world.games.len(), 2);
+        assert!(world.games.contains_key("q7ZvsdUF"));
+        assert!(world.games.contains_key("oRgrCk2z"));
+
+        // Each game streams its events in its own task
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while lichess.requests().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let mut requests = lichess.requests();
+        requests.sort();
+        assert_eq!(requests, vec!["stream oRgrCk2z", "stream q7ZvsdUF"]);
+    }
+
+    #[tokio::test]
+    async fn test_game_task_panic_gives_up() {
+        let resigned = Arc::new(Mutex::new(Vec::<String>::new()));
+        let give_up = |resigned: Arc<Mutex<Vec<String>>>| {
+            move |panic: String| async move { resigned.lock().unwrap().push(panic) }
+        };
+
+        run_isolated(
+            async { panic!("illegal move: e7e5") },
+            give_up(resigned.clone()),
+        )
+        .await;
+        assert_eq!(*resigned.lock().unwrap(), vec!["illegal move: e7e5"]);
+
+        // Games that end normally are left alone
+        run_isolated(async {}, give_up(resigned.clone())).await;
+        assert_eq!(resigned.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_draw_offer() {
+        let game_full = r#"{
+            "type": "gameFull",
+            "id": "q7ZvsdUF",
+            "rated": false,
+            "variant": {"key": "standard", "name": "Standard", "short": "Std"},
+            "clock": {"initial": 300000, "increment": 0},
+            "speed": "blitz",
+            "perf": {"name": "Blitz"},
+            "createdAt": 1620000000000,
+            "white": {"id": "poirebot", "name": "poirebot", "title": "BOT", "rating": 1500},
+            "black": {"id": "someone", "name": "someone", "rating": 1500},
+            "initialFen": "startpos",
+            "state": {
+                "type": "gameState",
+                "moves": "e2e4 e7e5",
+                "wtime": 300000,
+                "btime": 300000,
+                "winc": 0,
+                "binc": 0,
+                "wdraw": false,
+                "bdraw": true,
+                "status": "started"
+            }
+        }"#;
+        let board_state: BoardState = serde_json::from_str(game_full).unwrap();
+        if let BoardState::GameFull(game_full) = &board_state {
+            assert_eq!(
+                Opponent::from_game_full(game_full, "poirebot"),
+                Opponent::User("someone".into())
+            );
+        }
+        let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+
+        dispatch_board_event(&sender, "q7ZvsdUF", board_state, &test_config()).await;
+
+        assert!(matches!(
+            recv.recv().await,
+            Some(Message::SetBoard(_, _, Color::White, Some(_), None, false))
+        ));
+        assert!(matches!(
+            recv.recv().await,
+            Some(Message::DrawOffer(Color::Black))
+        ));
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        assert!(parse_moves("").is_empty());
+        assert!(parse_moves("  ").is_empty());
+        assert_eq!(
+            parse_moves("e2e4  e7e5 "),
+            vec![Move::from(("e2", "e4")), Move::from(("e7", "e5"))]
+        );
+    }
+
+    #[test]
+    fn test_state_with_null_or_missing_moves() {
+        // A known limitation of the client models, whose moves are required: these states are
+        // skipped by the board event stream
+        for moves in [r#""moves": null,"#, ""].iter() {
+            let game_state = format!(
+                r#"{{
+                    "type": "gameState",
+                    {}
+                    "wtime": 300000,
+                    "btime": 300000,
+                    "winc": 0,
+                    "binc": 0,
+                    "wdraw": false,
+                    "bdraw": true,
+                    "status": "started"
+                }}"#,
+                moves
+            );
+            assert!(
+                serde_json::from_str::<BoardState>(&game_state).is_err(),
+                "{:?}",
+                moves
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_without_moves() {
+        for moves in ["", " "].iter() {
+            let game_state = format!(
+                r#"{{
+                    "type": "gameState",
+                    "moves": "{}",
+                    "wtime": 300000,
+                    "btime": 300000,
+                    "winc": 0,
+                    "binc": 0,
+                    "wdraw": false,
+                    "bdraw": true,
+                    "status": "started"
+                }}"#,
+                moves
+            );
+            let board_state: BoardState = serde_json::from_str(&game_state).unwrap();
+            let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+
+            dispatch_board_event(&sender, "q7ZvsdUF", board_state, &test_config()).await;
+            drop(sender);
+
+            // The draw offer still comes through, without any move
+            assert!(matches!(
+                recv.recv().await,
+                Some(Message::DrawOffer(Color::Black))
+            ));
+            assert!(recv.recv().await.is_none(), "{:?}", moves);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_board_error_gives_up() {
+        let lichess = Arc::new(MockLichess::default());
+        let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+        sender
+            .send(Message::SetBoard(
+                "not a fen".into(),
+                vec![("e2", "e4").into(), ("e7", "e5").into()],
+                Color::White,
+                None,
+                None,
+                false,
+            ))
+            .unwrap();
+        drop(sender);
+
+        message_loop(
+            GameID {
+                id: "q7ZvsdUF".into(),
+            },
+            &mut recv,
+            lichess.clone(),
+            &test_config(),
+            &Mutex::new(GameSnapshot::default()),
+        )
+        .await;
+
+        // Too late to abort: the game is resigned rather than left to run out of time
+        assert_eq!(lichess.requests(), vec!["resign q7ZvsdUF"]);
+    }
+
+    #[tokio::test]
+    async fn test_move_before_set_board() {
+        let lichess = Arc::new(MockLichess::default());
+        let config = Config {
+            think_time: Some(Duration::from_millis(50)),
+            ..test_config()
+        };
+        let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+        let clocks = GameClocks {
+            white: Clock::from_millis(300_000, 0),
+            black: Clock::from_millis(300_000, 0),
+        };
+
+        // The opponent's move arrives before the board: the bot plays Black
+        sender
+            .send(Message::Move(
+                vec![("e2", "e4").into()],
+                Color::White,
+                false,
+                clocks,
+            ))
+            .unwrap();
+        sender
+            .send(Message::SetBoard(
+                "startpos".into(),
+                vec![],
+                Color::Black,
+                Some(clocks),
+                None,
+                false,
+            ))
+            .unwrap();
+        drop(sender);
+
+        let snapshot = Mutex::new(GameSnapshot::default());
+        message_loop(
+            GameID {
+                id: "q7ZvsdUF".into(),
+            },
+            &mut recv,
+            lichess.clone(),
+            &config,
+            &snapshot,
+        )
+        .await;
+
+        let moves = lichess
+            .requests()
+            .into_iter()
+            .filter(|request| request.starts_with("move q7ZvsdUF "))
+            .count();
+        assert_eq!(moves, 1, "{:?}", lichess.requests());
+        assert_eq!(snapshot.lock().unwrap().plies, 1);
+    }
+
+    #[tokio::test]
+    async fn test_opening_not_announced_again_on_resume() {
+        let lichess = Arc::new(MockLichess::default());
+        let config = Config {
+            think_time: Some(Duration::from_millis(50)),
+            announce_opening: true,
+            ..test_config()
+        };
+        let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<Message>();
+        let clocks = GameClocks {
+            white: Clock::from_millis(300_000, 0),
+            black: Clock::from_millis(300_000, 0),
+        };
+
+        // The game resumes after the Caro-Kann Defense left the book
+        let moves = parse_moves("e2e4 c7c6 d2d4");
+        sender
+            .send(Message::SetBoard(
+                "startpos".into(),
+                moves.clone(),
+                Color::White,
+                Some(clocks),
+                None,
+                false,
+            ))
+            .unwrap();
+        sender
+            .send(Message::Move(
+                [moves, parse_moves("d7d5")].concat(),
+                Color::Black,
+                false,
+                clocks,
+            ))
+            .unwrap();
+        drop(sender);
+
+        message_loop(
+            GameID {
+                id: "q7ZvsdUF".into(),
+            },
+            &mut recv,
+            lichess.clone(),
+            &config,
+            &Mutex::new(GameSnapshot::default()),
+        )
+        .await;
+
+        assert!(
+            lichess
+                .requests()
+                .iter()
+                .all(|request| !request.contains("Opening:")),
+            "{:?}",
+            lichess.requests()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_move_when_game_is_over() {
+        // No request is sent to Lichess, and no error is returned, which would resign the game
+        let lichess = Arc::new(MockLichess::default());
+        for fen in [
+            // Stalemate
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            // Checkmate
+            "R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1",
+        ]
+        .iter()
+        {
+            let mut brain = Brain::from_fen(fen, Color::Black).unwrap();
+            let result = find_and_send_move(
+                lichess.clone(),
+                "q7ZvsdUF",
+                &mut brain,
+                Duration::default(),
+                None,
+                &test_config().profile(false),
+                false,
+            )
+            .await;
+            assert_eq!(result.unwrap(), None, "{}", fen);
+            assert!(lichess.requests().is_empty(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_describe_last_move() {
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "c4f7"]
+            .iter()
+            .map(|m| Move::from_pure_notation(m))
+            .collect::<Vec<Move>>();
+        assert_eq!(
+            describe_last_move(Board::default(), &moves),
+            "Bxf7+ (capture, check)"
+        );
+        assert_eq!(
+            describe_last_move(Board::default(), &moves[..3]),
+            "Nf3 (quiet)"
+        );
+    }
+
+    #[test]
+    fn test_error_context() {
+        let mut brain = Brain::new(Board::default(), Color::White);
+        brain
+            .sync_moves(&[("e2", "e4").into(), ("e7", "e5").into()])
+            .unwrap();
+        assert_eq!(error_context(&test_config(), &brain), "");
+
+        let config = Config {
+            log_fen_on_error: true,
+            ..test_config()
+        };
+        assert_eq!(
+            error_context(&config, &brain),
+            format!(
+                " (FEN: {}, moves: e2e4 e7e5)",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+            )
+        );
+    }
+
+    #[test]
+    fn test_think_budget() {
+        let clocks = GameClocks {
+            white: Clock::from_millis(300_000, 0),
+            black: Clock::from_millis(60_000, 0),
+        };
+        let config = test_config();
+        assert_eq!(
+            think_budget(&config, Some(&clocks), Color::White, None),
+            Duration::from_millis(9_900)
+        );
+
+        // The fixed think time overrides the clock, and the Stockfish level
+        let config = Config {
+            think_time: Some(Duration::from_millis(1_500)),
+            ..test_config()
+        };
+        for color in [Color::White, Color::Black].iter() {
+            assert_eq!(
+                think_budget(&config, Some(&clocks), *color, Some(1)),
+                Duration::from_millis(1_500)
+            );
+        }
+        assert_eq!(
+            think_budget(&config, None, Color::White, None),
+            Duration::from_millis(1_500)
+        );
+
+        // Unless the clock runs out first
+        let clocks = GameClocks {
+            white: Clock::from_millis(1_000, 0),
+            black: Clock::from_millis(50, 0),
+        };
+        assert_eq!(
+            think_budget(&config, Some(&clocks), Color::White, None),
+            Duration::from_millis(1_000) - config.move_overhead
+        );
+        assert_eq!(
+            think_budget(&config, Some(&clocks), Color::Black, None),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_time_to_flag() {
+        let clocks = GameClocks {
+            white: Clock::from_millis(300_000, 0),
+            black: Clock::from_millis(50, 0),
+        };
+        let config = test_config();
+        assert_eq!(
+            time_to_flag(&config, Some(&clocks), Color::White),
+            Some(Duration::from_millis(300_000) - config.move_overhead)
+        );
+        assert_eq!(
+            time_to_flag(&config, Some(&clocks), Color::Black),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(time_to_flag(&config, None, Color::White), None);
+    }
+
+    #[test]
+    fn test_draw_policy_repetition() {
+        let policy = DrawPolicy {
+            claim_repetition: true,
+            ..DrawPolicy::default()
+        };
+        let board = Board::default();
+        let offer = |threefold, eval| policy.draw_reason(&board, threefold, eval, false).is_some();
+        assert!(offer(true, Evaluation::Score(0)));
+        assert!(offer(true, Evaluation::Score(-20)));
+        assert!(!offer(false, Evaluation::Score(0)));
+        assert!(!offer(true, Evaluation::Score(150)));
+        assert!(!offer(true, Evaluation::Score(-150)));
+        assert!(!offer(true, Evaluation::Best));
+
+        // Disabled
+        assert_eq!(
+            DrawPolicy::default().draw_reason(&board, true, Evaluation::Score(0), true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_draw_policy_combinations() {
+        let policy = DrawPolicy {
+            accept_draw_below_cp: Some(-100),
+            claim_fifty_move: true,
+            ..DrawPolicy::default()
+        };
+        let board = Board::default();
+        assert_eq!(
+            policy.draw_reason(&board, false, Evaluation::Score(-300), true),
+            Some("accepting the offer")
+        );
+        assert_eq!(
+            policy.draw_reason(&board, false, Evaluation::Worst, true),
+            Some("accepting the offer")
+        );
+        assert_eq!(
+            policy.draw_reason(&board, false, Evaluation::Score(-50), true),
+            None
+        );
+        // Not offered
+        assert_eq!(
+            policy.draw_reason(&board, false, Evaluation::Score(-300), false),
+            None
+        );
+        // Repetitions aren't claimed with this policy
+        assert_eq!(
+            policy.draw_reason(&board, true, Evaluation::Score(0), false),
+            None
+        );
+
+        let fifty = Board::from_fen("4k3/8/8/8/3r4/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(
+            policy.draw_reason(&fifty, false, Evaluation::Score(10), false),
+            Some("50-move rule")
+        );
+
+        // Rook against bishop, without pawns
+        let policy = DrawPolicy {
+            offer_draw_in_drawn_endgame: true,
+            ..DrawPolicy::default()
+        };
+        let drawn = Board::from_fen("4k3/8/8/8/3b4/8/8/R3K3 w - - 0 50").unwrap();
+        assert_eq!(
+            policy.draw_reason(&drawn, false, Evaluation::Score(0), false),
+            Some("drawn endgame")
+        );
+        assert_eq!(
+            policy.draw_reason(&fifty, false, Evaluation::Score(200), false),
+            None
+        );
+        let pawns = Board::from_fen("4k3/4p3/8/8/3b4/8/4P3/R3K3 w - - 0 50").unwrap();
+        assert_eq!(
+            policy.draw_reason(&pawns, false, Evaluation::Score(0), false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_should_abort_on_error() {
+        let config = Config {
+            abort_on_error: true,
+            ..test_config()
+        };
+        assert!(should_abort_on_error(&config, 0));
+        assert!(should_abort_on_error(&config, 1));
+        assert!(!should_abort_on_error(&config, 2));
+        assert!(!should_abort_on_error(&config, 40));
+
+        // Disabled: always resign
+        assert!(!should_abort_on_error(&test_config(), 0));
+    }
+
+    #[test]
+    fn test_game_seed() {
+        let first_move = |game_id: &str| {
+            Brain::new(Board::default(), Color::White)
+                .with_opening_seed(game_seed(game_id))
+                .search_at(2)
+                .unwrap()
+                .best
+        };
+
+        // The same game always starts the same way, but games vary
+        assert_eq!(first_move("q7ZvsdUF"), first_move("q7ZvsdUF"));
+        let first_moves = ["q7ZvsdUF", "oRgrCk2z", "abcdefgh", "12345678", "zzzzzzzz"]
+            .iter()
+            .map(|game_id| first_move(game_id))
+            .collect::<Vec<Move>>();
+        assert!(first_moves.iter().any(|m| *m != first_moves[0]));
+    }
+
+    #[test]
+    fn test_color_to_move() {
+        assert_eq!(color_to_move(0), Color::White);
+        assert_eq!(color_to_move(1), Color::Black);
+        assert_eq!(color_to_move(2), Color::White);
+        assert_eq!(color_to_move(41), Color::Black);
+
+        // Each bot color gets every other ply
+        for bot_color in [Color::White, Color::Black].iter().copied() {
+            let own_turns = (0..10)
+                .filter(|plies| color_to_move(*plies) == bot_color)
+                .collect::<Vec<usize>>();
+            let first = bot_color.index();
+            assert_eq!(own_turns, (first..10).step_by(2).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let timeout = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut watchdog = Watchdog::default();
+
+        // Not started yet
+        assert!(!watchdog.has_expired(start + Duration::from_secs(3600), timeout));
+
+        watchdog.feed(start);
+        assert!(!watchdog.has_expired(start, timeout));
+        assert!(!watchdog.has_expired(start + Duration::from_secs(60), timeout));
+        assert!(watchdog.has_expired(start + Duration::from_secs(61), timeout));
+
+        // Activity resets the timeout
+        watchdog.feed(start + Duration::from_secs(50));
+        assert!(!watchdog.has_expired(start + Duration::from_secs(100), timeout));
+        assert!(watchdog.has_expired(start + Duration::from_secs(111), timeout));
+    }
+
+    #[test]
+    fn test_challenge_options_form() {
+        let options = ChallengeOptions {
+            rated: true,
+            clock_limit: 600,
+            clock_increment: 5,
+            color: "random".into(),
+        };
+        assert!(options.validate().is_ok());
+        assert_eq!(
+            options.to_form(),
+            vec![
+                ("rated", "true".to_string()),
+                ("clock.limit", "600".to_string()),
+                ("clock.increment", "5".to_string()),
+                ("color", "random".to_string()),
+                ("variant", "standard".to_string()),
+            ]
+        );
+        assert_eq!(
+            ChallengeOptions::default().to_form()[..4],
+            [
+                ("rated", "false".to_string()),
+                ("clock.limit", "300".to_string()),
+                ("clock.increment", "0".to_string()),
+                ("color", "random".to_string()),
+            ]
+        );
+
+        // Stockfish challenges have the same clock and color, and are never rated
+        assert_eq!(
+            options.to_stockfish_form(),
+            vec![
+                ("clock.limit", "600".to_string()),
+                ("clock.increment", "5".to_string()),
+                ("color", "random".to_string()),
+                ("variant", "standard".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_delay() {
+        let range = (Duration::from_millis(200), Duration::from_millis(800));
+        let delays = (0..100)
+            .map(|seed| move_delay(range, seed, Duration::from_secs(5)))
+            .collect::<Vec<Duration>>();
+        assert!(delays
+            .iter()
+            .all(|delay| *delay >= Duration::from_millis(200)
+                && *delay <= Duration::from_millis(800)));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+
+        // Never over the remaining budget
+        for seed in 0..100 {
+            assert!(
+                move_delay(range, seed, Duration::from_millis(50)) <= Duration::from_millis(50)
+            );
+            assert_eq!(move_delay(range, seed, Duration::ZERO), Duration::ZERO);
+        }
+
+        // Disabled by default
+        assert_eq!(
+            move_delay(test_config().move_delay, 42, Duration::from_secs(5)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_bot_opponent_profile() {
+        let config = Config {
+            move_delay: (Duration::from_millis(200), Duration::from_millis(800)),
+            draw_policy: DrawPolicy {
+                accept_draw_below_cp: Some(-100),
+                claim_repetition: true,
+                ..DrawPolicy::default()
+            },
+            ..test_config()
+        };
+        let human = GameProfile {
+            chat: true,
+            move_delay: config.move_delay,
+            draw_policy: config.draw_policy.clone(),
+        };
+
+        // Without a contempt, bots are treated like humans
+        assert_eq!(config.profile(false), human);
+        assert_eq!(config.profile(true), human);
+
+        let config = Config {
+            bot_opponent_contempt: Some(50),
+            ..config
+        };
+        assert_eq!(config.profile(false), human);
+        let bot = config.profile(true);
+        assert!(!bot.chat);
+        assert_eq!(bot.move_delay, (Duration::ZERO, Duration::ZERO));
+
+        // Draws are only taken when clearly worse
+        let board = Board::default();
+        let offer = |eval: i32, offered: bool| {
+            bot.draw_policy
+                .draw_reason(&board, true, Evaluation::Score(eval), offered)
+                .is_some()
+        };
+        assert!(!offer(0, false));
+        assert!(!offer(-40, true));
+        assert!(offer(-60, true));
+    }
+
+    #[test]
+    fn test_heartbeat_summary() {
+        let mut world = World::default();
+        assert_eq!(
+            world.heartbeat_summary(Duration::from_secs(59)),
+            "Alive for 0h 00m 59s: 0 active games, 0 games played"
+        );
+
+        // Unresolved challenges aren't active games
+        for id in ["game1", "game2", "challenge"].iter() {
+            let (sender, _) = tokio::sync::mpsc::unbounded_channel::<Message>();
+            world.games.insert(id.to_string(), sender);
+        }
+        world
+            .challengers
+            .insert("challenge".into(), "someone".into());
+        world.games_played = 5;
+        assert_eq!(
+            world.heartbeat_summary(Duration::from_secs(3723)),
+            "Alive for 1h 02m 03s: 2 active games, 5 games played"
+        );
+    }
+
+    #[test]
+    fn test_max_games_per_opponent() {
+        let mut world = World::default();
+        for i in 0..3 {
+            let game_id = format!("game{}", i);
+            assert!(!world.has_reached_game_limit("spammer", Some(3)));
+            world.challengers.insert(game_id.clone(), "spammer".into());
+            world.record_game_start(&game_id);
+        }
+
+        // The 4th challenge is declined, but another user can still play
+        assert!(world.has_reached_game_limit("spammer", Some(3)));
+        assert!(!world.has_reached_game_limit("someone", Some(3)));
+        assert!(!world.has_reached_game_limit("spammer", None));
+
+        // Games started without an incoming challenge (e.g. rematches sent by the bot) don't count
+        world.record_game_start("unknown");
+        assert_eq!(world.games_per_opponent.values().sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn test_max_pending_challenges() {
+        let mut world = World::default();
+        assert!(!world.has_reached_pending_limit("spammer", 1));
+        world.challengers.insert("game0".into(), "spammer".into());
+
+        // The second challenge is declined while the first is unresolved
+        assert!(world.has_reached_pending_limit("spammer", 1));
+        assert!(!world.has_reached_pending_limit("spammer", 2));
+        assert!(!world.has_reached_pending_limit("someone", 1));
+
+        // Once the first challenge starts a game, another one can be sent
+        world.record_game_start("game0");
+        assert!(!world.has_reached_pending_limit("spammer", 1));
+    }
+
+    #[test]
+    fn test_rated_mode_decline_reason() {
+        let config = |accept_rated, accept_casual| Config {
+            accept_rated,
+            accept_casual,
+            ..test_config()
+        };
+
+        let both = config(true, true);
+        assert_eq!(rated_mode_decline_reason(true, &both), None);
+        assert_eq!(rated_mode_decline_reason(false, &both), None);
+
+        let rated_only = config(true, false);
+        assert_eq!(rated_mode_decline_reason(true, &rated_only), None);
+        assert!(rated_mode_decline_reason(false, &rated_only).is_some());
+
+        let casual_only = config(false, true);
+        assert!(rated_mode_decline_reason(true, &casual_only).is_some());
+        assert_eq!(rated_mode_decline_reason(false, &casual_only), None);
+
+        let neither = config(false, false);
+        assert!(rated_mode_decline_reason(true, &neither).is_some());
+        assert!(rated_mode_decline_reason(false, &neither).is_some());
+    }
+
+    #[test]
+    fn test_speed_decline_reason() {
+        let config = test_config();
+        assert_eq!(speed_decline_reason("blitz", &config), None);
+        assert_eq!(speed_decline_reason("correspondence", &config), None);
+        assert_eq!(speed_decline_reason("unlimited", &config), None);
+
+        let config = Config {
+            no_correspondence: true,
+            ..test_config()
+        };
+        assert_eq!(speed_decline_reason("blitz", &config), None);
+        assert!(speed_decline_reason("correspondence", &config).is_some());
+        assert!(speed_decline_reason("unlimited", &config).is_some());
+    }
+
+    #[test]
+    fn test_time_class_decline_reason() {
+        let time_control = |limit, increment| TimeControl {
+            initial: None,
+            increment: Some(increment),
+            total_time: None,
+            limit: Some(limit),
+            days_per_turn: None,
+            show: None,
+            r#type: Some("clock".into()),
+        };
+        assert_eq!(
+            challenge_time_class(&time_control(60, 1)),
+            Some(TimeClass::Bullet)
+        );
+        assert_eq!(
+            challenge_time_class(&time_control(300, 3)),
+            Some(TimeClass::Blitz)
+        );
+        let correspondence = TimeControl {
+            r#type: Some("correspondence".into()),
+            days_per_turn: Some(2),
+            ..time_control(0, 0)
+        };
+        assert_eq!(challenge_time_class(&correspondence), None);
+
+        let config = test_config();
+        assert_eq!(
+            time_class_decline_reason(Some(TimeClass::Bullet), &config),
+            None
+        );
+
+        let config = Config {
+            accepted_time_classes: vec![TimeClass::Blitz, TimeClass::Rapid],
+            ..test_config()
+        };
+        assert!(time_class_decline_reason(Some(TimeClass::Bullet), &config).is_some());
+        assert!(time_class_decline_reason(Some(TimeClass::Classical), &config).is_some());
+        assert_eq!(
+            time_class_decline_reason(Some(TimeClass::Blitz), &config),
+            None
+        );
+        assert_eq!(time_class_decline_reason(None, &config), None);
+    }
+
+    fn challenge(speed: &str, limit: u32, variant: &str, rated: bool) -> Challenge {
+        serde_json::from_value(serde_json::json!({
+            "id": "abcd1234",
+            "url": "https://lichess.org/abcd1234",
+            "color": "random",
+            "timeControl": { "type": "clock", "limit": limit, "increment": 0 },
+            "variant": { "key": variant, "name": variant },
+            "challenger": { "name": "someone" },
+            "perf": { "name": "Blitz" },
+            "rated": rated,
+            "speed": speed,
+            "status": "created",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_bot_account() {
+        let user = |title: Option<&str>| -> User {
+            serde_json::from_value(serde_json::json!({
+                "id": "poirebot",
+                "username": "poirebot",
+                "online": true,
+                "perfs": {},
+                "createdAt": 1620000000000u64,
+                "seenAt": 1620000000000u64,
+                "playTime": { "total": 0, "tv": 0 },
+                "title": title,
+            }))
+            .unwrap()
+        };
+        assert!(is_bot_account(&user(Some("BOT"))));
+        assert!(!is_bot_account(&user(None)));
+        assert!(!is_bot_account(&user(Some("GM"))));
+    }
+
+    #[test]
+    fn test_decide_challenge() {
+        let blitz = challenge("blitz", 300, "standard", true);
+        let decline = |reason| ChallengeDecision::Decline(reason);
+
+        let config = test_config();
+        assert_eq!(decide_challenge(&blitz, &config), ChallengeDecision::Accept);
+        assert_eq!(
+            decide_challenge(&challenge("blitz", 300, "fromPosition", false), &config),
+            ChallengeDecision::Accept
+        );
+        assert_eq!(
+            decide_challenge(&challenge("blitz", 300, "chess960", true), &config),
+            decline(DeclineReason::Variant)
+        );
+
+        let mut correspondence = challenge("correspondence", 0, "standard", true);
+        correspondence.time_control.r#type = Some("correspondence".into());
+        assert_eq!(
+            decide_challenge(&correspondence, &config),
+            ChallengeDecision::Accept
+        );
+        let declining = Config {
+            no_correspondence: true,
+            ..test_config()
+        };
+        assert_eq!(
+            decide_challenge(&correspondence, &declining),
+            decline(DeclineReason::Correspondence)
+        );
+
+        let config = Config {
+            no_accept: true,
+            ..test_config()
+        };
+        assert_eq!(
+            decide_challenge(&blitz, &config),
+            decline(DeclineReason::NoAccept)
+        );
+
+        let config = Config {
+            accepted_time_classes: vec![TimeClass::Rapid],
+            ..test_config()
+        };
+        assert_eq!(
+            decide_challenge(&blitz, &config),
+            decline(DeclineReason::TimeControl)
+        );
+
+        let config = Config {
+            accept_rated: false,
+            ..test_config()
+        };
+        assert_eq!(
+            decide_challenge(&blitz, &config),
+            decline(DeclineReason::Rated)
+        );
+        let config = Config {
+            accept_casual: false,
+            ..test_config()
+        };
+        assert_eq!(
+            decide_challenge(&challenge("blitz", 300, "standard", false), &config),
+            decline(DeclineReason::Casual)
+        );
+
+        // The following check is done separately
+        let config = Config {
+            following_only: true,
+            ..test_config()
+        };
+        assert_eq!(decide_challenge(&blitz, &config), ChallengeDecision::Accept);
+    }
+
+    #[tokio::test]
+    async fn test_accept_or_decline_challenge() {
+        let blitz = challenge("blitz", 300, "standard", true);
+
+        let lichess = Arc::new(MockLichess::default());
+        let decision = accept_or_decline_challenge(&blitz, lichess.clone(), &test_config())
+            .await
+            .unwrap();
+        assert_eq!(decision, ChallengeDecision::Accept);
+        assert_eq!(lichess.requests(), vec!["accept abcd1234"]);
+
+        let lichess = Arc::new(MockLichess::default());
+        let chess960 = challenge("blitz", 300, "chess960", true);
+        accept_or_decline_challenge(&chess960, lichess.clone(), &test_config())
+            .await
+            .unwrap();
+        assert_eq!(
+            lichess.requests(),
+            vec![format!(
+                "decline abcd1234 {}",
+                DeclineReason::Variant.message()
+            )]
+        );
+
+        // Only the users followed by the bot are accepted
+        let config = Config {
+            following_only: true,
+            ..test_config()
+        };
+        let lichess = Arc::new(MockLichess::default());
+        let decision = accept_or_decline_challenge(&blitz, lichess.clone(), &config)
+            .await
+            .unwrap();
+        assert_eq!(
+            decision,
+            ChallengeDecision::Decline(DeclineReason::NotFollowing)
+        );
+
+        let lichess = Arc::new(MockLichess {
+            followings: vec!["someone".into()],
+            ..MockLichess::default()
+        });
+        let decision = accept_or_decline_challenge(&blitz, lichess.clone(), &config)
+            .await
+            .unwrap();
+        assert_eq!(decision, ChallengeDecision::Accept);
+        assert_eq!(lichess.requests(), vec!["accept abcd1234"]);
+    }
+
+    #[test]
+    fn test_game_outcome() {
+        let state = |moves: &str, status: &str, winner: Option<&str>| GameState {
+            r#type: Some("gameState".into()),
+            moves: moves.into(),
+            wtime: 0,
+            btime: 0,
+            winc: 0,
+            binc: 0,
+            wdraw: false,
+            bdraw: false,
+            status: status.into(),
+            winner: winner.map(String::from),
+            rematch: None,
+        };
+
+        // Fool's mate
+        let mate = state("f2f3 e7e5 g2g4 d8h4", "mate", Some("black"));
+        assert_eq!(
+            GameOutcome::from_state(&mate, Color::Black, Board::default()),
+            Some(GameOutcome::WinByMate)
+        );
+        assert_eq!(
+            GameOutcome::from_state(&mate, Color::White, Board::default()),
+            Some(GameOutcome::LossByMate)
+        );
+
+        let resign = state("e2e4 e7e5", "resign", Some("white"));
+        assert_eq!(
+            GameOutcome::from_state(&resign, Color::White, Board::default()),
+            Some(GameOutcome::WinByResign)
+        );
+        let timeout = state("e2e4 e7e5", "outoftime", Some("white"));
+        assert_eq!(
+            GameOutcome::from_state(&timeout, Color::Black, Board::default()),
+            Some(GameOutcome::LossByTimeout)
+        );
+
+        let agreement = state("e2e4 e7e5", "draw", None);
+        assert_eq!(
+            GameOutcome::from_state(&agreement, Color::White, Board::default()),
+            Some(GameOutcome::DrawByAgreement)
+        );
+        let repetition = state("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8", "draw", None);
+        assert_eq!(
+            GameOutcome::from_state(&repetition, Color::White, Board::default()),
+            Some(GameOutcome::DrawByRepetition)
+        );
+        assert_eq!(
+            GameOutcome::from_state(
+                &state("e2e4", "aborted", None),
+                Color::White,
+                Board::default()
+            ),
+            Some(GameOutcome::Aborted)
+        );
+        assert_eq!(
+            GameOutcome::from_state(
+                &state("e2e4", "started", None),
+                Color::White,
+                Board::default()
+            ),
+            None
+        );
+
+        // Draws by the rules, from other positions
+        let bare_kings = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            GameOutcome::from_status("draw", None, Color::White, bare_kings, &[]),
+            Some(GameOutcome::DrawByInsufficient)
+        );
+        let fifty = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(
+            GameOutcome::from_status("draw", None, Color::White, fifty, &[("e1", "e2").into()]),
+            Some(GameOutcome::DrawBy50)
+        );
+        let stalemate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            GameOutcome::from_status("stalemate", None, Color::White, stalemate, &[]),
+            Some(GameOutcome::DrawByStalemate)
+        );
+    }
+
+    #[test]
+    fn test_rematch_opponent_fallback() {
+        let export_failure = || Err(anyhow::Error::msg("Failed to fetch game"));
+
+        let last_opponent = Some(Opponent::User("someone".into()));
+        assert_eq!(
+            rematch_opponent(export_failure(), last_opponent, "poirebot").unwrap(),
+            Opponent::User("someone".into())
+        );
+        let last_opponent = Some(Opponent::Stockfish(3));
+        assert_eq!(
+            rematch_opponent(export_failure(), last_opponent, "poirebot").unwrap(),
+            Opponent::Stockfish(3)
+        );
+        assert!(rematch_opponent(export_failure(), None, "poirebot").is_err());
+    }
+
+    #[test]
+    fn test_rematch_stockfish_level() {
+        assert_eq!(rematch_stockfish_level(2, 5), 3);
+        assert_eq!(rematch_stockfish_level(5, 5), 5);
+        // Played above the maximum level, or without one
+        assert_eq!(rematch_stockfish_level(7, 3), 3);
+        assert_eq!(rematch_stockfish_level(4, 0), 1);
+    }
+
+    #[test]
+    fn test_game_record_to_line() {
+        let record = GameRecord {
+            game_id: "q7ZvsdUF".into(),
+            opponent: "someone".into(),
+            opponent_rating: Some(1500),
+            time_control: "300+0".into(),
+            result: "win".into(),
+            moves: 42,
+        };
+        assert_eq!(record.to_line(), "q7ZvsdUF\tsomeone\t1500\t300+0\twin\t42");
+
+        let record = GameRecord {
+            opponent: "Stockfish level 3".into(),
+            opponent_rating: None,
+            time_control: "-".into(),
+            result: "draw".into(),
+            ..record
+        };
+        assert_eq!(
+            record.to_line(),
+            "q7ZvsdUF\tStockfish level 3\t-\t-\tdraw\t42"
+        );
+    }
+
+    #[test]
+    fn test_eval_sample_to_csv_row() {
+        let board = Board::default();
+        let sample = EvalSample::new(&board, Color::White, Evaluation::Score(35)).unwrap();
+        assert_eq!(
+            sample.to_csv_row(1.0),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1,35,1"
+        );
+
+        // Always from White's perspective
+        let sample = EvalSample::new(&board, Color::Black, Evaluation::Score(35)).unwrap();
+        assert_eq!(
+            sample.to_csv_row(0.5),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1,-35,0.5"
+        );
+
+        assert_eq!(
+            EvalSample::new(&board, Color::White, Evaluation::Best),
+            None
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::licorice::client::Lichess;
+use crate::licorice::errors::LichessError;
+use crate::licorice::models::board::BoardState;
+
+/// The time to wait before retrying a rate-limited request, when Lichess doesn't say. Lichess asks
+/// to wait a full minute after a 429.
+///
+/// Ref: https://lichess.org/page/api-tips
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The number of times a rate-limited request is retried before giving up.
+const RATE_LIMIT_RETRIES: usize = 2;
+
+/// The requests to Lichess made while handling games and challenges.
+///
+/// This is implemented by the Lichess client, and by mocks in tests so that the handlers can run
+/// without network.
+pub trait LichessClient: Send + Sync + 'static {
+    /// Plays the bot's move (in UCI notation), optionally offering a draw.
+    fn make_a_bot_move(
+        &self,
+        game_id: &str,
+        uci: &str,
+        offering_draw: bool,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Writes a message in the chat of a game (`player` or `spectator` room).
+    fn write_in_bot_chat(
+        &self,
+        game_id: &str,
+        room: &str,
+        text: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Aborts a game.
+    fn abort_bot_game(&self, game_id: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Resigns a game.
+    fn resign_bot_game(&self, game_id: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Accepts an incoming challenge.
+    fn challenge_accept(
+        &self,
+        challenge_id: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Declines an incoming challenge, with a message for the challenger.
+    fn challenge_decline(
+        &self,
+        challenge_id: &str,
+        reason: Option<&str>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Whether the bot account follows the given user.
+    fn is_following(
+        &self,
+        bot_username: &str,
+        username: &str,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    /// Streams the events of a game played by the bot: the full game first, then its states and
+    /// chat lines.
+    fn stream_bot_game_state(
+        &self,
+        game_id: &str,
+    ) -> impl Future<
+        Output = anyhow::Result<
+            impl Stream<Item = Result<BoardState, LichessError>> + Send + Unpin + 'static,
+        >,
+    > + Send;
+}
+
+impl LichessClient for Lichess {
+    async fn make_a_bot_move(
+        &self,
+        game_id: &str,
+        uci: &str,
+        offering_draw: bool,
+    ) -> anyhow::Result<()> {
+        Ok(Lichess::make_a_bot_move(self, game_id, uci, offering_draw).await?)
+    }
+
+    async fn write_in_bot_chat(&self, game_id: &str, room: &str, text: &str) -> anyhow::Result<()> {
+        Ok(Lichess::write_in_bot_chat(self, game_id, room, text).await?)
+    }
+
+    async fn abort_bot_game(&self, game_id: &str) -> anyhow::Result<()> {
+        Ok(Lichess::abort_bot_game(self, game_id).await?)
+    }
+
+    async fn resign_bot_game(&self, game_id: &str) -> anyhow::Result<()> {
+        Ok(Lichess::resign_bot_game(self, game_id).await?)
+    }
+
+    async fn challenge_accept(&self, challenge_id: &str) -> anyhow::Result<()> {
+        Ok(Lichess::challenge_accept(self, challenge_id).await?)
+    }
+
+    async fn challenge_decline(
+        &self,
+        challenge_id: &str,
+        reason: Option<&str>,
+    ) -> anyhow::Result<()> {
+        Ok(Lichess::challenge_decline(self, challenge_id, reason).await?)
+    }
+
+    async fn is_following(&self, bot_username: &str, username: &str) -> anyhow::Result<bool> {
+        let mut stream = self
+            .get_followings(bot_username)
+            .await
+            .with_context(|| "Failed to get followings")?;
+        while let Some(user) = stream.next().await {
+            if let Ok(user) = user {
+                if user.username == username {
+                    return Ok(true);
+                } else {
+                    continue;
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn stream_bot_game_state(
+        &self,
+        game_id: &str,
+    ) -> anyhow::Result<impl Stream<Item = Result<BoardState, LichessError>> + Send + Unpin + 'static>
+    {
+        Ok(Lichess::stream_bot_game_state(self, game_id).await?)
+    }
+}
+
+/// If the error is a rate limit (HTTP 429), returns the time to wait before retrying: the delay
+/// advised by Lichess, or `RATE_LIMIT_BACKOFF`.
+pub fn rate_limit_backoff(error: &anyhow::Error) -> Option<Duration> {
+    match error.downcast_ref::<LichessError>() {
+        Some(LichessError::RateLimited(retry_after)) => Some(
+            retry_after
+                .map(|seconds| Duration::from_secs(seconds as u64))
+                .unwrap_or(RATE_LIMIT_BACKOFF),
+        ),
+        _ => None,
+    }
+}
+
+/// Sends the request, and retries it after backing off when it is rate limited (see
+/// `rate_limit_backoff`), up to `RATE_LIMIT_RETRIES` times. Other errors are returned right away.
+pub async fn retry_rate_limited<T, F, R>(request: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> R,
+    R: Future<Output = anyhow::Result<T>>,
+{
+    retry_rate_limited_for(None, request).await
+}
+
+/// Like `retry_rate_limited`, but only waits for as long as the given time: each backoff is cut
+/// short to the time left, and the request is no longer retried once it has run out. This is for
+/// the requests racing against the bot's clock, like its moves.
+pub async fn retry_rate_limited_within<T, F, R>(time: Duration, request: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> R,
+    R: Future<Output = anyhow::Result<T>>,
+{
+    retry_rate_limited_for(Some(time), request).await
+}
+
+/// Retries the rate-limited request (see `retry_rate_limited_within`), without limit on the time
+/// if there is none.
+async fn retry_rate_limited_for<T, F, R>(
+    time: Option<Duration>,
+    mut request: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> R,
+    R: Future<Output = anyhow::Result<T>>,
+{
+    let start = Instant::now();
+    let mut retries = 0;
+    loop {
+        match request().await {
+            Err(e) if retries < RATE_LIMIT_RETRIES => {
+                let backoff = match rate_limit_backoff(&e) {
+                    Some(backoff) => backoff,
+                    None => return Err(e),
+                };
+                let backoff = match time.map(|time| time.saturating_sub(start.elapsed())) {
+                    Some(left) if left.is_zero() => {
+                        warn!("Rate limited by Lichess, and out of time to retry");
+                        return Err(e);
+                    }
+                    Some(left) => backoff.min(left),
+                    None => backoff,
+                };
+                warn!("Rate limited by Lichess, retrying in {:?}", backoff);
+                tokio::time::sleep(backoff).await;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A client that records the requests instead of sending them, for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockLichess {
+    /// The requests, e.g. `"accept abcd1234"`.
+    pub requests: std::sync::Mutex<Vec<String>>,
+    /// The users followed by the bot.
+    pub followings: Vec<String>,
+    /// The number of upcoming move requests to reject with a rate limit (HTTP 429).
+    pub rate_limited_moves: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl MockLichess {
+    fn record(&self, request: String) -> anyhow::Result<()> {
+        self.requests.lock().unwrap().push(request);
+        Ok(())
+    }
+
+    /// The requests recorded so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl LichessClient for MockLichess {
+    async fn make_a_bot_move(
+        &self,
+        game_id: &str,
+        uci: &str,
+        offering_draw: bool,
+    ) -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        let limited =
+            self.rate_limited_moves
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if limited.is_ok() {
+            return Err(LichessError::RateLimited(Some(0)).into());
+        }
+        self.record(format!("move {} {} {}", game_id, uci, offering_draw))
+    }
+
+    async fn write_in_bot_chat(&self, game_id: &str, room: &str, text: &str) -> anyhow::Result<()> {
+        self.record(format!("chat {} {} {}", game_id, room, text))
+    }
+
+    async fn abort_bot_game(&self, game_id: &str) -> anyhow::Result<()> {
+        self.record(format!("abort {}", game_id))
+    }
+
+    async fn resign_bot_game(&self, game_id: &str) -> anyhow::Result<()> {
+        self.record(format!("resign {}", game_id))
+    }
+
+    async fn challenge_accept(&self, challenge_id: &str) -> anyhow::Result<()> {
+        self.record(format!("accept {}", challenge_id))
+    }
+
+    async fn challenge_decline(
+        &self,
+        challenge_id: &str,
+        reason: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.record(format!("decline {} {}", challenge_id, reason.unwrap_or("")))
+    }
+
+    async fn is_following(&self, _bot_username: &str, username: &str) -> anyhow::Result<bool> {
+        Ok(self.followings.iter().any(|user| user == username))
+    }
+
+    async fn stream_bot_game_state(
+        &self,
+        game_id: &str,
+    ) -> anyhow::Result<impl Stream<Item = Result<BoardState, LichessError>> + Send + Unpin + 'static>
+    {
+        self.record(format!("stream {}", game_id))?;
+        Ok(tokio_stream::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_retry_rate_limited() {
+        let lichess = MockLichess {
+            rate_limited_moves: AtomicUsize::new(1),
+            ..MockLichess::default()
+        };
+        retry_rate_limited(|| lichess.make_a_bot_move("q7ZvsdUF", "e2e4", false))
+            .await
+            .unwrap();
+        assert_eq!(lichess.requests(), vec!["move q7ZvsdUF e2e4 false"]);
+
+        // Gives up after a few retries
+        let lichess = MockLichess {
+            rate_limited_moves: AtomicUsize::new(RATE_LIMIT_RETRIES + 1),
+            ..MockLichess::default()
+        };
+        let result =
+            retry_rate_limited(|| lichess.make_a_bot_move("q7ZvsdUF", "e2e4", false)).await;
+        assert!(rate_limit_backoff(&result.unwrap_err()).is_some());
+        assert!(lichess.requests().is_empty());
+
+        // Other errors aren't retried
+        let attempts = AtomicUsize::new(0);
+        let result = retry_rate_limited(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(anyhow::Error::msg("game over"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_rate_limited_within() {
+        let attempts = AtomicUsize::new(0);
+        let rate_limited = || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(LichessError::RateLimited(Some(60)).into())
+        };
+
+        // The minute-long backoff is cut short to the time left, then there is no time to retry
+        let start = Instant::now();
+        let result = retry_rate_limited_within(Duration::from_millis(50), rate_limited).await;
+        assert!(rate_limit_backoff(&result.unwrap_err()).is_some());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // No time left: no retry
+        attempts.store(0, Ordering::SeqCst);
+        let result = retry_rate_limited_within(Duration::ZERO, rate_limited).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // Retried like `retry_rate_limited` while there is time
+        let lichess = MockLichess {
+            rate_limited_moves: AtomicUsize::new(1),
+            ..MockLichess::default()
+        };
+        retry_rate_limited_within(Duration::from_secs(10), || {
+            lichess.make_a_bot_move("q7ZvsdUF", "e2e4", false)
+        })
+        .await
+        .unwrap();
+        assert_eq!(lichess.requests(), vec!["move q7ZvsdUF e2e4 false"]);
+    }
+
+    #[test]
+    fn test_rate_limit_backoff() {
+        let error = |e: LichessError| anyhow::Error::from(e);
+        assert_eq!(
+            rate_limit_backoff(&error(LichessError::RateLimited(Some(5)))),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            rate_limit_backoff(&error(LichessError::RateLimited(None))),
+            Some(RATE_LIMIT_BACKOFF)
+        );
+        assert_eq!(
+            rate_limit_backoff(&error(LichessError::StatusCode(500, "error".into()))),
+            None
+        );
+        let context = error(LichessError::RateLimited(None)).context("Failed to move");
+        assert_eq!(rate_limit_backoff(&context), Some(RATE_LIMIT_BACKOFF));
+    }
+}
@@ -7,101 +7,28 @@ use std::sync::Arc;
 use anyhow::Context;
 use clap::{App, AppSettings, Arg, ArgMatches};
 use licorice::client::Lichess;
+use poirebot::game::{Board, Move};
 pub use poirebot_licorice as licorice;
 
 use crate::bot::{abort_games, send_stockfish_challenge, send_user_challenge, start_bot};
 
 mod bot;
+mod client;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args = App::new(clap::crate_name!())
-        .version(clap::crate_version!())
-        .author(clap::crate_authors!())
-        .about(clap::crate_description!())
-        .arg(
-            Arg::with_name("token")
-                .short("t")
-                .long("token")
-                .help("Personal authentication token for Lichess")
-                .env("LICHESS_TOKEN")
-                .required(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("debug")
-                .long("debug")
-                .alias("verbose")
-                .help("Turns on verbose logging")
-                .required(false)
-                .takes_value(false)
-                .global(true),
-        )
-        .subcommand(
-            App::new("start")
-                .about("Starts the bot to run on Lichess.org")
-                .arg(
-                    Arg::with_name("challenge")
-                        .short("c")
-                        .long("challenge")
-                        .help("Lichess username to send a challenge to on startup")
-                        .required(false)
-                        .takes_value(true),
-                )
-                .arg(
-                    Arg::with_name("stockfish")
-                        .long("stockfish")
-                        .help("Start a Stockfish duel with the given strength (1-8)")
-                        .takes_value(true)
-                        .conflicts_with("challenge")
-                        .required(false),
-                )
-                .arg(
-                    Arg::with_name("abort")
-                        .long("abort")
-                        .help("Aborts all ongoing games")
-                        .takes_value(false)
-                        .required(false),
-                )
-                .arg(
-                    Arg::with_name("no-accept")
-                        .long("no-accept")
-                        .help("Disables all incoming challenges")
-                        .takes_value(false)
-                        .required(false),
-                )
-                .arg(
-                    Arg::with_name("rematch")
-                        .long("rematch")
-                        .help("Always challenge for a rematch after game is over")
-                        .takes_value(false)
-                        .required(false),
-                )
-                .arg(
-                    Arg::with_name("following-only")
-                        .long("following-only")
-                        .help("Only allow incoming challenges from users followed by the Bot")
-                        .takes_value(false)
-                        .required(false),
-                ),
-        )
-        .subcommand(
-            App::new("upgrade-account")
-                .about("Upgrades the Lichess.org account to a BOT account (irreversible)")
-                .arg(
-                    Arg::with_name("yes")
-                        .short("y")
-                        .long("yes")
-                        .help("Skip the confirmation step")
-                        .required(false)
-                        .takes_value(false),
-                ),
-        )
-        .setting(AppSettings::SubcommandRequiredElseHelp)
-        .get_matches();
+    let args = cli().get_matches();
 
     init_logger(args.is_present("debug"));
 
+    // Offline commands, which don't need a Lichess token
+    if let Some(args) = args.subcommand_matches("perft") {
+        return perft_divide(args);
+    }
+    if let Some(args) = args.subcommand_matches("self-play") {
+        return self_play(args);
+    }
+
     let lichess = init_lichess(&args).with_context(|| "Failed to initialize Lichess")?;
     let lichess = Arc::new(lichess);
 
@@ -110,7 +37,19 @@ async fn main() -> anyhow::Result<()> {
         .await
         .with_context(|| "Failed to get current user profile")?;
 
-    if let Some(ref args) = args.subcommand_matches("start") {
+    if let Some(args) = args.subcommand_matches("start") {
+        // The bot endpoints are only available to BOT accounts
+        if !bot::is_bot_account(&lichess_user) {
+            if !args.is_present("auto-upgrade") {
+                return Err(anyhow::Error::msg(format!(
+                    "{} is not a BOT account: upgrade it with the `upgrade-account` command first \
+                    (irreversible), or start with --auto-upgrade",
+                    &lichess_user.username
+                )));
+            }
+            bot::upgrade_bot_account(lichess.clone(), &lichess_user).await?;
+        }
+
         // Abort if specified
         if args.is_present("abort") {
             abort_games(lichess.clone())
@@ -118,52 +57,25 @@ async fn main() -> anyhow::Result<()> {
                 .with_context(|| "Failed to resign ongoing games")?;
         }
 
-        let stockfish: Option<(u8, u8)> = if let Some(stockfish_arg) = args.value_of("stockfish") {
-            let pattern = regex::Regex::new(r"(\d+)(?:-(\d+))?").unwrap();
-            let matches = pattern
-                .captures(stockfish_arg)
-                .with_context(|| "Failed to parse stockfish arg")?;
-
-            let stockfish_min = matches
-                .get(1)
-                .with_context(|| {
-                    "Invalid stockfish arg, use --stockfish=1 or -stockfish=1-8 for example"
-                })?
-                .as_str();
-            let stockfish_min = stockfish_min
-                .parse()
-                .with_context(|| "Invalid stockfish level")?;
-            let stockfish_max = matches
-                .get(2)
-                .map_or(Ok(stockfish_min), |m| m.as_str().parse())
-                .with_context(|| "Invalid max stockfish level")?;
-            Some((stockfish_min, stockfish_max))
-        } else {
-            None
-        };
-
-        let config = bot::Config {
-            no_accept: args.is_present("no-accept"),
-            username: lichess_user.username.clone(),
-            rematch: args.is_present("rematch"),
-            stockfish: stockfish.map_or(0, |s| s.0),
-            stockfish_max: stockfish.map_or(0, |s| s.1),
-            following_only: args.is_present("following-only"),
-        };
+        let config = bot::Config::from_args(args, &lichess_user.username)?;
 
         // Challenge if specified
         if let Some(challenge_username) = args.value_of("challenge") {
-            send_user_challenge(lichess.clone(), challenge_username.into())
-                .await
-                .with_context(|| format!("Failed to send challenge to {}", challenge_username))?;
-        } else if let Some((stockfish, _)) = stockfish {
-            send_stockfish_challenge(lichess.clone(), stockfish)
+            send_user_challenge(
+                lichess.clone(),
+                challenge_username.into(),
+                &config.challenge,
+            )
+            .await
+            .with_context(|| format!("Failed to send challenge to {}", challenge_username))?;
+        } else if config.stockfish > 0 {
+            send_stockfish_challenge(lichess.clone(), config.stockfish, &config.challenge)
                 .await
                 .with_context(|| "Failed to send challenge to Stockfish")?;
         }
 
         start_bot(lichess, config).await
-    } else if let Some(ref args) = args.subcommand_matches("upgrade-account") {
+    } else if let Some(args) = args.subcommand_matches("upgrade-account") {
         if !args.is_present("yes") {
             println!(
                 "Are you sure you want to upgrade {} to a BOT account?",
@@ -186,6 +98,461 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+/// The command-line interface.
+fn cli() -> App<'static, 'static> {
+    App::new(clap::crate_name!())
+    .version(clap::crate_version!())
+    .author(clap::crate_authors!())
+    .about(clap::crate_description!())
+    .arg(
+        Arg::with_name("token")
+            .short("t")
+            .long("token")
+            .help("Personal authentication token for Lichess")
+            .env("LICHESS_TOKEN")
+            .required(false)
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("debug")
+            .long("debug")
+            .alias("verbose")
+            .help("Turns on verbose logging")
+            .required(false)
+            .takes_value(false)
+            .global(true),
+    )
+    .subcommand(
+        App::new("start")
+            .about("Starts the bot to run on Lichess.org")
+            .arg(
+                Arg::with_name("challenge")
+                    .short("c")
+                    .long("challenge")
+                    .help("Lichess username to send a challenge to on startup")
+                    .required(false)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("clock-limit")
+                    .long("clock-limit")
+                    .help("The initial time of the challenges' clock, in seconds (default: 300)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("clock-increment")
+                    .long("clock-increment")
+                    .help("The increment of the challenges' clock, in seconds (default: 0)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("color")
+                    .long("color")
+                    .help("The bot's color in the challenges: white, black or random (default)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("rated")
+                    .long("rated")
+                    .help("Send a rated challenge (the color must be random)")
+                    .takes_value(false)
+                    .requires("challenge")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("stockfish")
+                    .long("stockfish")
+                    .help("Start a Stockfish duel with the given strength (1-8)")
+                    .takes_value(true)
+                    .conflicts_with("challenge")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("offer-draw-on-repetition")
+                    .long("offer-draw-on-repetition")
+                    .help("Offer a draw when repeating a balanced position for the third time")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("claim-fifty-move")
+                    .long("claim-fifty-move")
+                    .help("Offer a draw when reaching the 50-move rule in a balanced position")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("offer-draw-in-drawn-endgame")
+                    .long("offer-draw-in-drawn-endgame")
+                    .help("Offer a draw in a balanced endgame without pawns")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("accept-draw-below")
+                    .long("accept-draw-below")
+                    .help("Accept draw offers when the evaluation for the bot is below this (in centipawns)")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("bot-opponent-contempt")
+                    .long("bot-opponent-contempt")
+                    .help("Against other bots, do not chat, wait or offer draws, and only accept a draw when the evaluation for the bot is below minus this contempt (in centipawns)")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("abort-on-error")
+                    .long("abort-on-error")
+                    .help("Abort instead of resigning when failing to move, if the game can still be aborted")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("book")
+                    .long("book")
+                    .help("Opening book to play from (one 'EPD<TAB>move<TAB>weight' per line)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("engine-name")
+                    .long("engine-name")
+                    .help("The engine name to show in the chat (the version is kept)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("time-classes")
+                    .long("time-classes")
+                    .help("Comma-separated time controls to accept (ultrabullet, bullet, blitz, rapid, classical)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("heartbeat-secs")
+                    .long("heartbeat-secs")
+                    .help("Log a summary of the bot's activity every this many seconds (0 disables it, the default)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("abort-idle-after")
+                    .long("abort-idle-after")
+                    .help("Abort live games where the opponent hasn't made their first move after this many seconds")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("stockfish-budget-factors")
+                    .long("stockfish-budget-factors")
+                    .help("Comma-separated shares of the move budget to spend against each Stockfish level (1 to 8)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("abort")
+                    .long("abort")
+                    .help("Aborts all ongoing games")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("no-accept")
+                    .long("no-accept")
+                    .help("Disables all incoming challenges")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("rematch")
+                    .long("rematch")
+                    .help("Always challenge for a rematch after game is over")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("following-only")
+                    .long("following-only")
+                    .help("Only allow incoming challenges from users followed by the Bot")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("results-log")
+                    .long("results-log")
+                    .help("File to append the result of every finished game to")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("eval-log")
+                    .long("eval-log")
+                    .help("CSV file to append the evaluated positions and game results to, for tuning")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("rated-only")
+                    .long("rated-only")
+                    .help("Only accept rated challenges")
+                    .takes_value(false)
+                    .conflicts_with("casual-only")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("casual-only")
+                    .long("casual-only")
+                    .help("Only accept casual challenges")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("no-correspondence")
+                    .long("no-correspondence")
+                    .help("Declines correspondence and unlimited challenges")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("max-games-per-opponent")
+                    .long("max-games-per-opponent")
+                    .help("Decline challenges from users who already played this many games")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("max-pending-challenges")
+                    .long("max-pending-challenges")
+                    .help("Decline challenges from users who already have this many unresolved challenges")
+                    .takes_value(true)
+                    .default_value("1"),
+            )
+            .arg(
+                Arg::with_name("no-clock-think-time")
+                    .long("no-clock-think-time")
+                    .help("Think time per move (in seconds) in games without a clock")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("think-time-ms")
+                    .long("think-time-ms")
+                    .help("Fixed think time per move (in milliseconds), regardless of the clock")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("ponder")
+                    .long("ponder")
+                    .help("Think during the opponent's turn, assuming it plays the expected reply")
+                    .required(false)
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("opening-extension")
+                    .long("opening-extension")
+                    .help("Search this many extra plies in the opening, while the pieces are undeveloped and the clock allows it")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("opening-extension-moves")
+                    .long("opening-extension-moves")
+                    .help("The number of moves at the start of the game to search deeper (see --opening-extension)")
+                    .takes_value(true)
+                    .default_value("8"),
+            )
+            .arg(
+                Arg::with_name("max-nodes")
+                    .long("max-nodes")
+                    .help("The maximum number of nodes to search for each move, in addition to the think time")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("auto-upgrade")
+                    .long("auto-upgrade")
+                    .help("Upgrade the account to a BOT account (irreversible) if it isn't one yet")
+                    .required(false)
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("log-fen-on-error")
+                    .long("log-fen-on-error")
+                    .help("Log the position and the moves of a game along with its errors")
+                    .required(false)
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("announce-opening")
+                    .long("announce-opening")
+                    .help("Announce the name of the opening in the chat")
+                    .required(false)
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("move-overhead-ms")
+                    .long("move-overhead-ms")
+                    .help("Time (in milliseconds) to reserve from every move for network latency (defaults to 100)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("min-move-delay-ms")
+                    .long("min-move-delay-ms")
+                    .help("The shortest delay (in milliseconds) to wait for before sending a move (defaults to 0)")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("max-move-delay-ms")
+                    .long("max-move-delay-ms")
+                    .help("The longest delay (in milliseconds) to wait for before sending a move, within the move budget")
+                    .takes_value(true)
+                    .required(false),
+            ),
+    )
+    .subcommand(
+        App::new("upgrade-account")
+            .about("Upgrades the Lichess.org account to a BOT account (irreversible)")
+            .arg(
+                Arg::with_name("yes")
+                    .short("y")
+                    .long("yes")
+                    .help("Skip the confirmation step")
+                    .required(false)
+                    .takes_value(false),
+            ),
+    )
+    .subcommand(
+        App::new("perft")
+            .about("Counts the legal moves of each root move at the given depth, to debug the move generator")
+            .arg(
+                Arg::with_name("depth")
+                    .help("The depth to count at (in plies)")
+                    .required(true)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("fen")
+                    .long("fen")
+                    .help("The position to start from (defaults to the starting position)")
+                    .required(false)
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        App::new("self-play")
+            .about("Plays the engine against itself offline, e.g. to test endgames from a position")
+            .arg(
+                Arg::with_name("fen")
+                    .long("fen")
+                    .alias("position")
+                    .help("The position to start from (defaults to the starting position)")
+                    .required(false)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("depth")
+                    .long("depth")
+                    .help("The depth to search every move at (in plies, default: 3)")
+                    .required(false)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("max-plies")
+                    .long("max-plies")
+                    .help("The number of plies to stop after, if the game isn't over (default: 100)")
+                    .required(false)
+                    .takes_value(true),
+            ),
+    )
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+}
+
+/// Prints the perft count of each root move, and the total.
+fn perft_divide(args: &ArgMatches) -> anyhow::Result<()> {
+    let depth = args
+        .value_of("depth")
+        .with_context(|| "Missing depth")?
+        .parse()
+        .with_context(|| "Invalid depth")?;
+    let board = starting_board(args)?;
+
+    let divide = board.perft_divide(depth, board.turn);
+    for (m, count) in divide.iter() {
+        println!("{}: {}", m.to_pure_notation(), count);
+    }
+    println!();
+    println!(
+        "Nodes: {}",
+        divide.iter().map(|(_, count)| count).sum::<u64>()
+    );
+    Ok(())
+}
+
+/// The board to start from: the `--fen` argument, or the starting position.
+fn starting_board(args: &ArgMatches) -> anyhow::Result<Board> {
+    match args.value_of("fen") {
+        Some(fen) => {
+            let board = Board::from_fen(fen).with_context(|| format!("Invalid FEN: {}", fen))?;
+            board
+                .is_legal_position()
+                .map_err(|e| anyhow::Error::msg(format!("Illegal position: {} ({})", fen, e)))?;
+            Ok(board)
+        }
+        None => Ok(Board::default()),
+    }
+}
+
+/// Plays the engine against itself from the `--fen` position, and prints the game.
+fn self_play(args: &ArgMatches) -> anyhow::Result<()> {
+    let board = starting_board(args)?;
+    let depth = args
+        .value_of("depth")
+        .map(|depth| depth.parse())
+        .transpose()
+        .with_context(|| "Invalid depth")?
+        .unwrap_or(3);
+    let max_plies = args
+        .value_of("max-plies")
+        .map(|plies| plies.parse())
+        .transpose()
+        .with_context(|| "Invalid number of plies")?
+        .unwrap_or(100);
+
+    let moves = play_against_itself(board, depth, max_plies);
+    let mut end = board;
+    moves.iter().for_each(|m| end.apply_move(*m));
+    println!("{}", board.san_line(&moves));
+    println!();
+    println!("Final position: {}", end.to_fen());
+    Ok(())
+}
+
+/// The moves of the engine playing both sides from the board, until the game is over (or can only
+/// be drawn) or after the given number of plies.
+fn play_against_itself(mut board: Board, depth: usize, max_plies: usize) -> Vec<Move> {
+    let mut moves = Vec::new();
+    while moves.len() < max_plies && !board.is_dead_position() && board.halfmove_clock < 100 {
+        match poirebot::genius::search(&board, board.turn, depth) {
+            Some(result) => {
+                board.apply_move(result.best);
+                moves.push(result.best);
+            }
+            None => break,
+        }
+    }
+    moves
+}
+
 fn init_logger(debug: bool) {
     if std::env::var("POIREBOT_LOG").is_err() {
         std::env::set_var("POIREBOT_LOG", "info");
@@ -204,3 +571,209 @@ fn init_lichess(args: &ArgMatches) -> anyhow::Result<Lichess> {
         .to_string();
     Ok(Lichess::new(token))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use poirebot::time::TimeClass;
+
+    use super::*;
+
+    #[test]
+    fn test_self_play_from_fen() {
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1";
+        let args = cli().get_matches_from(vec!["poirebot-lichess", "self-play", "--fen", fen]);
+        let board = starting_board(args.subcommand_matches("self-play").unwrap()).unwrap();
+        assert_eq!(board, Board::from_fen(fen).unwrap());
+
+        // The back-rank mate ends the game
+        let moves = play_against_itself(board, 2, 10);
+        assert_eq!(moves, vec![Move::from(("d1", "d8"))]);
+
+        let args = cli().get_matches_from(vec!["poirebot-lichess", "self-play"]);
+        let board = starting_board(args.subcommand_matches("self-play").unwrap()).unwrap();
+        assert_eq!(board, Board::default());
+
+        // Invalid FEN, and a position without kings
+        for fen in ["not a fen", "8/8/8/8/8/8/8/8 w - - 0 1"].iter() {
+            let args = cli().get_matches_from(vec!["poirebot-lichess", "self-play", "--fen", fen]);
+            let error = starting_board(args.subcommand_matches("self-play").unwrap()).unwrap_err();
+            assert!(error.to_string().contains(fen), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let args = cli().get_matches_from(vec![
+            "poirebot-lichess",
+            "start",
+            "--stockfish",
+            "2-5",
+            "--rated-only",
+            "--time-classes",
+            "blitz,rapid",
+            "--no-clock-think-time",
+            "30",
+            "--max-games-per-opponent",
+            "3",
+            "--move-overhead-ms",
+            "250",
+            "--offer-draw-on-repetition",
+            "--accept-draw-below",
+            "-150",
+            "--bot-opponent-contempt",
+            "30",
+        ]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(config.username, "poirebot");
+        assert_eq!((config.stockfish, config.stockfish_max), (2, 5));
+        assert!(config.accept_rated);
+        assert!(!config.accept_casual);
+        assert!(!config.no_correspondence);
+        assert_eq!(
+            config.accepted_time_classes,
+            vec![TimeClass::Blitz, TimeClass::Rapid]
+        );
+        assert_eq!(config.no_clock_think_time, Duration::from_secs(30));
+        assert_eq!(config.move_overhead, Duration::from_millis(250));
+        assert_eq!(config.move_delay, (Duration::ZERO, Duration::ZERO));
+        assert_eq!(config.max_games_per_opponent, Some(3));
+        assert_eq!(config.max_pending_challenges, 1);
+        assert_eq!(config.opening_extension, None);
+        assert_eq!(config.max_nodes, None);
+        assert_eq!(
+            config.stockfish_budget_factors,
+            poirebot::time::STOCKFISH_BUDGET_FACTORS
+        );
+        assert_eq!(config.idle_abort_timeout, None);
+        assert_eq!(config.think_time, None);
+        assert_eq!(config.challenge, bot::ChallengeOptions::default());
+        assert_eq!(
+            config.draw_policy,
+            bot::DrawPolicy {
+                accept_draw_below_cp: Some(-150),
+                claim_repetition: true,
+                ..bot::DrawPolicy::default()
+            }
+        );
+        assert_eq!(config.bot_opponent_contempt, Some(30));
+
+        // Invalid values are reported
+        let args =
+            cli().get_matches_from(vec!["poirebot-lichess", "start", "--time-classes", "slow"]);
+        assert!(
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").is_err()
+        );
+
+        let args = cli().get_matches_from(vec![
+            "poirebot-lichess",
+            "start",
+            "--challenge",
+            "someone",
+            "--clock-limit",
+            "600",
+            "--clock-increment",
+            "5",
+            "--color",
+            "white",
+        ]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(
+            config.challenge,
+            bot::ChallengeOptions {
+                rated: false,
+                clock_limit: 600,
+                clock_increment: 5,
+                color: "white".into(),
+            }
+        );
+        for invalid in [
+            vec!["--color", "white", "--rated"],
+            vec!["--color", "purple"],
+            vec!["--clock-limit", "0"],
+            vec!["--clock-increment", "120"],
+            vec!["--min-move-delay-ms", "500", "--max-move-delay-ms", "100"],
+            vec!["--stockfish-budget-factors", "0,0.1,0.2,0.3,0.4,0.5,0.6,1"],
+            vec![
+                "--stockfish-budget-factors",
+                "0.1,0.1,0.2,0.3,0.4,0.5,0.6,1.01",
+            ],
+            vec![
+                "--stockfish-budget-factors",
+                "0.1,0.1,0.2,0.3,0.4,0.5,0.6,inf",
+            ],
+            vec![
+                "--stockfish-budget-factors",
+                "0.1,0.1,0.2,0.3,0.4,0.5,0.6,NaN",
+            ],
+        ]
+        .iter()
+        {
+            let mut argv = vec!["poirebot-lichess", "start", "--challenge", "someone"];
+            argv.extend(invalid);
+            let args = cli().get_matches_from(argv);
+            assert!(
+                bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot")
+                    .is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+
+        let args = cli().get_matches_from(vec![
+            "poirebot-lichess",
+            "start",
+            "--opening-extension",
+            "2",
+        ]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(config.opening_extension, Some((8, 2)));
+
+        let args =
+            cli().get_matches_from(vec!["poirebot-lichess", "start", "--max-nodes", "50000"]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(config.max_nodes, Some(50_000));
+
+        let args = cli().get_matches_from(vec!["poirebot-lichess", "start", "--no-correspondence"]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert!(config.no_correspondence);
+
+        // The factors may go up to the whole budget
+        let args = cli().get_matches_from(vec![
+            "poirebot-lichess",
+            "start",
+            "--stockfish-budget-factors",
+            "0.01,0.1,0.2,0.3,0.4,0.5,1,1.0",
+        ]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(
+            config.stockfish_budget_factors,
+            [0.01, 0.1, 0.2, 0.3, 0.4, 0.5, 1.0, 1.0]
+        );
+
+        let args =
+            cli().get_matches_from(vec!["poirebot-lichess", "start", "--think-time-ms", "1500"]);
+        let config =
+            bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot").unwrap();
+        assert_eq!(config.think_time, Some(Duration::from_millis(1_500)));
+        for think_time in ["0", "fast"].iter() {
+            let args = cli().get_matches_from(vec![
+                "poirebot-lichess",
+                "start",
+                "--think-time-ms",
+                think_time,
+            ]);
+            assert!(
+                bot::Config::from_args(args.subcommand_matches("start").unwrap(), "poirebot")
+                    .is_err()
+            );
+        }
+    }
+}
@@ -13,6 +13,40 @@ pub struct BitBoard(pub u64);
 #[allow(dead_code)]
 pub const EMPTY: BitBoard = BitBoard(0);
 
+/// The squares of the A file, which wrap around to the H file when shifting West.
+const FILE_A_MASK: u64 = 0x0101_0101_0101_0101;
+
+/// The squares of the H file, which wrap around to the A file when shifting East.
+const FILE_H_MASK: u64 = FILE_A_MASK << 7;
+
+/// A direction to shift a `BitBoard` in, from White's side of the board: North is towards the 8th
+/// rank, and East towards the H file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    N,
+    S,
+    E,
+    W,
+    NE,
+    NW,
+    SE,
+    SW,
+}
+
+impl Direction {
+    /// All the directions, e.g. for the steps of a king.
+    pub const ALL: [Direction; 8] = [
+        Direction::N,
+        Direction::S,
+        Direction::E,
+        Direction::W,
+        Direction::NE,
+        Direction::NW,
+        Direction::SE,
+        Direction::SW,
+    ];
+}
+
 impl BitAnd for BitBoard {
     type Output = BitBoard;
 
@@ -271,35 +305,43 @@ impl Not for &BitBoard {
     }
 }
 
+/// Shifting by 64 or more moves every bit out of the board, resulting in an empty `BitBoard`
+/// (instead of overflowing).
 impl Shl<u8> for BitBoard {
     type Output = BitBoard;
 
+    #[inline]
     fn shl(self, rhs: u8) -> BitBoard {
-        BitBoard(self.0 << rhs)
+        BitBoard(self.0.checked_shl(rhs as u32).unwrap_or(0))
     }
 }
 
 impl Shl<u8> for &BitBoard {
     type Output = BitBoard;
 
+    #[inline]
     fn shl(self, rhs: u8) -> BitBoard {
-        BitBoard(self.0 << rhs)
+        BitBoard(self.0.checked_shl(rhs as u32).unwrap_or(0))
     }
 }
 
+/// Shifting by 64 or more moves every bit out of the board, resulting in an empty `BitBoard`
+/// (instead of overflowing).
 impl Shr<u8> for BitBoard {
     type Output = BitBoard;
 
+    #[inline]
     fn shr(self, rhs: u8) -> BitBoard {
-        BitBoard(self.0 >> rhs)
+        BitBoard(self.0.checked_shr(rhs as u32).unwrap_or(0))
     }
 }
 
 impl Shr<u8> for &BitBoard {
     type Output = BitBoard;
 
+    #[inline]
     fn shr(self, rhs: u8) -> BitBoard {
-        BitBoard(self.0 >> rhs)
+        BitBoard(self.0.checked_shr(rhs as u32).unwrap_or(0))
     }
 }
 
@@ -331,6 +373,12 @@ impl BitBoard {
         BitBoard(b)
     }
 
+    /// Iterate over every `Position` set, without consuming this `BitBoard`.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Position> {
+        *self
+    }
+
     /// Construct a new `BitBoard` with a particular `Position` set
     #[inline]
     pub fn from_position<T: Into<Position>>(position: T) -> BitBoard {
@@ -340,10 +388,8 @@ impl BitBoard {
     /// Convert to Position
     #[inline]
     pub fn to_position(&self) -> Position {
-        let trailing = self.0.trailing_zeros() as u8;
-        let file = trailing & 7;
-        let rank = trailing >> 3;
-        Position::new(file % 8, rank % 8).unwrap()
+        // An empty board has 64 trailing zeros, which wraps around to a1
+        Position::from_int(self.0.trailing_zeros() as u8 & 63)
     }
 
     /// Count the number of `Positions` set in this `BitBoard`
@@ -354,12 +400,14 @@ impl BitBoard {
 
     /// Reverse this `BitBoard`.  Look at it from the opponents perspective.
     #[inline]
+    #[must_use]
     pub fn swap(&self) -> BitBoard {
         BitBoard(self.0.swap_bytes())
     }
 
     /// Mirror this `Bitboard` horizontally (left becomes right).
     #[inline]
+    #[must_use]
     pub fn mirror_horizontally(&self) -> BitBoard {
         let mut x = self.0;
         let k1: u64 = 0x5555555555555555;
@@ -373,6 +421,7 @@ impl BitBoard {
 
     /// Mirror this `Bitboard` horizontally (left becomes right) and flip the colors.
     #[inline]
+    #[must_use]
     pub fn rotate(&self) -> BitBoard {
         self.swap().mirror_horizontally()
     }
@@ -380,6 +429,7 @@ impl BitBoard {
     /// Flip this `Bitboard` about the diagonal.
     /// Source: https://www.chessprogramming.org/Flipping_Mirroring_and_Rotating#Diagonal
     #[inline]
+    #[must_use]
     #[rustfmt::skip]
     pub fn flip_diagonally(&self) -> BitBoard {
         let mut x = self.0;
@@ -399,6 +449,7 @@ impl BitBoard {
     /// Flip this `Bitboard` about the anti-diagonal.
     /// Source: https://www.chessprogramming.org/Flipping_Mirroring_and_Rotating#Anti-Diagonal
     #[inline]
+    #[must_use]
     #[rustfmt::skip]
     pub fn flip_anti_diagonally(&self) -> BitBoard {
         let mut x = self.0;
@@ -415,6 +466,24 @@ impl BitBoard {
         BitBoard(x)
     }
 
+    /// Shift every `Position` set one square in the given direction. Squares that would leave the
+    /// board, or wrap around to the other side, are dropped.
+    #[inline]
+    #[must_use]
+    pub const fn shift(&self, direction: Direction) -> BitBoard {
+        let b = self.0;
+        BitBoard(match direction {
+            Direction::N => b << 8,
+            Direction::S => b >> 8,
+            Direction::E => (b & !FILE_H_MASK) << 1,
+            Direction::W => (b & !FILE_A_MASK) >> 1,
+            Direction::NE => (b & !FILE_H_MASK) << 9,
+            Direction::NW => (b & !FILE_A_MASK) << 7,
+            Direction::SE => (b & !FILE_H_MASK) >> 7,
+            Direction::SW => (b & !FILE_A_MASK) >> 9,
+        })
+    }
+
     /// Convert this `BitBoard` to a `usize` (for table lookups)
     #[inline]
     pub fn to_size(&self, rightshift: u8) -> usize {
@@ -473,6 +542,17 @@ impl Iterator for BitBoard {
     }
 }
 
+/// For a borrowed `BitBoard`, iterate over every `Position` set (see `BitBoard::iter`).
+impl IntoIterator for &BitBoard {
+    type Item = Position;
+    type IntoIter = BitBoard;
+
+    #[inline]
+    fn into_iter(self) -> BitBoard {
+        *self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -491,4 +571,78 @@ mod tests {
                 .collect::<Vec<String>>()
         );
     }
+
+    #[test]
+    fn test_out_of_range_shifts() {
+        let board = BitBoard::from_position("a1") | BitBoard::from_position("h8");
+        assert_eq!(board << 64, EMPTY);
+        assert_eq!(board >> 64, EMPTY);
+        assert_eq!(&board << 255, EMPTY);
+        assert_eq!(&board >> 255, EMPTY);
+        assert_eq!(board << 63, BitBoard::from_position("h8"));
+        assert_eq!(board >> 63, BitBoard::from_position("a1"));
+    }
+
+    #[test]
+    fn test_shift() {
+        let d4 = BitBoard::from_position("d4");
+        for (direction, expected) in [
+            (Direction::N, "d5"),
+            (Direction::S, "d3"),
+            (Direction::E, "e4"),
+            (Direction::W, "c4"),
+            (Direction::NE, "e5"),
+            (Direction::NW, "c5"),
+            (Direction::SE, "e3"),
+            (Direction::SW, "c3"),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                d4.shift(*direction),
+                BitBoard::from_position(*expected),
+                "{:?}",
+                direction
+            );
+        }
+
+        // Nothing wraps around the edges
+        let h4 = BitBoard::from_position("h4");
+        let a4 = BitBoard::from_position("a4");
+        for direction in [Direction::E, Direction::NE, Direction::SE].iter() {
+            assert_eq!(h4.shift(*direction), EMPTY, "{:?}", direction);
+        }
+        for direction in [Direction::W, Direction::NW, Direction::SW].iter() {
+            assert_eq!(a4.shift(*direction), EMPTY, "{:?}", direction);
+        }
+        let corners = BitBoard::from_position("a8") | BitBoard::from_position("h1");
+        assert_eq!(corners.shift(Direction::N), BitBoard::from_position("h2"));
+        assert_eq!(corners.shift(Direction::S), BitBoard::from_position("a7"));
+        assert_eq!(
+            Direction::ALL
+                .iter()
+                .map(|direction| BitBoard::from_position("a1").shift(*direction))
+                .fold(EMPTY, |all, b| all | b)
+                .popcnt(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_borrowed_iter() {
+        let board = BitBoard::from_position("a1") | BitBoard::from_position("h8");
+        let positions = vec![Position::from("a1"), Position::from("h8")];
+        assert_eq!(board.iter().collect::<Vec<Position>>(), positions);
+        assert_eq!(board.iter().collect::<Vec<Position>>(), positions);
+
+        let mut count = 0;
+        for _ in &board {
+            count += 1;
+        }
+        for position in &board {
+            assert!(positions.contains(&position));
+        }
+        assert_eq!(count, 2);
+        assert_eq!(board.popcnt(), 2);
+    }
 }
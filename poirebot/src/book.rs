@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::game::{Board, Move};
+
+/// An opening book: the moves to play in known positions, with their weights.
+///
+/// The text format has one entry per line: the EPD of the position (see `Board::to_epd`), the
+/// move in pure coordinate notation, and its weight, separated by tabs. For example (with `\t`
+/// for the tabs):
+///
+/// ```text
+/// rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\te2e4\t10
+/// ```
+///
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    /// The moves by position (EPD), in the order of the book.
+    entries: HashMap<String, Vec<(Move, u32)>>,
+}
+
+impl OpeningBook {
+    /// Reads the book from a file (see `parse`).
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read opening book: {}", path.display()))?;
+        Ok(Self::parse(&text))
+    }
+
+    /// Parses the book from text. Malformed lines are skipped with a warning.
+    pub fn parse(text: &str) -> Self {
+        let mut book = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_entry(line) {
+                Some((epd, m, weight)) => book.entries.entry(epd).or_default().push((m, weight)),
+                None => warn!("Skipped malformed opening book line {}: {}", i + 1, line),
+            }
+        }
+        book
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the book has no position.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the book move with the highest weight in the position, if any. Moves that aren't
+    /// legal in the position are ignored, and the first one wins a tie.
+    pub fn lookup(&self, board: &Board) -> Option<Move> {
+        self.entries
+            .get(&board.to_epd())?
+            .iter()
+            .filter(|(m, _)| board.is_legal_move(*m))
+            .fold(None, |best: Option<(Move, u32)>, (m, weight)| match best {
+                Some((_, best_weight)) if best_weight >= *weight => best,
+                _ => Some((*m, *weight)),
+            })
+            .map(|(m, _)| m)
+    }
+}
+
+/// Parses a line of the book: `EPD<TAB>move<TAB>weight`.
+fn parse_entry(line: &str) -> Option<(String, Move, u32)> {
+    let mut fields = line.split('\t');
+    let epd = Board::from_fen(&format!("{} 0 1", fields.next()?.trim()))
+        .ok()?
+        .to_epd();
+    let m = Move::from_uci(fields.next()?.trim()).ok()?;
+    let weight = fields.next()?.trim().parse().ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    Some((epd, m, weight))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\td2d4\t5
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\te2e4\t10
+";
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("poirebot-book-{}.txt", std::process::id()));
+        std::fs::write(&path, BOOK).unwrap();
+        let book = OpeningBook::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&Board::default()), Some(("e2", "e4").into()));
+
+        let mut board = Board::default();
+        board.apply_move(("e2", "e4").into());
+        assert_eq!(book.lookup(&board), None);
+
+        assert!(OpeningBook::load(Path::new("/nonexistent/book.txt")).is_err());
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        let text = format!(
+            "# comment\n\n{}not a position\te2e4\t1\n{}\n{}\n",
+            BOOK,
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\te2e4",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\tg1f3\tmany\nrnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\tz1f3\t1",
+        );
+        let book = OpeningBook::parse(&text);
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&Board::default()), Some(("e2", "e4").into()));
+
+        // Illegal moves are never played
+        let book =
+            OpeningBook::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\te2e5\t10\n");
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&Board::default()), None);
+    }
+}
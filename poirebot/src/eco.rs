@@ -0,0 +1,113 @@
+use crate::game::Move;
+
+/// A small table of well-known openings, by their moves from the starting position (in pure
+/// coordinate notation). Longer lines are variations of the shorter ones they start with.
+///
+/// Ref: https://www.chessprogramming.org/ECO
+const OPENINGS: &[(&str, &str)] = &[
+    ("e2e4 e7e5 g1f3 b8c6 f1b5", "Ruy Lopez"),
+    ("e2e4 e7e5 g1f3 b8c6 f1b5 a7a6", "Ruy Lopez: Morphy Defense"),
+    ("e2e4 e7e5 g1f3 b8c6 f1b5 g8f6", "Ruy Lopez: Berlin Defense"),
+    ("e2e4 e7e5 g1f3 b8c6 f1c4", "Italian Game"),
+    (
+        "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5",
+        "Italian Game: Giuoco Piano",
+    ),
+    (
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6",
+        "Italian Game: Two Knights Defense",
+    ),
+    ("e2e4 e7e5 g1f3 b8c6 d2d4", "Scotch Game"),
+    ("e2e4 e7e5 g1f3 g8f6", "Petrov's Defense"),
+    ("e2e4 e7e5 g1f3 d7d6", "Philidor Defense"),
+    ("e2e4 e7e5 f2f4", "King's Gambit"),
+    ("e2e4 e7e5 b1c3", "Vienna Game"),
+    ("e2e4 c7c5", "Sicilian Defense"),
+    (
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+        "Sicilian Defense: Najdorf Variation",
+    ),
+    ("e2e4 c7c5 b1c3", "Sicilian Defense: Closed"),
+    ("e2e4 e7e6", "French Defense"),
+    ("e2e4 c7c6", "Caro-Kann Defense"),
+    ("e2e4 d7d5", "Scandinavian Defense"),
+    ("e2e4 g8f6", "Alekhine's Defense"),
+    ("e2e4 d7d6", "Pirc Defense"),
+    ("d2d4 d7d5 c2c4", "Queen's Gambit"),
+    ("d2d4 d7d5 c2c4 d5c4", "Queen's Gambit Accepted"),
+    ("d2d4 d7d5 c2c4 e7e6", "Queen's Gambit Declined"),
+    ("d2d4 d7d5 c2c4 c7c6", "Slav Defense"),
+    ("d2d4 d7d5 c1f4", "London System"),
+    ("d2d4 g8f6 c2c4 g7g6", "King's Indian Defense"),
+    ("d2d4 g8f6 c2c4 e7e6 b1c3 f8b4", "Nimzo-Indian Defense"),
+    ("d2d4 g8f6 c2c4 g7g6 b1c3 d7d5", "Grünfeld Defense"),
+    ("d2d4 f7f5", "Dutch Defense"),
+    ("c2c4", "English Opening"),
+    ("g1f3", "Réti Opening"),
+];
+
+/// Returns the name of the opening played with the moves from the starting position: the
+/// longest known line that the moves start with, if any.
+///
+/// For example: `1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4` becomes `"Ruy Lopez: Morphy Defense"`.
+pub fn opening_name(moves: &[Move]) -> Option<&'static str> {
+    let played = to_notation(moves);
+    OPENINGS
+        .iter()
+        .filter(|(line, _)| starts_with_line(&played, line))
+        .max_by_key(|(line, _)| line.len())
+        .map(|(_, name)| *name)
+}
+
+/// Whether the moves have left the table: no known line goes further than them, so the opening
+/// name can't get any more specific.
+pub fn is_out_of_book(moves: &[Move]) -> bool {
+    let played = to_notation(moves);
+    !OPENINGS.iter().any(|(line, _)| {
+        let line = line.split(' ').collect::<Vec<&str>>();
+        line.len() > played.len() && same_start(&played, &line)
+    })
+}
+
+fn to_notation(moves: &[Move]) -> Vec<String> {
+    moves.iter().map(Move::to_pure_notation).collect()
+}
+
+/// Whether the played moves start with all the moves of the line.
+fn starts_with_line(played: &[String], line: &str) -> bool {
+    let line = line.split(' ').collect::<Vec<&str>>();
+    played.len() >= line.len() && same_start(played, &line)
+}
+
+/// Whether the played moves and the line agree on their common plies.
+fn same_start(played: &[String], line: &[&str]) -> bool {
+    played.iter().zip(line.iter()).all(|(a, b)| a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(line: &str) -> Vec<Move> {
+        line.split(' ').map(Move::from_pure_notation).collect()
+    }
+
+    #[test]
+    fn test_opening_name() {
+        let ruy_lopez = moves("e2e4 e7e5 g1f3 b8c6 f1b5");
+        assert_eq!(opening_name(&ruy_lopez), Some("Ruy Lopez"));
+        assert!(!is_out_of_book(&ruy_lopez));
+
+        let morphy = moves("e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4");
+        assert_eq!(opening_name(&morphy), Some("Ruy Lopez: Morphy Defense"));
+        assert!(is_out_of_book(&morphy));
+
+        assert_eq!(
+            opening_name(&moves("e2e4 c7c5 g1f3")),
+            Some("Sicilian Defense")
+        );
+        assert_eq!(opening_name(&moves("e2e4 e7e5")), None);
+        assert_eq!(opening_name(&[]), None);
+        assert!(is_out_of_book(&moves("a2a3 e7e5")));
+    }
+}
@@ -56,8 +56,7 @@ impl Board {
             }
         }
 
-        // TODO
-        let _active_color = if fen_split.next().with_context(|| "no active color")? == "w" {
+        let turn = if fen_split.next().with_context(|| "no active color")? == "w" {
             Color::White
         } else {
             Color::Black
@@ -92,11 +91,17 @@ impl Board {
             }
         };
 
-        // TODO
-        let _half_move_clock = fen_split.next().with_context(|| "no half-move clock")?;
+        let halfmove_clock = fen_split
+            .next()
+            .with_context(|| "no half-move clock")?
+            .parse::<u32>()
+            .with_context(|| "invalid half-move clock")?;
 
-        // TODO
-        let _full_move_clock = fen_split.next().with_context(|| "no full-move clock")?;
+        let fullmove_number = fen_split
+            .next()
+            .with_context(|| "no full-move clock")?
+            .parse::<u32>()
+            .with_context(|| "invalid full-move clock")?;
 
         // Construct board
         let white = BoardSide::new(Color::White, |side| {
@@ -146,12 +151,86 @@ impl Board {
             }
         });
 
-        Ok(Self { white, black })
+        Ok(Self {
+            white,
+            black,
+            turn,
+            halfmove_clock,
+            fullmove_number,
+        })
     }
 
     /// Convert a `Board` to Forsyth–Edwards Notation (FEN) string
     pub fn to_fen(&self) -> String {
-        unimplemented!()
+        format!(
+            "{} {} {}",
+            self.to_epd(),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// Convert a `Board` to Extended Position Description (EPD) string, i.e. the first four
+    /// fields of the FEN (piece placement, active color, castling and en-passant), without the
+    /// move clocks. Positions that only differ by their move clocks have the same EPD.
+    ///
+    /// Ref: https://www.chessprogramming.org/Extended_Position_Description
+    pub fn to_epd(&self) -> String {
+        let mut piece_placement = Vec::with_capacity(8);
+        for rank in (0..8).rev() {
+            let mut rank_pieces = String::new();
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.get_piece(Position::from((file, rank))) {
+                    Some(piece) => {
+                        if empty != 0 {
+                            rank_pieces.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank_pieces.push(piece.to_letter_notation());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty != 0 {
+                rank_pieces.push_str(&empty.to_string());
+            }
+            piece_placement.push(rank_pieces);
+        }
+
+        let active_color = match self.turn {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let castling_availability = [
+            (Color::White, "h1", 'K'),
+            (Color::White, "a1", 'Q'),
+            (Color::Black, "h8", 'k'),
+            (Color::Black, "a8", 'q'),
+        ]
+        .iter()
+        .filter(|(color, rook, _)| self.get_side(*color).can_castle_with((*rook).into()))
+        .map(|(_, _, c)| *c)
+        .collect::<String>();
+        let castling_availability = if castling_availability.is_empty() {
+            "-".to_owned()
+        } else {
+            castling_availability
+        };
+
+        let en_passant_target = self
+            .en_passant_square()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "-".to_owned());
+
+        format!(
+            "{} {} {} {}",
+            piece_placement.join("/"),
+            active_color,
+            castling_availability,
+            en_passant_target
+        )
     }
 }
 
@@ -164,4 +243,45 @@ mod tests {
         let parsed = Board::from_fen(DEFAULT_FEN).expect("failed to parse FEN");
         assert_eq!(parsed, Board::default());
     }
+
+    #[test]
+    fn test_to_fen() {
+        assert_eq!(Board::default().to_fen(), DEFAULT_FEN);
+
+        let fens = [
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+            "r3k3/8/8/8/8/8/8/R3K3 w Qq - 12 40",
+            "8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+        ];
+        for fen in fens.iter() {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), *fen);
+        }
+
+        let mut board = Board::default();
+        board.apply_move(("e2", "e4").into());
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        board.apply_move(("g8", "f6").into());
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn test_to_epd() {
+        assert_eq!(
+            Board::default().to_epd(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"
+        );
+
+        // Only the move clocks differ
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let later = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 12 40").unwrap();
+        assert_eq!(board.to_epd(), later.to_epd());
+        assert_ne!(board.to_fen(), later.to_fen());
+        assert_eq!(board.to_epd(), "4k3/8/8/8/8/8/8/4K2R w K -");
+    }
 }
@@ -1,12 +1,23 @@
 use std::fmt::{Debug, Display, Formatter};
+use std::io::IsTerminal;
+
+use anyhow::Context;
+use itertools::Itertools;
 
 use crate::bitboard::{BitBoard, EMPTY};
-use crate::game::pieces::{get_castling_rook_move, is_pawn_two_step, Color, Pieces};
+use crate::game::pieces::{
+    get_castling_rook_move, is_pawn_two_step, Color, PieceKind, Pieces, RANK_1, RANK_8,
+};
 use crate::game::position::Position;
 
 pub mod fen;
+pub mod perft;
+pub mod phase;
 pub mod pieces;
 pub mod position;
+pub mod san;
+pub mod see;
+pub mod zobrist;
 
 /// A chess piece move (origin and destination).
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -52,6 +63,9 @@ impl Move {
     /// Ref: https://www.chessprogramming.org/Algebraic_Chess_Notation#Pure_coordinate_notation
     ///
     /// For example: `"a1a2q"` becomes `Move(a1, a2, Queen)`.
+    ///
+    /// Note: this function panics if a square is invalid, and ignores an invalid promotion. Use
+    /// `Move::from_uci` for untrusted input.
     pub fn from_pure_notation(notation: &str) -> Self {
         let origin = notation.chars().take(2).collect::<String>();
         let destination = notation.chars().skip(2).take(2).collect::<String>();
@@ -60,6 +74,48 @@ impl Move {
         Move(origin.into(), destination.into(), promotion.into())
     }
 
+    /// Convert a `Move` to UCI notation, which is the pure coordinate notation (see
+    /// `to_pure_notation`).
+    pub fn to_uci(&self) -> String {
+        self.to_pure_notation()
+    }
+
+    /// Convert a `Move` from UCI notation, which is the pure coordinate notation (see
+    /// `from_pure_notation`). Both squares and the promotion (if any) are validated, and only
+    /// moves to the first or last rank may promote. Use `Board::move_from_uci` to also check
+    /// that the moved piece is a pawn.
+    ///
+    /// For example: `"e7e8q"` becomes `Move(e7, e8, Queen)`, while `"e7e9"` is an error.
+    pub fn from_uci(notation: &str) -> anyhow::Result<Self> {
+        if !notation.is_ascii() || !(4..=5).contains(&notation.len()) {
+            return Err(anyhow::Error::msg(format!(
+                "invalid UCI move: {}",
+                notation
+            )));
+        }
+        let origin = Position::from_notation(&notation[..2])
+            .with_context(|| format!("invalid UCI move origin: {}", notation))?;
+        let destination = Position::from_notation(&notation[2..4])
+            .with_context(|| format!("invalid UCI move destination: {}", notation))?;
+        let promotion = match &notation[4..] {
+            "" => Promotion::None,
+            "q" | "r" | "b" | "n" => Promotion::from(&notation[4..]),
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "invalid UCI move promotion: {}",
+                    notation
+                )))
+            }
+        };
+        if promotion != Promotion::None && destination.rank_y != 0 && destination.rank_y != 7 {
+            return Err(anyhow::Error::msg(format!(
+                "UCI move promotes before the last rank: {}",
+                notation
+            )));
+        }
+        Ok(Move(origin, destination, promotion))
+    }
+
     /// Returns a new `Move` with the given `Promotion`.
     pub fn with_promotion(&self, promotion: Promotion) -> Self {
         Move(self.0, self.1, promotion)
@@ -68,6 +124,8 @@ impl Move {
 
 /// A pawn promotion decision. Use `None` when there is no promotion.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Promotion {
     Queen,
     Rook,
@@ -76,6 +134,23 @@ pub enum Promotion {
     None,
 }
 
+/// The promotion choices available to a pawn reaching the last rank.
+pub const PROMOTIONS: [Promotion; 4] = [
+    Promotion::Queen,
+    Promotion::Rook,
+    Promotion::Bishop,
+    Promotion::Knight,
+];
+
+/// The weight of each attack on a king zone by piece type, in the order of `PieceKind` (pawn,
+/// rook, knight, bishop, queen, king). Heavier pieces make for more dangerous attacks.
+const KING_ZONE_ATTACK_WEIGHTS: [u32; 6] = [1, 3, 2, 2, 5, 0];
+
+/// The standard value of each piece type in centipawns, in the order of `PieceKind` (pawn, rook,
+/// knight, bishop, queen, king). The king is never traded, so it has no value.
+pub const PIECE_VALUES_CP: [i32; 6] = [100, 500, 320, 330, 900, 0];
+
+#[allow(clippy::derivable_impls)]
 impl Default for Promotion {
     fn default() -> Self {
         Promotion::None
@@ -94,6 +169,19 @@ impl Display for Promotion {
     }
 }
 
+impl Promotion {
+    /// The kind of piece the pawn promotes to, or `None` if there is no promotion.
+    pub fn piece_kind(&self) -> Option<PieceKind> {
+        match self {
+            Self::Queen => Some(PieceKind::Queen),
+            Self::Rook => Some(PieceKind::Rook),
+            Self::Bishop => Some(PieceKind::Bishop),
+            Self::Knight => Some(PieceKind::Knight),
+            Self::None => None,
+        }
+    }
+}
+
 impl From<&str> for Promotion {
     fn from(p: &str) -> Self {
         match p {
@@ -112,12 +200,53 @@ impl From<String> for Promotion {
     }
 }
 
+/// What a move did, as returned by `Board::apply_move_with_info`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct MoveInfo {
+    /// Whether a piece was captured (including en-passant).
+    pub capture: bool,
+    /// Whether the king castled.
+    pub castle: bool,
+    /// Whether a pawn was promoted.
+    pub promotion: bool,
+    /// Whether the move gave check.
+    pub check: bool,
+}
+
+impl Display for MoveInfo {
+    /// Lists what the move did, e.g. `capture, check`, or `quiet` if nothing.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let tags = [
+            (self.capture, "capture"),
+            (self.castle, "castle"),
+            (self.promotion, "promotion"),
+            (self.check, "check"),
+        ];
+        let tags = tags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, tag)| *tag)
+            .join(", ");
+        if tags.is_empty() {
+            write!(f, "quiet")
+        } else {
+            write!(f, "{}", tags)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Board {
     /// The `Color::White` board side.
     pub white: BoardSide,
     /// The `Color::Black` board side.
     pub black: BoardSide,
+    /// The side to move.
+    pub turn: Color,
+    /// The number of half-moves since the last capture or pawn move (for the fifty-move rule).
+    pub halfmove_clock: u32,
+    /// The number of the full move, starting at 1 and incremented after Black's move.
+    pub fullmove_number: u32,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -143,8 +272,6 @@ pub struct BoardSide {
 
     /// Inherited; Where all this side's pieces are.
     pub pieces: BitBoard,
-    /// Inherited; squares attacked by this side's pieces.
-    pub attacks: BitBoard,
 
     /// Whether the king has already moved.
     pub king_has_moved: bool,
@@ -172,6 +299,35 @@ impl BoardSide {
         }
     }
 
+    /// Get the type of the piece at the given position if any.
+    ///
+    /// This is cheaper than `get_piece` when the color and position aren't needed.
+    pub fn get_piece_kind(&self, position: Position) -> Option<PieceKind> {
+        let bb = BitBoard::from(position);
+
+        if bb & self.pieces == EMPTY {
+            None
+        } else if bb & self.pawns != EMPTY {
+            Some(PieceKind::Pawn)
+        } else if bb & self.rooks != EMPTY {
+            Some(PieceKind::Rook)
+        } else if bb & self.knights != EMPTY {
+            Some(PieceKind::Knight)
+        } else if bb & self.bishops != EMPTY {
+            Some(PieceKind::Bishop)
+        } else if bb & self.queens != EMPTY {
+            Some(PieceKind::Queen)
+        } else {
+            Some(PieceKind::King)
+        }
+    }
+
+    /// Whether this side can still castle with the rook from the given square, i.e. neither the
+    /// king nor that rook have moved.
+    pub fn can_castle_with(&self, rook: Position) -> bool {
+        !self.king_has_moved && (self.unmoved_rooks & self.rooks & BitBoard::from(rook)) != EMPTY
+    }
+
     /// Return a new instance for the other side.
     pub fn flip(&self) -> Self {
         Self {
@@ -184,7 +340,6 @@ impl BoardSide {
             king: self.king.swap(),
             unmoved_rooks: self.unmoved_rooks.swap(),
             pieces: self.pieces.swap(),
-            attacks: self.attacks.swap(),
             en_passant_target: self.en_passant_target.swap(),
             king_has_moved: self.king_has_moved,
         }
@@ -202,7 +357,6 @@ impl BoardSide {
             king: EMPTY,
             unmoved_rooks: EMPTY,
             pieces: EMPTY,
-            attacks: EMPTY,
             en_passant_target: EMPTY,
             king_has_moved: false,
         };
@@ -216,11 +370,13 @@ impl BoardSide {
         self.refresh();
     }
 
-    /// Re-calculates inherited properties (attacks, pieces, etc.)
+    /// Re-calculates inherited properties (the pieces).
+    ///
+    /// The attacks depend on the pieces of both sides, so they are computed from the board instead
+    /// (see `Board::all_attacks`).
     fn refresh(&mut self) -> &mut Self {
         self.pieces =
             self.pawns | self.rooks | self.knights | self.bishops | self.queens | self.king;
-        self.attacks = BitBoard::default(); // TODO: Calculate attacks
         self
     }
 
@@ -233,6 +389,25 @@ impl BoardSide {
         let queen_score: f32 = self.queens.popcnt() as f32 * 8.0;
         pawn_score + rook_score + knight_score + bishop_score + queen_score
     }
+
+    /// The number of pieces of this side on the board, including the king and pawns.
+    pub fn count_pieces(&self) -> u32 {
+        self.pieces.popcnt()
+    }
+
+    /// The material of this side in centipawns (see `PIECE_VALUES_CP`).
+    fn material_cp(&self) -> i32 {
+        [
+            (self.pawns, PieceKind::Pawn),
+            (self.rooks, PieceKind::Rook),
+            (self.knights, PieceKind::Knight),
+            (self.bishops, PieceKind::Bishop),
+            (self.queens, PieceKind::Queen),
+        ]
+        .iter()
+        .map(|(pieces, kind)| pieces.popcnt() as i32 * PIECE_VALUES_CP[*kind as usize])
+        .sum()
+    }
 }
 
 impl Board {
@@ -354,7 +529,11 @@ impl Board {
                         opponent.mutate(|opponent| opponent.pawns &= !remove_bb);
                     }
                     Pieces::Rook(_, _) => {
-                        opponent.mutate(|opponent| opponent.rooks &= !remove_bb);
+                        // A rook taken on its home square can't be castled with anymore
+                        opponent.mutate(|opponent| {
+                            opponent.rooks &= !remove_bb;
+                            opponent.unmoved_rooks &= !remove_bb;
+                        });
                     }
                     Pieces::Knight(_, _) => {
                         opponent.mutate(|opponent| opponent.knights &= !remove_bb);
@@ -380,8 +559,97 @@ impl Board {
             Color::Black => {
                 self.black = side;
                 self.white = opponent;
+                self.fullmove_number += 1;
             }
         }
+
+        if piece_moved.is_pawn() || piece_taken.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        self.turn = color.opposite();
+    }
+
+    /// Update the board after the side to move played, validating the move first: there must be
+    /// one of its pieces on the origin, a pawn reaching the last rank must be given a promotion
+    /// (and only such a pawn), and the move must be legal.
+    ///
+    /// The board is left untouched when the move is rejected.
+    pub fn try_apply_move(&mut self, m: Move) -> anyhow::Result<()> {
+        let origin = m.0;
+        let piece = self
+            .get_piece(origin)
+            .filter(|piece| piece.get_color() == self.turn)
+            .ok_or_else(|| {
+                anyhow::Error::msg(format!(
+                    "no piece to move for {:?} on {}",
+                    self.turn, origin
+                ))
+            })?;
+
+        check_promotion(piece, m)?;
+        if !self.is_legal_move(m) {
+            return Err(anyhow::Error::msg(format!(
+                "illegal move: {}",
+                m.to_pure_notation()
+            )));
+        }
+
+        self.apply_move(m);
+        Ok(())
+    }
+
+    /// Parses a move in UCI notation (see `Move::from_uci`) in the context of this board: when
+    /// there is a piece on the origin, a pawn reaching the last rank must be given a promotion, and
+    /// only such a pawn can promote. The legality of the move is not checked.
+    pub fn move_from_uci(&self, notation: &str) -> anyhow::Result<Move> {
+        let m = Move::from_uci(notation)?;
+        if let Some(piece) = self.get_piece(m.0) {
+            check_promotion(piece, m)?;
+        }
+        Ok(m)
+    }
+
+    /// Parses a move in UCI notation (see `Move::from_uci`) and plays it like `try_apply_move`.
+    ///
+    /// The board is left untouched when the move is invalid or rejected.
+    pub fn apply_uci(&mut self, notation: &str) -> anyhow::Result<()> {
+        let m = Move::from_uci(notation)?;
+        self.try_apply_move(m)
+    }
+
+    /// Returns a copy of the board after the move, leaving this board untouched.
+    ///
+    /// Like `apply_move`, the move is not validated.
+    pub fn with_move(&self, m: Move) -> Board {
+        let mut outcome = *self;
+        outcome.apply_move(m);
+        outcome
+    }
+
+    /// Update the board after a player moved, like `apply_move`, and return what the move did
+    /// (see `MoveInfo`), e.g. for logging.
+    pub fn apply_move_with_info(&mut self, m: Move) -> MoveInfo {
+        let piece = self
+            .get_piece(m.0)
+            .expect("moved something that doesn't exist");
+        let color = piece.get_color();
+        let capture = match self.get_piece(m.1) {
+            Some(captured) => captured.get_color() != color,
+            // En-passant: a pawn moving diagonally to an empty square
+            None => piece.is_pawn() && m.0.file_x != m.1.file_x,
+        };
+        let castle = piece.is_king() && (m.0.file_x as i8 - m.1.file_x as i8).abs() == 2;
+        let promotion = m.2 != Promotion::None;
+
+        self.apply_move(m);
+        MoveInfo {
+            capture,
+            castle,
+            promotion,
+            check: self.is_in_check(color.opposite()),
+        }
     }
 
     /// Get a list of pawns of the given color.
@@ -400,15 +668,22 @@ impl Board {
             .or_else(|| self.black.get_piece(position))
     }
 
+    /// Get the type of the piece at the given position if any.
+    pub fn get_piece_kind(&self, position: Position) -> Option<PieceKind> {
+        self.white
+            .get_piece_kind(position)
+            .or_else(|| self.black.get_piece_kind(position))
+    }
+
     /// Get the piece at the given position if any.
     pub fn get_piece_value(&self, position: Position) -> u8 {
-        match self.get_piece(position) {
-            Some(Pieces::Pawn(_, _)) => 1,
-            Some(Pieces::Bishop(_, _)) => 3,
-            Some(Pieces::Knight(_, _)) => 3,
-            Some(Pieces::Rook(_, _)) => 5,
-            Some(Pieces::Queen(_, _)) => 8,
-            Some(Pieces::King(_, _)) => 100,
+        match self.get_piece_kind(position) {
+            Some(PieceKind::Pawn) => 1,
+            Some(PieceKind::Bishop) => 3,
+            Some(PieceKind::Knight) => 3,
+            Some(PieceKind::Rook) => 5,
+            Some(PieceKind::Queen) => 8,
+            Some(PieceKind::King) => 100,
             None => 0,
         }
     }
@@ -418,6 +693,14 @@ impl Board {
         self.white.pieces | self.black.pieces
     }
 
+    /// The en-passant target square, if the last move was a pawn double-step: the square behind
+    /// the pawn, where it can be captured.
+    pub fn en_passant_square(&self) -> Option<Position> {
+        (self.white.en_passant_target | self.black.en_passant_target)
+            .into_iter()
+            .next()
+    }
+
     /// Mutates and then refresh inherited properties.
     pub fn mutate<F: FnOnce(&mut Self)>(&mut self, f: F) {
         f(self);
@@ -439,6 +722,52 @@ impl Board {
         }
     }
 
+    /// Returns the pieces on the board as an 8x8 grid, indexed by `[rank][file]` (`[0][0]` is a1,
+    /// `[7][7]` is h8).
+    pub fn to_array(&self) -> [[Option<(Color, PieceKind)>; 8]; 8] {
+        let mut grid = [[None; 8]; 8];
+        for side in [&self.white, &self.black].iter() {
+            for position in side.pieces {
+                grid[position.rank_y as usize][position.file_x as usize] =
+                    side.get_piece_kind(position).map(|kind| (side.color, kind));
+            }
+        }
+        grid
+    }
+
+    /// Builds a board from an 8x8 grid of pieces, indexed like `to_array`, with the given side to
+    /// move. There are no castling rights nor en-passant target, and the clocks start over.
+    pub fn from_array(grid: [[Option<(Color, PieceKind)>; 8]; 8], to_move: Color) -> Board {
+        let side = |color: Color| {
+            BoardSide::new(color, |side| {
+                for (rank, row) in grid.iter().enumerate() {
+                    for (file, piece) in row.iter().enumerate() {
+                        let kind = match piece {
+                            Some((piece_color, kind)) if *piece_color == color => kind,
+                            _ => continue,
+                        };
+                        let square = BitBoard::from(Position::from_int((rank * 8 + file) as u8));
+                        match kind {
+                            PieceKind::Pawn => side.pawns |= square,
+                            PieceKind::Rook => side.rooks |= square,
+                            PieceKind::Knight => side.knights |= square,
+                            PieceKind::Bishop => side.bishops |= square,
+                            PieceKind::Queen => side.queens |= square,
+                            PieceKind::King => side.king |= square,
+                        }
+                    }
+                }
+            })
+        };
+        Board {
+            white: side(Color::White),
+            black: side(Color::Black),
+            turn: to_move,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
     /// Draws the board as an 8x8 ASCII string
     pub fn draw_ascii(&self, side: Color) -> String {
         let mut v = Vec::with_capacity(8);
@@ -464,39 +793,666 @@ impl Board {
         v.join("\n")
     }
 
+    /// Draws the board for a terminal, with rank and file labels. The squares and pieces are
+    /// colored with ANSI escape codes, unless stdout isn't a terminal or `NO_COLOR` is set.
+    ///
+    /// Ref: https://no-color.org
+    pub fn pretty(&self, side: Color) -> String {
+        let colored = std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+        self.pretty_with(side, colored)
+    }
+
+    /// Draws the board like `pretty`, choosing whether to use colors. Without colors, this is
+    /// `draw_ascii` with labels.
+    pub fn pretty_with(&self, side: Color, colored: bool) -> String {
+        let mut lines = Vec::with_capacity(9);
+
+        for row in 0..8 {
+            let rank = if side.is_black() { row } else { 7 - row };
+            let mut line = format!("{} ", rank + 1);
+            for file in 0..8 {
+                let position = Position::from((file, rank));
+                let piece = self.get_piece(position);
+                let letter = piece.map(|p| p.to_letter_notation()).unwrap_or('.');
+
+                if colored {
+                    let background = if position.is_light_square() {
+                        "\x1b[48;5;180m"
+                    } else {
+                        "\x1b[48;5;95m"
+                    };
+                    let foreground = match piece {
+                        Some(piece) if piece.is_white() => "\x1b[1;97m",
+                        _ => "\x1b[1;30m",
+                    };
+                    let letter = if piece.is_some() {
+                        letter.to_ascii_uppercase()
+                    } else {
+                        ' '
+                    };
+                    line.push_str(&format!("{}{} {} \x1b[0m", background, foreground, letter));
+                } else {
+                    line.push(letter);
+                    line.push(' ');
+                }
+            }
+            lines.push(line);
+        }
+
+        lines.push(if colored {
+            "   a  b  c  d  e  f  g  h".to_owned()
+        } else {
+            "  a b c d e f g h".to_owned()
+        });
+        lines.join("\n")
+    }
+
     /// Returns a bitboard of tiles for all potential moves (destinations) by the given side.
     ///
     /// Note: this does not check whether the move is 100% legal (could cause a self-check).
     pub fn get_all_moves(&self, color: Color) -> BitBoard {
         let side = self.get_side(color);
-        let pawns = pieces::pawn::get_pawn_moves_and_attacks(&self, color, &side.pawns);
-        let rooks = pieces::rook::get_rook_sliding_moves(&self, color, &side.rooks);
+        let pawns = pieces::pawn::get_pawn_moves_and_attacks(self, color, &side.pawns);
+        let rooks = pieces::rook::get_rook_sliding_moves(self, color, &side.rooks);
         let knights = side
             .knights
-            .map(|knight| pieces::knight::get_knight_moves(&self, color, knight))
+            .map(|knight| pieces::knight::get_knight_moves(self, color, knight))
             .collect::<BitBoard>();
-        let bishops = pieces::bishop::get_bishop_sliding_moves(&self, color, &side.bishops);
-        let queens = pieces::queen::get_queen_sliding_moves(&self, color, &side.queens);
+        let bishops = pieces::bishop::get_bishop_sliding_moves(self, color, &side.bishops);
+        let queens = pieces::queen::get_queen_sliding_moves(self, color, &side.queens);
         let king = side
             .king
-            .map(|king| pieces::king::get_king_steps(&self, color, king))
+            .map(|king| pieces::king::get_king_steps(self, color, king))
             .collect::<BitBoard>();
 
         pawns | rooks | knights | bishops | queens | king
     }
 
+    /// Returns a bitboard of the squares attacked by the pawns of the given side: the diagonals
+    /// in front of them, whether or not there is a piece to capture.
+    pub fn pawn_attacks(&self, color: Color) -> BitBoard {
+        pieces::pawn::get_pawn_attacks(color, &self.get_side(color).pawns)
+    }
+
+    /// Returns a bitboard of all the squares attacked by the given side: the diagonals in front
+    /// of its pawns (whether or not there is a piece to capture), and the moves of its other
+    /// pieces (without castling).
+    ///
+    /// Note: the squares occupied by the side's own pieces are never included.
+    pub fn all_attacks(&self, color: Color) -> BitBoard {
+        let king = self
+            .get_side(color)
+            .king
+            .map(|king| pieces::king::get_king_steps(self, color, king))
+            .collect::<BitBoard>();
+        self.piece_attacks(color) | king
+    }
+
+    /// Like `all_attacks`, but without the squares attacked by the king.
+    pub fn piece_attacks(&self, color: Color) -> BitBoard {
+        let side = self.get_side(color);
+        let pawns = self.pawn_attacks(color);
+
+        let rooks = pieces::rook::get_rook_sliding_moves(self, color, &side.rooks);
+        let knights = side
+            .knights
+            .map(|knight| pieces::knight::get_knight_moves(self, color, knight))
+            .collect::<BitBoard>();
+        let bishops = pieces::bishop::get_bishop_sliding_moves(self, color, &side.bishops);
+        let queens = pieces::queen::get_queen_sliding_moves(self, color, &side.queens);
+
+        (pawns & !side.pieces) | rooks | knights | bishops | queens
+    }
+
+    /// Measures the danger around the king of the given color: for every enemy piece, the number
+    /// of squares it attacks in the king zone (see `get_king_zone`), multiplied by the weight of
+    /// the piece (pawn 1, knight and bishop 2, rook 3, queen 5).
+    ///
+    /// Returns 0 if the side has no king.
+    pub fn king_zone_attackers(&self, king_color: Color) -> u32 {
+        let king = match self.king_position(king_color) {
+            Some(king) => king,
+            None => return 0,
+        };
+        let zone = pieces::king::get_king_zone(king);
+        let color = king_color.opposite();
+        let side = self.get_side(color);
+
+        let weighted = |kind: PieceKind, attacks: BitBoard| {
+            KING_ZONE_ATTACK_WEIGHTS[kind as usize] * (attacks & zone).popcnt()
+        };
+        let mut total = weighted(PieceKind::Pawn, self.pawn_attacks(color));
+        for knight in side.knights {
+            total += weighted(
+                PieceKind::Knight,
+                pieces::knight::get_knight_moves(self, color, knight),
+            );
+        }
+        for bishop in side.bishops {
+            let attacks =
+                pieces::bishop::get_bishop_sliding_moves(self, color, &BitBoard::from(bishop));
+            total += weighted(PieceKind::Bishop, attacks);
+        }
+        for rook in side.rooks {
+            let attacks = pieces::rook::get_rook_sliding_moves(self, color, &BitBoard::from(rook));
+            total += weighted(PieceKind::Rook, attacks);
+        }
+        for queen in side.queens {
+            let attacks =
+                pieces::queen::get_queen_sliding_moves(self, color, &BitBoard::from(queen));
+            total += weighted(PieceKind::Queen, attacks);
+        }
+        total
+    }
+
+    /// Whether neither side has enough material left to checkmate: bare kings, a single minor
+    /// piece, or only bishops that all stand on squares of the same color.
+    pub fn has_insufficient_material(&self) -> bool {
+        let (white, black) = (&self.white, &self.black);
+        if (white.pawns | white.rooks | white.queens | black.pawns | black.rooks | black.queens)
+            != EMPTY
+        {
+            return false;
+        }
+
+        let knights = white.knights | black.knights;
+        let bishops = white.bishops | black.bishops;
+        if (knights | bishops).popcnt() <= 1 {
+            return true;
+        }
+
+        knights == EMPTY && bishops.map(|p| p.is_light_square()).all_equal()
+    }
+
+    /// Whether the position is dead: no sequence of legal moves can lead to a checkmate, so the
+    /// game is a draw.
+    ///
+    /// This covers the cases of `has_insufficient_material` (bare kings, a single minor piece, or
+    /// same-colored bishops), and the pawn walls that lock both kings out (see
+    /// `is_locked_pawn_wall`). Other dead positions are not detected.
+    pub fn is_dead_position(&self) -> bool {
+        self.has_insufficient_material() || self.is_locked_pawn_wall()
+    }
+
+    /// Whether there are only kings and pawns left, and none of them can ever move but the kings:
+    /// every pawn is blocked by the pawn in front of it, no pawn can capture, and no king can get
+    /// next to an enemy pawn without walking through the squares guarded by the enemy pawns.
+    fn is_locked_pawn_wall(&self) -> bool {
+        let (white, black) = (&self.white, &self.black);
+        let pawns = white.pawns | black.pawns;
+        let others = white.rooks
+            | white.knights
+            | white.bishops
+            | white.queens
+            | black.rooks
+            | black.knights
+            | black.bishops
+            | black.queens;
+        if others != EMPTY || pawns == EMPTY {
+            return false;
+        }
+
+        let blocked = |side: &BoardSide| {
+            let forward = if side.color.is_white() { 1 } else { -1 };
+            side.pawns.into_iter().all(|pawn| {
+                pawn.offset(0, forward)
+                    .is_some_and(|front| pawns & BitBoard::from(front) != EMPTY)
+            })
+        };
+        if !blocked(white)
+            || !blocked(black)
+            || self.pawn_attacks(Color::White) & black.pawns != EMPTY
+            || self.pawn_attacks(Color::Black) & white.pawns != EMPTY
+        {
+            return false;
+        }
+
+        [white, black].iter().all(|side| {
+            let enemy = self.get_side(side.color.opposite());
+            let region = self.king_region(side.color, pawns | self.pawn_attacks(enemy.color));
+            let reach = region
+                .map(pieces::king::get_king_attacks)
+                .collect::<BitBoard>();
+            reach & enemy.pawns == EMPTY
+        })
+    }
+
+    /// The squares the king of the given color can walk to from where it stands, avoiding the
+    /// given squares.
+    fn king_region(&self, color: Color, avoid: BitBoard) -> BitBoard {
+        let mut region = self.get_side(color).king;
+        let mut frontier = region;
+        while frontier != EMPTY {
+            frontier = frontier
+                .map(pieces::king::get_king_attacks)
+                .collect::<BitBoard>()
+                & !avoid
+                & !region;
+            region |= frontier;
+        }
+        region
+    }
+
+    /// Checks that the position makes sense before trusting it (e.g. from an external FEN):
+    /// each side has exactly one king, there are no pawns on the first or last rank, the side
+    /// that just moved isn't in check, and the castling rights match the king and rook placement.
+    ///
+    /// Returns the first violation found, if any.
+    pub fn is_legal_position(&self) -> Result<(), String> {
+        for side in [&self.white, &self.black].iter() {
+            let kings = side.king.popcnt();
+            if kings != 1 {
+                return Err(format!("{:?} has {} kings", side.color, kings));
+            }
+
+            if side.pawns & (RANK_1 | RANK_8) != EMPTY {
+                return Err(format!("{:?} has pawns on the back rank", side.color));
+            }
+
+            if !side.king_has_moved && side.unmoved_rooks != EMPTY {
+                let home = if side.color.is_white() { "e1" } else { "e8" };
+                if self.king_position(side.color) != Some(home.into()) {
+                    return Err(format!(
+                        "{:?} can castle, but its king isn't on {}",
+                        side.color, home
+                    ));
+                }
+                if let Some(rook) = (side.unmoved_rooks & !side.rooks).next() {
+                    return Err(format!(
+                        "{:?} can castle, but there is no rook on {}",
+                        side.color, rook
+                    ));
+                }
+            }
+        }
+
+        if self.is_in_check(self.turn.opposite()) {
+            return Err(format!(
+                "{:?} is in check, but it's {:?}'s turn",
+                self.turn.opposite(),
+                self.turn
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the square of the king of the given side, or `None` if it has no king (or more
+    /// than one, which isn't a legal position either).
+    pub fn king_position(&self, color: Color) -> Option<Position> {
+        let king = self.get_side(color).king;
+        if king.popcnt() == 1 {
+            Some(king.to_position())
+        } else {
+            None
+        }
+    }
+
     /// Returns whether the given side is in check.
     pub fn is_in_check(&self, color: Color) -> bool {
         let side = self.get_side(color);
         (self.get_all_moves(color.opposite()) & side.king).popcnt() != 0
     }
 
+    /// Returns whether the given side is checkmated: in check, without any legal move.
+    pub fn is_checkmate(&self, color: Color) -> bool {
+        self.checkers(color) != EMPTY && self.legal_move_count(color) == 0
+    }
+
+    /// Returns a bitboard of the opponent's pieces giving check to the king of the given side.
+    ///
+    /// With a single checker, the check can be evaded by moving the king, capturing the checker,
+    /// or blocking it (if sliding). With two checkers, only king moves can evade it.
+    pub fn checkers(&self, color: Color) -> BitBoard {
+        let king = self.get_side(color).king;
+        let opponent = self.get_side(color.opposite());
+
+        // Look from the king's square, as if it were each kind of piece
+        let pawn_attacks = pieces::pawn::get_pawn_attacks(color, &king);
+        let knight_attacks = king
+            .map(|king| pieces::knight::get_knight_moves(self, color, king))
+            .collect::<BitBoard>();
+        let straight_attacks = pieces::rook::get_rook_sliding_moves(self, color, &king);
+        let diagonal_attacks = pieces::bishop::get_bishop_sliding_moves(self, color, &king);
+
+        (pawn_attacks & opponent.pawns)
+            | (knight_attacks & opponent.knights)
+            | (straight_attacks & (opponent.rooks | opponent.queens))
+            | (diagonal_attacks & (opponent.bishops | opponent.queens))
+    }
+
+    /// Returns whether the move gives check to the opponent's king, directly or by uncovering a
+    /// sliding piece (including castling, en-passant and promotions).
+    ///
+    /// The check is found by looking at the pieces attacking the king's square once the move is
+    /// played, without playing it on a copy of the board.
+    pub fn gives_check(&self, m: Move) -> bool {
+        let Move(origin, destination, promotion) = m;
+        let (color, kind) = match self.get_piece(origin) {
+            Some(piece) => (piece.get_color(), piece.get_kind()),
+            None => return false,
+        };
+        let king = match self.king_position(color.opposite()) {
+            Some(king) => king,
+            None => return false,
+        };
+        let kind = promotion.piece_kind().unwrap_or(kind);
+        let side = self.get_side(color);
+
+        // The squares vacated and filled by the move, and the pieces of the side after it
+        let mut vacated = BitBoard::from(origin);
+        let mut filled = BitBoard::from(destination);
+        let mut rooks = side.rooks;
+        if kind == PieceKind::King {
+            if let Some(Move(rook_origin, rook_destination, _)) = get_castling_rook_move(&m) {
+                vacated |= BitBoard::from(rook_origin);
+                filled |= BitBoard::from(rook_destination);
+                rooks = (rooks & !BitBoard::from(rook_origin)) | BitBoard::from(rook_destination);
+            }
+        }
+        if kind == PieceKind::Pawn
+            && origin.file_x != destination.file_x
+            && self.get_piece(destination).is_none()
+        {
+            // En-passant: the captured pawn is behind the destination
+            vacated |= BitBoard::from(destination.backwards(color, 1));
+        }
+        let occupied = (self.get_bitboard() & !vacated) | filled;
+        let placed = |pieces: BitBoard, pieces_kind: PieceKind| {
+            let pieces = pieces & !BitBoard::from(origin);
+            if pieces_kind == kind {
+                pieces | BitBoard::from(destination)
+            } else {
+                pieces
+            }
+        };
+        let pawns = placed(side.pawns, PieceKind::Pawn);
+        let knights = placed(side.knights, PieceKind::Knight);
+        let bishops = placed(side.bishops, PieceKind::Bishop);
+        let rooks = placed(rooks, PieceKind::Rook);
+        let queens = placed(side.queens, PieceKind::Queen);
+
+        // Look from the king's square, as if it were each kind of piece (see `checkers`)
+        let king_bb = BitBoard::from(king);
+        let straight = pieces::sliding::get_straight_attacks(occupied, king);
+        let diagonal = pieces::sliding::get_diagonal_attacks(occupied, king);
+        (pieces::pawn::get_pawn_attacks(color.opposite(), &king_bb) & pawns) != EMPTY
+            || (pieces::knight::get_knight_attacks(king) & knights) != EMPTY
+            || (straight & (rooks | queens)) != EMPTY
+            || (diagonal & (bishops | queens)) != EMPTY
+    }
+
+    /// Returns a bitboard of the pieces of the given side that are pinned to their king: each one
+    /// stands alone between the king and an opponent's rook, bishop or queen attacking along that
+    /// line, so it can only move along the line (if at all).
+    ///
+    /// For example, a knight pinned to its king has no legal moves.
+    pub fn pinned_pieces(&self, color: Color) -> BitBoard {
+        let side = self.get_side(color);
+        let opponent = self.get_side(color.opposite());
+        let occupied = self.get_bitboard();
+
+        let mut pinned = EMPTY;
+        for king in side.king {
+            let straight = (opponent.rooks | opponent.queens)
+                .filter(|slider| slider.file_x == king.file_x || slider.rank_y == king.rank_y);
+            let diagonal = (opponent.bishops | opponent.queens)
+                .filter(|slider| slider.file_x != king.file_x && slider.rank_y != king.rank_y);
+            for slider in straight.chain(diagonal) {
+                let blockers = pieces::sliding::get_squares_between(slider, king) & occupied;
+                if blockers.popcnt() == 1 && (blockers & side.pieces) != EMPTY {
+                    pinned |= blockers;
+                }
+            }
+        }
+        pinned
+    }
+
+    /// Returns the list of potential moves by the given side, including castling and all the
+    /// promotion choices.
+    ///
+    /// Note: this does not check whether the move is 100% legal (could cause a self-check).
+    /// Use `legal_moves` for that.
+    ///
+    /// The moves are sorted in the same order as `legal_moves`.
+    pub fn pseudo_legal_moves(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.for_each_pseudo_legal_move(color, |m| moves.push(m));
+        sort_moves(&mut moves);
+        moves
+    }
+
+    /// Returns the list of legal captures by the given side, including en-passant, and the legal
+    /// promotions (capturing or not), for the quiescence search.
+    ///
+    /// The moves are generated directly to the opponent's pieces and the last ranks, rather than
+    /// filtering all the legal moves, and are sorted in the same order as `legal_moves`.
+    pub fn generate_captures(&self, color: Color) -> Vec<Move> {
+        let opponent = self.get_side(color.opposite());
+        let mut moves = Vec::with_capacity(16);
+        self.for_each_legal_move_to(color, opponent.pieces, |m| moves.push(m));
+        sort_moves(&mut moves);
+        moves
+    }
+
+    /// Calls the given function with every potential move by the given side (see
+    /// `pseudo_legal_moves`), without allocating.
+    fn for_each_pseudo_legal_move<F: FnMut(Move)>(&self, color: Color, f: F) {
+        self.for_each_pseudo_legal_move_to(color, !EMPTY, f);
+    }
+
+    /// Calls the given function with every potential move by the given side (see
+    /// `pseudo_legal_moves`) to one of the target squares. Pawns can also capture en-passant and
+    /// promote, whatever the targets.
+    fn for_each_pseudo_legal_move_to<F: FnMut(Move)>(
+        &self,
+        color: Color,
+        targets: BitBoard,
+        mut f: F,
+    ) {
+        let side = self.get_side(color);
+        let pawn_targets =
+            targets | self.get_side(color.opposite()).en_passant_target | RANK_1 | RANK_8;
+
+        for pawn in side.pawns {
+            let destinations =
+                pieces::pawn::get_pawn_moves_and_attacks(self, color, &BitBoard::from(pawn))
+                    & pawn_targets;
+            for destination in destinations {
+                let m = Move::from((pawn, destination));
+                if self.is_promotion_move(m) {
+                    for promotion in &PROMOTIONS {
+                        f(m.with_promotion(*promotion));
+                    }
+                } else {
+                    f(m);
+                }
+            }
+        }
+        for rook in side.rooks {
+            (pieces::rook::get_rook_sliding_moves(self, color, &BitBoard::from(rook)) & targets)
+                .for_each(|destination| f(Move::from((rook, destination))));
+        }
+        for knight in side.knights {
+            (pieces::knight::get_knight_moves(self, color, knight) & targets)
+                .for_each(|destination| f(Move::from((knight, destination))));
+        }
+        for bishop in side.bishops {
+            (pieces::bishop::get_bishop_sliding_moves(self, color, &BitBoard::from(bishop))
+                & targets)
+                .for_each(|destination| f(Move::from((bishop, destination))));
+        }
+        for queen in side.queens {
+            (pieces::queen::get_queen_sliding_moves(self, color, &BitBoard::from(queen)) & targets)
+                .for_each(|destination| f(Move::from((queen, destination))));
+        }
+        for king in side.king {
+            ((pieces::king::get_king_steps(self, color, king)
+                | pieces::king::get_king_castling_moves(self, color))
+                & targets)
+                .for_each(|destination| f(Move::from((king, destination))));
+        }
+    }
+
+    /// Returns the list of legal moves by the given side.
+    ///
+    /// Each potential move is simulated on a copy of the board, and discarded if it leaves the
+    /// king in check. Since the simulation goes through `apply_move`, this also covers the moves
+    /// where the captured piece isn't on the destination square (en-passant exposing the king on
+    /// its rank).
+    ///
+    /// The moves are sorted by origin square, then by destination square (from a1 to h8), and
+    /// promotions to the same square are listed as queen, rook, bishop, knight. The order only
+    /// depends on the position, so that the search and the tests are reproducible; the search
+    /// applies its own move ordering on top of it.
+    pub fn legal_moves(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.for_each_legal_move(color, |m| moves.push(m));
+        sort_moves(&mut moves);
+        moves
+    }
+
+    /// Returns the number of legal moves by the given side, without allocating.
+    pub fn legal_move_count(&self, color: Color) -> usize {
+        let mut count = 0;
+        self.for_each_legal_move(color, |_| count += 1);
+        count
+    }
+
+    /// Calls the given function with every legal move by the given side.
+    ///
+    /// Out of check, the moves of the pieces that aren't pinned (see `pinned_pieces`) can't expose
+    /// the king, so they are legal without simulating them; only the king moves, the pinned
+    /// pieces and en-passant captures are checked.
+    fn for_each_legal_move<F: FnMut(Move)>(&self, color: Color, f: F) {
+        self.for_each_legal_move_to(color, !EMPTY, f);
+    }
+
+    /// Calls the given function with every legal move by the given side to one of the target
+    /// squares (see `for_each_pseudo_legal_move_to`).
+    fn for_each_legal_move_to<F: FnMut(Move)>(&self, color: Color, targets: BitBoard, mut f: F) {
+        let side = self.get_side(color);
+        let king = side.king;
+        let evasions = self.get_evasion_squares(color);
+        let unpinned = if evasions == !EMPTY {
+            side.pieces & !king & !self.pinned_pieces(color)
+        } else {
+            EMPTY
+        };
+        self.for_each_pseudo_legal_move_to(color, targets, |m| {
+            let is_en_passant = (side.pawns & BitBoard::from(m.0)) != EMPTY
+                && m.0.file_x != m.1.file_x
+                && self.get_piece(m.1).is_none();
+            if (unpinned & BitBoard::from(m.0)) != EMPTY && !is_en_passant {
+                f(m);
+                return;
+            }
+            let is_evasion =
+                king == BitBoard::from(m.0) || (evasions & BitBoard::from(m.1)) != EMPTY;
+            if is_evasion && self.is_legal_pseudo_move(m, color) {
+                f(m);
+            }
+        });
+    }
+
+    /// Returns the destinations where pieces other than the king can move to, when evading check.
+    ///
+    /// Out of check, this is every square. In single check, this is the checker (to capture it)
+    /// and the squares between a sliding checker and the king (to block it). In double check,
+    /// only the king can move, so this is empty.
+    fn get_evasion_squares(&self, color: Color) -> BitBoard {
+        let checkers = self.checkers(color);
+        match (checkers.popcnt(), self.king_position(color)) {
+            (0, _) | (_, None) => !EMPTY,
+            (1, Some(king)) => {
+                let checker = checkers.to_position();
+                let opponent = self.get_side(color.opposite());
+
+                // A checking pawn can also be captured en-passant
+                let en_passant = if (checkers & opponent.pawns) != EMPTY {
+                    opponent.en_passant_target
+                } else {
+                    EMPTY
+                };
+
+                checkers | pieces::sliding::get_squares_between(checker, king) | en_passant
+            }
+            _ => EMPTY,
+        }
+    }
+
+    /// Whether the move takes a pawn to the last rank, where it must promote. The promotion of
+    /// the move itself doesn't matter.
+    pub fn is_promotion_move(&self, m: Move) -> bool {
+        let reaches_last_rank = m.1.rank_y == 0 || m.1.rank_y == 7;
+        reaches_last_rank && self.get_piece(m.0).is_some_and(|piece| piece.is_pawn())
+    }
+
+    /// Whether the given move is legal in this position.
+    pub fn is_legal_move(&self, m: Move) -> bool {
+        match self.get_piece(m.0) {
+            Some(piece) => self.legal_moves(piece.get_color()).contains(&m),
+            None => false,
+        }
+    }
+
+    /// Whether a move coming out of `pseudo_legal_moves` is legal.
+    fn is_legal_pseudo_move(&self, m: Move, color: Color) -> bool {
+        // Castling is not allowed out of check, or through an attacked square
+        let is_king = self.get_side(color).king == BitBoard::from(m.0);
+        if let Some(Move(_, rook_destination, _)) = get_castling_rook_move(&m).filter(|_| is_king) {
+            if self.is_in_check(color) {
+                return false;
+            }
+            let mut passing = *self;
+            passing.apply_move(Move::from((m.0, rook_destination)));
+            if passing.is_in_check(color) {
+                return false;
+            }
+        }
+
+        let outcome = self.with_move(m);
+        !outcome.is_in_check(color)
+    }
+
     /// Evaluate the score of a side's pieces, based on the piece types and positions.
     pub fn piecewise_score(&self, color: Color) -> f32 {
         let side = self.get_side(color);
         let opponent = self.get_side(color.opposite());
         side.piecewise_score() - opponent.piecewise_score()
     }
+
+    /// The number of pieces on the board, for both sides, including the kings and pawns.
+    ///
+    /// For example, tablebases only cover positions with few pieces (e.g. 6 or less).
+    pub fn count_pieces(&self) -> u32 {
+        self.get_bitboard().popcnt()
+    }
+
+    /// The material balance of a side in centipawns, with the standard piece values (see
+    /// `PIECE_VALUES_CP`): positive when the side has more material than its opponent.
+    pub fn material_balance_cp(&self, color: Color) -> i32 {
+        self.get_side(color).material_cp() - self.get_side(color.opposite()).material_cp()
+    }
+}
+
+/// Checks that the move promotes if and only if the piece is a pawn reaching the last rank.
+fn check_promotion(piece: Pieces, m: Move) -> anyhow::Result<()> {
+    let Move(_, destination, promotion) = m;
+    let reaches_last_rank = destination.rank_y == 0 || destination.rank_y == 7;
+    if piece.is_pawn() && reaches_last_rank && promotion == Promotion::None {
+        return Err(anyhow::Error::msg(format!(
+            "move requires a promotion choice: {}",
+            m.to_pure_notation()
+        )));
+    }
+    if promotion != Promotion::None && !(piece.is_pawn() && reaches_last_rank) {
+        return Err(anyhow::Error::msg(format!(
+            "move can't promote: {}",
+            m.to_pure_notation()
+        )));
+    }
+    Ok(())
 }
 
 impl Default for Board {
@@ -520,10 +1476,22 @@ impl Default for Board {
 
         let black = white.flip();
 
-        Self { white, black }
+        Self {
+            white,
+            black,
+            turn: Color::White,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
     }
 }
 
+/// Sorts the moves by origin square, then by destination square.
+/// The sort is stable, so promotions keep the generation order.
+fn sort_moves(moves: &mut [Move]) {
+    moves.sort_by_key(|m| (m.0.to_int(), m.1.to_int()));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -643,6 +1611,87 @@ mod tests {
         assert!(promoted.is_queen());
     }
 
+    #[test]
+    fn test_get_piece_kind() {
+        let board = Board::from_fen(
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 4 5",
+        )
+        .unwrap();
+        for side in [board.white, board.black].iter() {
+            for i in 0..64 {
+                let position = Position::from_int(i);
+                assert_eq!(
+                    side.get_piece_kind(position),
+                    side.get_piece(position).map(|p| p.get_kind()),
+                    "{}",
+                    position
+                );
+            }
+        }
+        assert_eq!(board.get_piece_kind("c4".into()), Some(PieceKind::Bishop));
+        assert_eq!(board.get_piece_kind("c6".into()), Some(PieceKind::Knight));
+        assert_eq!(board.get_piece_kind("e5".into()), Some(PieceKind::Pawn));
+        assert_eq!(board.get_piece_kind("d4".into()), None);
+    }
+
+    #[test]
+    fn test_has_insufficient_material() {
+        let insufficient = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/4K3 w - - 0 1",
+            // Bishops on the same color (f8 and c1 are both dark)
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K1B1 w - - 0 1",
+        ];
+        for fen in insufficient.iter() {
+            assert!(
+                Board::from_fen(fen).unwrap().has_insufficient_material(),
+                "{}",
+                fen
+            );
+        }
+
+        let sufficient = [
+            Board::default().to_fen(),
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".into(),
+            "4k3/8/8/8/8/8/8/4KR2 w - - 0 1".into(),
+            "4k3/8/8/8/8/8/8/3NKN2 w - - 0 1".into(),
+            "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1".into(),
+            // Bishops on opposite colors (f8 is dark, f1 is light)
+            "4kb2/8/8/8/8/8/8/4KB2 w - - 0 1".into(),
+        ];
+        for fen in sufficient.iter() {
+            assert!(
+                !Board::from_fen(fen).unwrap().has_insufficient_material(),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_order() {
+        let board = Board::default();
+        let moves = board.legal_moves(Color::White);
+        assert_eq!(moves, board.legal_moves(Color::White));
+        assert_eq!(moves.first(), Some(&("b1", "a3").into()));
+        assert_eq!(moves.last(), Some(&("h2", "h4").into()));
+        assert!(moves
+            .windows(2)
+            .all(|w| (w[0].0.to_int(), w[0].1.to_int()) < (w[1].0.to_int(), w[1].1.to_int())));
+
+        // Promotions to the same square follow the `PROMOTIONS` order
+        let board = Board::from_fen("7k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotions = board
+            .legal_moves(Color::White)
+            .into_iter()
+            .filter(|m| m.0 == "e7".into())
+            .map(|m| m.2)
+            .collect::<Vec<Promotion>>();
+        assert_eq!(promotions, PROMOTIONS.to_vec());
+    }
+
     #[test]
     fn test_queenside_castling() {
         // Initialize with a board that has the queenside rooks and kings only.
@@ -741,10 +1790,755 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_move_from_uci() {
+        assert_eq!(Move::from_uci("e2e4").unwrap(), ("e2", "e4").into());
+        assert_eq!(
+            Move::from_uci("c7d8q").unwrap(),
+            Move("c7".into(), "d8".into(), Promotion::Queen)
+        );
+        assert_eq!(
+            Move::from_uci("a2a1n").unwrap(),
+            ("a2", "a1", Promotion::Knight).into()
+        );
+        assert_eq!(Move::from_uci("e7e8q").unwrap().to_uci(), "e7e8q");
+
+        for invalid in [
+            "", "e2", "e2e", "e2e9", "i2e4", "e2e4k", "e2e4qq", "é2e4", "e2 e4", "e2e4q", "a7a6n",
+        ]
+        .iter()
+        {
+            assert!(Move::from_uci(invalid).is_err(), "{}", invalid);
+        }
+
+        // With the board: pawns reaching the last rank must promote, and only them
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.move_from_uci("a7a8q").unwrap(),
+            ("a7", "a8", Promotion::Queen).into()
+        );
+        assert!(board.move_from_uci("a7a8").is_err());
+        assert!(board.move_from_uci("a1a8q").is_err());
+        assert_eq!(board.move_from_uci("a1a7").unwrap(), ("a1", "a7").into());
+    }
+
+    #[test]
+    fn test_flip_castling_rights() {
+        // White can only castle kingside, Black only queenside
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+
+        let white = board.white.flip();
+        assert_eq!(white.color, Color::Black);
+        assert!(white.can_castle_with("h8".into()));
+        assert!(!white.can_castle_with("a8".into()));
+        let black = board.black.flip();
+        assert_eq!(black.color, Color::White);
+        assert!(black.can_castle_with("a1".into()));
+        assert!(!black.can_castle_with("h1".into()));
+
+        // The mirrored board has the mirrored rights
+        let mirrored = Board {
+            white: black,
+            black: white,
+            turn: Color::Black,
+            ..board
+        };
+        assert_eq!(mirrored.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 0 1");
+        assert!(mirrored
+            .legal_moves(Color::Black)
+            .contains(&("e8", "g8").into()));
+        assert!(!mirrored
+            .legal_moves(Color::Black)
+            .contains(&("e8", "c8").into()));
+
+        // A king that moved loses both rights
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        let white = board.white.flip();
+        assert!(!white.can_castle_with("h8".into()));
+        assert!(!white.can_castle_with("a8".into()));
+    }
+
     #[test]
     fn test_is_in_check() {
         let board =
             Board::from_fen("rnb1k1nr/pp1p1ppp/P1PPP3/8/4P3/8/7b/3q1K2 w kq - 0 1").unwrap();
         assert!(board.is_in_check(Color::White));
     }
+
+    #[test]
+    fn test_king_position() {
+        let board = Board::default();
+        assert_eq!(board.king_position(Color::White), Some("e1".into()));
+        assert_eq!(board.king_position(Color::Black), Some("e8".into()));
+
+        // Without a king, rather than a1
+        let board = Board::from_fen("8/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.king_position(Color::White), Some("e1".into()));
+        assert_eq!(board.king_position(Color::Black), None);
+        assert!(!board.is_in_check(Color::Black));
+        assert_eq!(board.king_zone_attackers(Color::Black), 0);
+        assert!(!board.legal_moves(Color::White).is_empty());
+    }
+
+    #[test]
+    fn test_pawn_attacks() {
+        let board = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.pawn_attacks(Color::White),
+            BitBoard::from_position("d5") | BitBoard::from_position("f5")
+        );
+        assert_eq!(board.pawn_attacks(Color::Black), BitBoard::default());
+
+        // Edge pawns only attack one square, whether or not there is a piece there
+        let board = Board::from_fen("4k3/7p/8/8/8/8/P7/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.pawn_attacks(Color::White),
+            BitBoard::from_position("b3")
+        );
+        assert_eq!(
+            board.pawn_attacks(Color::Black),
+            BitBoard::from_position("g6")
+        );
+    }
+
+    #[test]
+    fn test_material_balance_cp() {
+        let board = Board::default();
+        assert_eq!(board.material_balance_cp(Color::White), 0);
+
+        // White has a rook and a pawn against Black's bishop and knight
+        let board = Board::from_fen("2b1k3/3n4/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.material_balance_cp(Color::White), 600 - 650);
+        assert_eq!(board.material_balance_cp(Color::Black), 50);
+    }
+
+    #[test]
+    fn test_is_dead_position() {
+        let dead = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            // Bishops on the same color (f8 and c1 are both dark)
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ];
+        for fen in dead.iter() {
+            assert!(Board::from_fen(fen).unwrap().is_dead_position(), "{}", fen);
+        }
+
+        let alive = [
+            "4k3/8/8/8/8/8/8/4KR2 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        ];
+        for fen in alive.iter() {
+            assert!(!Board::from_fen(fen).unwrap().is_dead_position(), "{}", fen);
+        }
+
+        // A pawn wall that locks both kings out
+        let board = Board::from_fen("8/8/1k6/p1p1p1p1/P1P1P1P1/8/4K3/8 w - - 0 1").unwrap();
+        assert!(!board.has_insufficient_material());
+        assert!(board.is_dead_position());
+
+        let alive = [
+            // The white king gets through the h-file, to the e5 pawn
+            "8/8/1k6/p1p1p3/P1P1P3/8/4K3/8 w - - 0 1",
+            // The pawns on the a and b files can take each other
+            "8/8/1k6/pp2p1p1/PP2P1P1/8/4K3/8 w - - 0 1",
+            // The h3 pawn can move
+            "8/8/1k6/p1p1p1p1/P1P1P1P1/7P/4K3/8 w - - 0 1",
+            // The white king is already next to an enemy pawn
+            "8/8/1k6/p1p1pKp1/P1P1P1P1/8/8/8 w - - 0 1",
+            // A knight can still move
+            "8/8/1k6/p1p1p1p1/P1P1P1P1/8/4K3/6N1 w - - 0 1",
+        ];
+        for fen in alive.iter() {
+            assert!(!Board::from_fen(fen).unwrap().is_dead_position(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_en_passant_square() {
+        let board = Board::default();
+        assert_eq!(board.en_passant_square(), None);
+
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+            .unwrap();
+        assert_eq!(board.en_passant_square(), Some(Position::from("d6")));
+
+        // Gone after any other move
+        let board = board.with_move(("g1", "f3").into());
+        assert_eq!(board.en_passant_square(), None);
+
+        let board = Board::default().with_move(("e2", "e4").into());
+        assert_eq!(board.en_passant_square(), Some(Position::from("e3")));
+    }
+
+    #[test]
+    fn test_with_move() {
+        let board = Board::default();
+        let outcome = board.with_move(("e2", "e4").into());
+        assert_eq!(board, Board::default());
+
+        let mut expected = Board::default();
+        expected.apply_move(("e2", "e4").into());
+        assert_eq!(outcome, expected);
+        assert_eq!(outcome.turn, Color::Black);
+        assert_eq!(
+            outcome.get_piece_kind(Position::from("e4")),
+            Some(PieceKind::Pawn)
+        );
+    }
+
+    #[test]
+    fn test_count_pieces() {
+        let mut board = Board::default();
+        assert_eq!(board.count_pieces(), 32);
+        assert_eq!(board.white.count_pieces(), 16);
+        assert_eq!(board.black.count_pieces(), 16);
+
+        // 1. e4 d5 2. exd5 Qxd5 3. Nc3 Qxa2 4. Rxa2
+        for m in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a2", "a1a2"].iter() {
+            board.apply_move(Move::from_pure_notation(m));
+        }
+        assert_eq!(board.count_pieces(), 28);
+        assert_eq!(board.white.count_pieces(), 14);
+        assert_eq!(board.black.count_pieces(), 14);
+
+        let board = Board::from_fen("8/8/4k3/8/2R5/8/3PK3/8 w - - 0 1").unwrap();
+        assert_eq!(board.count_pieces(), 4);
+    }
+
+    #[test]
+    fn test_apply_move_with_info() {
+        // Bxf7+ in the Italian game
+        let mut board =
+            Board::from_fen("r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let info = board.apply_move_with_info(("c4", "f7").into());
+        assert_eq!(
+            info,
+            MoveInfo {
+                capture: true,
+                check: true,
+                ..MoveInfo::default()
+            }
+        );
+        assert_eq!(info.to_string(), "capture, check");
+        assert_eq!(board.get_piece_kind("f7".into()), Some(PieceKind::Bishop));
+
+        let mut board = Board::default();
+        assert_eq!(
+            board.apply_move_with_info(("g1", "f3").into()).to_string(),
+            "quiet"
+        );
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            board.apply_move_with_info(("e1", "g1").into()).to_string(),
+            "castle"
+        );
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board
+                .apply_move_with_info(("b7", "b8", Promotion::Queen).into())
+                .to_string(),
+            "promotion, check"
+        );
+    }
+
+    #[test]
+    fn test_is_promotion_move() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(board.is_promotion_move(("b7", "b8").into()));
+        assert!(board.is_promotion_move(("b7", "b8", Promotion::Knight).into()));
+        // Not a pawn
+        assert!(!board.is_promotion_move(("a1", "a8").into()));
+        // Not the last rank
+        assert!(!board.is_promotion_move(("e1", "e2").into()));
+        assert_eq!(
+            board
+                .legal_moves(Color::White)
+                .into_iter()
+                .filter(|m| m.0 == "b7".into())
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_generate_captures() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // Kiwipete
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            // En-passant, with a pinned pawn that can't take it
+            "8/8/8/KPp4r/8/8/8/4k3 w - c6 0 2",
+            "8/8/8/3Pp3/8/8/8/K3k3 w - e6 0 2",
+            // Promotions, capturing or not
+            "1n2k3/P1P5/8/8/8/8/8/4K3 w - - 0 1",
+            // In check
+            "4k3/8/8/8/1b6/8/3P4/4K3 w - - 0 1",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            let color = board.turn;
+            let opponent = board.get_side(color.opposite());
+            let expected = board
+                .legal_moves(color)
+                .into_iter()
+                .filter(|m| {
+                    let en_passant = (opponent.en_passant_target & BitBoard::from(m.1)) != EMPTY
+                        && board.get_piece_kind(m.0) == Some(PieceKind::Pawn);
+                    (opponent.pieces & BitBoard::from(m.1)) != EMPTY
+                        || en_passant
+                        || m.2 != Promotion::None
+                })
+                .collect::<Vec<Move>>();
+            assert_eq!(board.generate_captures(color), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_to_array() {
+        let grid = Board::default().to_array();
+        let back_rank = [
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ];
+        for (file, kind) in back_rank.iter().enumerate() {
+            assert_eq!(grid[0][file], Some((Color::White, *kind)));
+            assert_eq!(grid[1][file], Some((Color::White, PieceKind::Pawn)));
+            assert_eq!(grid[6][file], Some((Color::Black, PieceKind::Pawn)));
+            assert_eq!(grid[7][file], Some((Color::Black, *kind)));
+        }
+        assert!(grid[2..6].iter().flatten().all(Option::is_none));
+
+        let board = Board::from_fen("8/8/4k3/8/2R5/8/3PK3/8 w - - 0 1").unwrap();
+        let grid = board.to_array();
+        assert_eq!(grid[3][2], Some((Color::White, PieceKind::Rook)));
+        assert_eq!(grid[5][4], Some((Color::Black, PieceKind::King)));
+        assert_eq!(grid.iter().flatten().filter(|p| p.is_some()).count(), 4);
+    }
+
+    #[test]
+    fn test_from_array() {
+        let grid = Board::default().to_array();
+        assert_eq!(Board::from_array(grid, Color::White).to_array(), grid);
+        assert_eq!(
+            Board::from_array(grid, Color::Black).to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b - - 0 1"
+        );
+
+        let mut grid = [[None; 8]; 8];
+        grid[0][4] = Some((Color::White, PieceKind::King));
+        grid[3][2] = Some((Color::White, PieceKind::Rook));
+        grid[5][4] = Some((Color::Black, PieceKind::King));
+        grid[6][0] = Some((Color::Black, PieceKind::Pawn));
+        let board = Board::from_array(grid, Color::Black);
+        assert_eq!(board.to_array(), grid);
+        assert_eq!(board.to_fen(), "8/p7/4k3/8/2R5/8/8/4K3 b - - 0 1");
+        assert_eq!(board.is_legal_position(), Ok(()));
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        // The knight on e2 is pinned by the rook, the bishop on d2 by the bishop on a5, and the
+        // knight on b1 is not pinned (the rook on c1 is also in the way)
+        let board = Board::from_fen("3k4/4r3/8/b7/8/8/3BN3/qNR1K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.pinned_pieces(Color::White),
+            BitBoard::from_position("e2") | BitBoard::from_position("d2")
+        );
+        assert_eq!(board.pinned_pieces(Color::Black), EMPTY);
+
+        // The pinned knight has no legal moves, the pinned bishop can only move along the pin
+        let moves = board.legal_moves(Color::White);
+        assert!(!moves.iter().any(|m| m.0 == "e2".into()));
+        assert_eq!(
+            moves
+                .iter()
+                .filter(|m| m.0 == "d2".into())
+                .map(Move::to_pure_notation)
+                .collect::<Vec<String>>(),
+            vec!["d2c3", "d2b4", "d2a5"]
+        );
+
+        // Rooks only pin along files and ranks, bishops along diagonals, queens along both
+        let board = Board::from_fen("4k3/8/8/8/8/2r5/3N4/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pinned_pieces(Color::White), EMPTY);
+        let board = Board::from_fen("4k3/4b3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pinned_pieces(Color::White), EMPTY);
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/q2NK3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.pinned_pieces(Color::White),
+            BitBoard::from_position("d1")
+        );
+        assert_eq!(board.legal_move_count(Color::White), 4);
+    }
+
+    #[test]
+    fn test_all_attacks() {
+        // Only the pawns and knights can reach the third rank
+        let board = Board::default();
+        assert_eq!(board.all_attacks(Color::White), pieces::RANK_3);
+        assert_eq!(board.all_attacks(Color::Black), pieces::RANK_6);
+
+        // Kiwipete: compare against the attacks of each piece
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        for color in [Color::White, Color::Black].iter().copied() {
+            let side = board.get_side(color);
+            let forward = if color.is_white() { 1 } else { -1 };
+            let mut expected = EMPTY;
+            for pawn in side.pawns {
+                for file in [pawn.file_x as i8 - 1, pawn.file_x as i8 + 1]
+                    .iter()
+                    .copied()
+                {
+                    if (0..8).contains(&file) {
+                        let target =
+                            Position::from((file as u8, (pawn.rank_y as i8 + forward) as u8));
+                        if side.get_piece(target).is_none() {
+                            expected |= BitBoard::from(target);
+                        }
+                    }
+                }
+            }
+            for piece in side.rooks | side.bishops | side.queens | side.knights | side.king {
+                expected |= match side.get_piece_kind(piece).unwrap() {
+                    PieceKind::Rook => {
+                        pieces::rook::get_rook_sliding_moves(&board, color, &BitBoard::from(piece))
+                    }
+                    PieceKind::Bishop => pieces::bishop::get_bishop_sliding_moves(
+                        &board,
+                        color,
+                        &BitBoard::from(piece),
+                    ),
+                    PieceKind::Queen => pieces::queen::get_queen_sliding_moves(
+                        &board,
+                        color,
+                        &BitBoard::from(piece),
+                    ),
+                    PieceKind::Knight => pieces::knight::get_knight_moves(&board, color, piece),
+                    _ => pieces::king::get_king_steps(&board, color, piece),
+                };
+            }
+            assert_eq!(board.all_attacks(color), expected, "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn test_is_legal_position() {
+        assert_eq!(Board::default().is_legal_position(), Ok(()));
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(
+            Board::from_fen(kiwipete).unwrap().is_legal_position(),
+            Ok(())
+        );
+
+        let check = |fen: &str| Board::from_fen(fen).unwrap().is_legal_position();
+        assert_eq!(
+            check("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err("Black has 0 kings".to_owned())
+        );
+        assert_eq!(
+            check("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            Err("White has 2 kings".to_owned())
+        );
+        assert_eq!(
+            check("4k3/8/8/8/8/8/8/4K2p w - - 0 1"),
+            Err("Black has pawns on the back rank".to_owned())
+        );
+        assert_eq!(check("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Ok(()));
+        assert_eq!(
+            check("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1"),
+            Err("White is in check, but it's Black's turn".to_owned())
+        );
+        assert_eq!(
+            check("4k3/8/8/8/8/8/8/5K1R w K - 0 1"),
+            Err("White can castle, but its king isn't on e1".to_owned())
+        );
+        assert_eq!(check("r3k3/8/8/8/8/8/8/4K3 w q - 0 1"), Ok(()));
+        assert_eq!(
+            check("4k2r/8/8/8/8/8/8/4K3 w q - 0 1"),
+            Err("Black can castle, but there is no rook on a8".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_try_apply_move() {
+        let fen = "4k3/1P6/8/8/8/8/8/4K2R w K - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        board
+            .try_apply_move(("b7", "b8", Promotion::Knight).into())
+            .unwrap();
+        assert!(board.get_piece("b8".into()).unwrap().is_knight());
+
+        // A pawn reaching the last rank must promote
+        let mut board = Board::from_fen(fen).unwrap();
+        let err = board.try_apply_move(("b7", "b8").into()).unwrap_err();
+        assert_eq!(err.to_string(), "move requires a promotion choice: b7b8");
+        assert_eq!(board, Board::from_fen(fen).unwrap());
+
+        // ... and only such a pawn
+        let err = board
+            .try_apply_move(("h1", "h8", Promotion::Queen).into())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "move can't promote: h1h8q");
+
+        assert!(board.try_apply_move(("e8", "d8").into()).is_err());
+        assert!(board.try_apply_move(("e1", "e3").into()).is_err());
+        board.try_apply_move(("e1", "g1").into()).unwrap();
+        assert_eq!(board.turn, Color::Black);
+    }
+
+    #[test]
+    fn test_king_zone_attackers() {
+        assert_eq!(Board::default().king_zone_attackers(Color::White), 0);
+
+        // The knight on f4 attacks g2 around the castled king
+        let board = Board::from_fen("4k3/8/8/8/5n2/8/5PPP/6K1 w - - 0 1").unwrap();
+        let before = board.king_zone_attackers(Color::White);
+        assert_eq!(before, 2);
+
+        // A queen on h4 also attacks h2 and f2, through h3 and g3
+        let board = Board::from_fen("4k3/8/8/8/5n1q/8/5PPP/6K1 w - - 0 1").unwrap();
+        let after = board.king_zone_attackers(Color::White);
+        assert!(after > before);
+        assert_eq!(after, before + 5 * 2);
+
+        // No king, no danger
+        let board = Board::from_fen("4k3/8/8/8/5n1q/8/5PPP/8 w - - 0 1").unwrap();
+        assert_eq!(board.king_zone_attackers(Color::White), 0);
+    }
+
+    #[test]
+    fn test_pretty() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+
+        for side in [Color::White, Color::Black].iter().copied() {
+            let pretty = board.pretty_with(side, false);
+            let lines = pretty.lines().collect::<Vec<&str>>();
+            assert_eq!(lines.len(), 9);
+            assert_eq!(lines[8], "  a b c d e f g h");
+
+            // Without the labels, this is the ASCII drawing
+            let unlabeled = lines[..8]
+                .iter()
+                .map(|line| &line[2..])
+                .collect::<Vec<&str>>()
+                .join("\n");
+            assert_eq!(unlabeled, board.draw_ascii(side));
+        }
+        assert!(board
+            .pretty_with(Color::White, false)
+            .starts_with("8 r . . . k . . r"));
+        assert!(board
+            .pretty_with(Color::Black, false)
+            .starts_with("1 R . . . K . . R"));
+
+        let colored = board.pretty_with(Color::White, true);
+        assert!(colored.contains("\x1b["));
+        assert_eq!(colored.lines().count(), 9);
+    }
+
+    #[test]
+    fn test_checkers() {
+        let board = Board::default();
+        assert_eq!(board.checkers(Color::White), EMPTY);
+        assert_eq!(board.checkers(Color::Black), EMPTY);
+
+        // Single check by a bishop, the rook on e8 is blocked by the e2 pawn
+        let board = Board::from_fen("4r1k1/8/8/8/1b6/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White), BitBoard::from_position("b4"));
+
+        // Single check by a pawn
+        let board = Board::from_fen("6k1/8/8/8/8/8/3p4/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White), BitBoard::from_position("d2"));
+        let board = Board::from_fen("6k1/5P2/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::Black), BitBoard::from_position("f7"));
+
+        // Double check by a knight and a rook: capturing the knight with the rook isn't enough
+        let board = Board::from_fen("4r1k1/8/8/8/8/3n3R/8/4K3 w - - 0 1").unwrap();
+        let checkers = board.checkers(Color::White);
+        assert_eq!(checkers.popcnt(), 2);
+        assert_eq!(
+            checkers,
+            BitBoard::from_position("e8") | BitBoard::from_position("d3")
+        );
+        let moves = board.legal_moves(Color::White);
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.0 == Position::from("e1")));
+    }
+
+    #[test]
+    fn test_gives_check() {
+        // Direct check
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(board.gives_check(("a1", "a8").into()));
+        assert!(!board.gives_check(("a1", "a7").into()));
+
+        // Every legal move agrees with playing it: discovered checks, castling, en-passant
+        // (uncovering the rook on the rank) and promotions
+        for fen in [
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "8/8/8/k1pP3R/8/8/8/4K3 w - c6 0 1",
+            "3k4/1P6/8/8/8/8/8/4K3 w - - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.legal_moves(Color::White) {
+                let expected = board.with_move(m).checkers(Color::Black) != EMPTY;
+                assert_eq!(board.gives_check(m), expected, "{}: {:?}", fen, m);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legal_move_count() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4r1k1/8/8/8/8/3n3R/8/4K3 w - - 0 1",
+            "8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        ];
+        for fen in fens.iter() {
+            let board = Board::from_fen(fen).unwrap();
+            for color in [Color::White, Color::Black].iter() {
+                assert_eq!(
+                    board.legal_move_count(*color),
+                    board.legal_moves(*color).len()
+                );
+            }
+        }
+
+        assert_eq!(Board::default().legal_move_count(Color::White), 20);
+        // Stalemate
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.legal_move_count(Color::Black), 0);
+    }
+
+    #[test]
+    fn test_check_evasions() {
+        // Double check: only the king can move, to d1, d2 or f1 (the rook and knight cover e2, f2)
+        let board = Board::from_fen("4r1k1/8/8/8/8/3n3R/8/4K3 w - - 0 1").unwrap();
+        let moves = board.legal_moves(Color::White);
+        assert_eq!(moves.len(), 3);
+        assert!(moves.iter().all(|m| m.0 == Position::from("e1")));
+
+        // Single check by the e8 rook: king steps (d2, f1, f2), blocking with the bishop (e2),
+        // the knight (e2, e4) or the queen (e4), or capturing the rook with the queen
+        let board = Board::from_fen("4r1k1/8/8/8/Q7/2N5/8/3BK3 w - - 0 1").unwrap();
+        let moves = board.legal_moves(Color::White);
+        let mut expected: Vec<Move> = vec![
+            ("e1", "d2").into(),
+            ("e1", "f1").into(),
+            ("e1", "f2").into(),
+            ("d1", "e2").into(),
+            ("c3", "e2").into(),
+            ("c3", "e4").into(),
+            ("a4", "e8").into(),
+            ("a4", "e4").into(),
+        ];
+        assert_eq!(moves.len(), expected.len());
+        expected.retain(|m| !moves.contains(m));
+        assert_eq!(expected, vec![]);
+
+        // The same moves come out of the unspecialized generation
+        let unspecialized = board
+            .pseudo_legal_moves(Color::White)
+            .into_iter()
+            .filter(|m| board.is_legal_pseudo_move(*m, Color::White))
+            .collect::<Vec<Move>>();
+        assert_eq!(moves, unspecialized);
+
+        // A checking pawn can be captured en-passant
+        let board = Board::from_fen("8/8/8/2k5/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        assert!(board
+            .legal_moves(Color::Black)
+            .contains(&("d4", "e3").into()));
+    }
+
+    #[test]
+    fn test_legal_moves_apply_cleanly() {
+        // Every generated move can be applied, and leads to a legal position (2 plies deep)
+        fn check(board: &Board, depth: usize) {
+            for m in board.legal_moves(board.turn) {
+                let mut outcome = *board;
+                outcome.apply_move(m);
+                let fen = board.to_fen();
+                assert_eq!(
+                    outcome.is_legal_position(),
+                    Ok(()),
+                    "{} in {}",
+                    m.to_pure_notation(),
+                    fen
+                );
+                assert_eq!(
+                    outcome.white.pieces & outcome.black.pieces,
+                    EMPTY,
+                    "{} in {}",
+                    m.to_pure_notation(),
+                    fen
+                );
+                assert_eq!(outcome.turn, board.turn.opposite());
+                if depth > 1 {
+                    check(&outcome, depth - 1);
+                }
+            }
+        }
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/1P6/8/8/8/8/6p1/4K2R b K - 0 1",
+        ]
+        .iter()
+        {
+            check(&Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_en_passant_exposing_king_is_illegal() {
+        // Taking c6 en-passant would remove both pawns from the 5th rank, exposing the king
+        let board = Board::from_fen("8/8/8/KPp4r/8/8/8/7k w - c6 0 1").unwrap();
+        let moves = board.legal_moves(Color::White);
+        assert!(!moves.contains(&("b5", "c6").into()));
+        assert!(!board.is_legal_move(("b5", "c6").into()));
+        assert!(moves.contains(&("b5", "b6").into()));
+
+        // Without the rook, the en-passant capture is legal
+        let board = Board::from_fen("8/8/8/KPp5/8/8/8/7k w - c6 0 1").unwrap();
+        assert!(board.is_legal_move(("b5", "c6").into()));
+    }
+
+    #[test]
+    fn test_castling_through_check_is_illegal() {
+        // The black rook on f8 attacks f1, which the king passes through when castling kingside
+        let board = Board::from_fen("r3kr2/8/8/8/8/8/8/R3K2R w KQq - 0 1").unwrap();
+        let moves = board.legal_moves(Color::White);
+        assert!(!moves.contains(&("e1", "g1").into()));
+        assert!(moves.contains(&("e1", "c1").into()));
+
+        // Black can only castle queenside
+        let moves = board.legal_moves(Color::Black);
+        assert!(moves.contains(&("e8", "c8").into()));
+        assert!(!moves.contains(&("e8", "g8").into()));
+    }
 }
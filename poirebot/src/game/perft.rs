@@ -0,0 +1,68 @@
+use crate::game::pieces::Color;
+use crate::game::{Board, Move};
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree at the given depth (in plies), starting with
+    /// the given color. This is used to verify the move generator against known results.
+    ///
+    /// Ref: https://www.chessprogramming.org/Perft
+    pub fn perft(&self, depth: usize, color: Color) -> u64 {
+        match depth {
+            0 => 1,
+            1 => self.legal_move_count(color) as u64,
+            _ => self
+                .legal_moves(color)
+                .into_iter()
+                .map(|m| {
+                    let outcome = self.with_move(m);
+                    outcome.perft(depth - 1, color.opposite())
+                })
+                .sum(),
+        }
+    }
+
+    /// Like `perft`, but returns the count of each root move separately, to find which subtree
+    /// disagrees with a reference move generator.
+    pub fn perft_divide(&self, depth: usize, color: Color) -> Vec<(Move, u64)> {
+        self.legal_moves(color)
+            .into_iter()
+            .map(|m| {
+                let outcome = self.with_move(m);
+                (m, outcome.perft(depth.max(1) - 1, color.opposite()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perft_start_position() {
+        let board = Board::default();
+        assert_eq!(board.perft(0, Color::White), 1);
+        assert_eq!(board.perft(1, Color::White), 20);
+        assert_eq!(board.perft(2, Color::White), 400);
+        assert_eq!(board.perft(3, Color::White), 8_902);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        // Castling, en-passant, promotions and pins
+        // Ref: https://www.chessprogramming.org/Perft_Results#Position_2
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(board.perft(1, Color::White), 48);
+        assert_eq!(board.perft(2, Color::White), 2_039);
+        assert_eq!(board.perft(3, Color::White), 97_862);
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let divide = Board::default().perft_divide(2, Color::White);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, count)| *count == 20));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::game::pieces::Color;
+use crate::game::{Board, BoardSide};
+
+/// The phase weight of knights and bishops.
+const MINOR_PHASE: u32 = 1;
+/// The phase weight of rooks.
+const ROOK_PHASE: u32 = 2;
+/// The phase weight of queens.
+const QUEEN_PHASE: u32 = 4;
+/// The total phase weight of the starting position's non-pawn material.
+const TOTAL_PHASE: u32 = MINOR_PHASE * 8 + ROOK_PHASE * 4 + QUEEN_PHASE * 2;
+
+/// The scalar value of a full endgame (see `Board::phase_scalar`).
+pub const ENDGAME_PHASE: u32 = 256;
+
+/// A coarse classification of the game phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl BoardSide {
+    /// The phase weight of this side's non-pawn material.
+    fn phase_material(&self) -> u32 {
+        (self.knights.popcnt() + self.bishops.popcnt()) * MINOR_PHASE
+            + self.rooks.popcnt() * ROOK_PHASE
+            + self.queens.popcnt() * QUEEN_PHASE
+    }
+}
+
+impl Board {
+    /// Returns the game phase as a scalar from 0 (opening, all the non-pawn material is on the
+    /// board) to `ENDGAME_PHASE` (only kings and pawns are left), to interpolate between opening
+    /// and endgame evaluations.
+    ///
+    /// Uses the standard phase weights: knight and bishop 1, rook 2, queen 4.
+    /// Ref: https://www.chessprogramming.org/Tapered_Eval
+    pub fn phase_scalar(&self) -> u32 {
+        let remaining = (self.get_side(Color::White).phase_material()
+            + self.get_side(Color::Black).phase_material())
+        .min(TOTAL_PHASE);
+        ((TOTAL_PHASE - remaining) * ENDGAME_PHASE + TOTAL_PHASE / 2) / TOTAL_PHASE
+    }
+
+    /// Classifies the game phase based on the remaining non-pawn material.
+    pub fn phase(&self) -> Phase {
+        let phase = self.phase_scalar();
+        if phase < ENDGAME_PHASE / 4 {
+            Phase::Opening
+        } else if phase < ENDGAME_PHASE * 3 / 4 {
+            Phase::Middlegame
+        } else {
+            Phase::Endgame
+        }
+    }
+
+    /// Whether the game is in the endgame phase.
+    pub fn is_endgame(&self) -> bool {
+        self.phase() == Phase::Endgame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_position_is_opening() {
+        let board = Board::default();
+        assert_eq!(board.phase_scalar(), 0);
+        assert_eq!(board.phase(), Phase::Opening);
+    }
+
+    #[test]
+    fn test_bare_kings_is_endgame() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.phase_scalar(), ENDGAME_PHASE);
+        assert_eq!(board.phase(), Phase::Endgame);
+        assert!(board.is_endgame());
+        assert!(!Board::default().is_endgame());
+    }
+
+    #[test]
+    fn test_middlegame_phase() {
+        // Queens and a pair of rooks traded
+        let board =
+            Board::from_fen("r1b1kbn1/pppppppp/2n5/8/8/2N5/PPPPPPPP/R1B1KBN1 w Qq - 0 1").unwrap();
+        assert_eq!(board.phase(), Phase::Middlegame);
+    }
+}
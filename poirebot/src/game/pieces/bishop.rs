@@ -8,7 +8,7 @@ use crate::game::Board;
 /// Note that multiple bishops can be passed in the bitboard; to get the moves for individual bishops,
 /// iterate over the bishops and call this function with the singular bitboard for each bishop.
 pub fn get_bishop_sliding_moves(board: &Board, color: Color, origin: &BitBoard) -> BitBoard {
-    get_sliding_diagonal_moves(&board, color, &origin)
+    get_sliding_diagonal_moves(board, color, origin)
 }
 
 #[cfg(test)]
@@ -49,4 +49,19 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn get_bishop_sliding_captures() {
+        // The enemy pawn on c3 blocks the ray in the middle: the squares behind it are not
+        // reachable, and only the destination holding it is a capture
+        let board = Board::from_fen("4k3/8/8/8/8/2p5/8/B3K3 w - - 0 1").unwrap();
+        let moves = super::get_bishop_sliding_moves(&board, Color::White, &BitBoard::from("a1"));
+        let values = moves
+            .map(|destination| (destination, board.get_piece_value(destination)))
+            .collect::<Vec<(Position, u8)>>();
+        assert_eq!(
+            values,
+            vec![(Position::from("b2"), 0), (Position::from("c3"), 1)]
+        );
+    }
 }
@@ -1,5 +1,5 @@
-use crate::bitboard::BitBoard;
-use crate::game::pieces::{Color, FILES, RANKS};
+use crate::bitboard::{BitBoard, Direction};
+use crate::game::pieces::Color;
 use crate::game::position::Position;
 use crate::game::Board;
 
@@ -7,6 +7,21 @@ use crate::game::Board;
 /// Generated at compile-time.
 const KING_MOVES: [BitBoard; 64] = compile_king_moves();
 
+/// A castling option: (rook origin, king destination, squares that must be empty).
+type Castle = (&'static str, &'static str, &'static [&'static str]);
+
+/// The castling options for White (queenside, then kingside).
+const WHITE_CASTLES: [Castle; 2] = [
+    ("a1", "c1", &["b1", "c1", "d1"]),
+    ("h1", "g1", &["f1", "g1"]),
+];
+
+/// The castling options for Black (queenside, then kingside).
+const BLACK_CASTLES: [Castle; 2] = [
+    ("a8", "c8", &["b8", "c8", "d8"]),
+    ("h8", "g8", &["f8", "g8"]),
+];
+
 /// Generates a bitboard with the moves (steps) that can be performed by the king at the given position.
 ///
 /// Note: this doesn't verify if the move is 100% legal, i.e. it could put the king in check.
@@ -20,44 +35,68 @@ pub fn get_king_steps(board: &Board, color: Color, origin: Position) -> BitBoard
     grid & !own_pieces
 }
 
+/// Generates a bitboard with the squares attacked by a king at the given position, whatever stands
+/// on them.
+pub fn get_king_attacks(origin: Position) -> BitBoard {
+    KING_MOVES[origin.to_int() as usize]
+}
+
+/// Generates a bitboard with the zone around a king at the given position: its square and the
+/// squares it could step to, whether or not they are occupied.
+pub fn get_king_zone(origin: Position) -> BitBoard {
+    KING_MOVES[origin.to_int() as usize] | BitBoard::from(origin)
+}
+
+/// Generates a bitboard with the castling destinations available to the king of the given color.
+///
+/// This only checks that the king and the rook haven't moved, and that the squares between them
+/// are empty. It does not verify that the king is out of check, or that it doesn't pass through
+/// an attacked square; see `Board::legal_moves` for that.
+pub fn get_king_castling_moves(board: &Board, color: Color) -> BitBoard {
+    let side = board.get_side(color);
+    let all_pieces = board.get_bitboard();
+    let mut result = BitBoard::default();
+
+    let (king_origin, castles) = match color {
+        Color::White => ("e1", &WHITE_CASTLES),
+        Color::Black => ("e8", &BLACK_CASTLES),
+    };
+
+    if side.king_has_moved || board.king_position(color) != Some(king_origin.into()) {
+        return result;
+    }
+
+    for (rook_origin, destination, between) in castles.iter() {
+        let rook_bb = BitBoard::from_position(*rook_origin);
+        let between_bb = between
+            .iter()
+            .map(|p| BitBoard::from_position(*p))
+            .collect::<BitBoard>();
+
+        if (rook_bb & side.rooks & side.unmoved_rooks).popcnt() == 1
+            && (between_bb & all_pieces).popcnt() == 0
+        {
+            result |= BitBoard::from_position(*destination);
+        }
+    }
+
+    result
+}
+
 /// Generates the BitBoard map for all possible king move grids.
 const fn compile_king_moves() -> [BitBoard; 64] {
     let mut moves: [BitBoard; 64] = [BitBoard(0); 64];
     let mut i = 0usize;
     loop {
-        // Generates the 64 possible positions for a king, with the surrounding steps
-        // To generate the steps, it does the following bitwise operation:
-        // (file | file-1 | file+1) & (rank | rank-1 | rank+1) &!center
-        // ... where file-1 is the file directly West of the current one, if any
-        // ... where file+1 is the file directly East of the current one, if any
-        // ... where rank-1 is the rank directly South of the current one, if any
-        // ... where rank+1 is the rank directly North of the current one, if any
-
-        let file = i % 8_usize;
-        let rank = i / 8_usize;
-        let center = 1u64 << i;
-
-        let mut files = FILES[file];
-        let mut ranks = RANKS[rank];
-
-        if file != 0 {
-            files = BitBoard(files.0 | FILES[file - 1].0);
-        }
-
-        if file != 7 {
-            files = BitBoard(files.0 | FILES[file + 1].0);
-        }
+        // Generates the 64 possible positions for a king, with a step in every direction
+        let center = BitBoard(1u64 << i);
 
-        if rank != 0 {
-            ranks = BitBoard(ranks.0 | RANKS[rank - 1].0);
+        let mut d = 0usize;
+        while d < Direction::ALL.len() {
+            moves[i].0 |= center.shift(Direction::ALL[d]).0;
+            d += 1;
         }
 
-        if rank != 7 {
-            ranks = BitBoard(ranks.0 | RANKS[rank + 1].0);
-        }
-
-        moves[i] = BitBoard(files.0 & ranks.0 & !center);
-
         i += 1;
         if i == 64 {
             break;
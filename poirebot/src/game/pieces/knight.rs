@@ -1,5 +1,5 @@
-use crate::bitboard::BitBoard;
-use crate::game::pieces::{Color, FILE_A, FILE_B, FILE_G, FILE_H};
+use crate::bitboard::{BitBoard, Direction};
+use crate::game::pieces::Color;
 use crate::game::position::Position;
 use crate::game::Board;
 
@@ -18,37 +18,44 @@ pub fn get_knight_moves(board: &Board, color: Color, origin: Position) -> BitBoa
     grid & !own_pieces
 }
 
+/// Generates a bitboard with the squares attacked by a knight at the given position, whatever
+/// stands on them.
+pub fn get_knight_attacks(origin: Position) -> BitBoard {
+    KNIGHT_MOVES[origin.to_int() as usize]
+}
+
 /// Generates the BitBoard map for all possible knight move grids.
 const fn compile_knight_moves() -> [BitBoard; 64] {
     let mut moves: [BitBoard; 64] = [BitBoard(0); 64];
     let mut i = 0usize;
     loop {
-        // Generates the 64 possible positions for a knight, with the possible moves
-        let center = 1u64 << i;
+        // Generates the 64 possible positions for a knight, with the possible moves: a straight
+        // step, then a diagonal one away from the center
+        let center = BitBoard(1u64 << i);
 
         // North-North-East
-        moves[i].0 |= (center << 17) & !FILE_A.0;
+        moves[i].0 |= center.shift(Direction::N).shift(Direction::NE).0;
 
         // North-East-East
-        moves[i].0 |= (center << 10) & !FILE_A.0 & !FILE_B.0;
+        moves[i].0 |= center.shift(Direction::E).shift(Direction::NE).0;
 
         // South-East-East
-        moves[i].0 |= (center >> 6) & !FILE_A.0 & !FILE_B.0;
+        moves[i].0 |= center.shift(Direction::E).shift(Direction::SE).0;
 
         // South-South-East
-        moves[i].0 |= (center >> 15) & !FILE_A.0;
+        moves[i].0 |= center.shift(Direction::S).shift(Direction::SE).0;
 
         // South-South-West
-        moves[i].0 |= (center >> 17) & !FILE_H.0;
+        moves[i].0 |= center.shift(Direction::S).shift(Direction::SW).0;
 
         // South-West-West
-        moves[i].0 |= (center >> 10) & !FILE_H.0 & !FILE_G.0;
+        moves[i].0 |= center.shift(Direction::W).shift(Direction::SW).0;
 
         // North-West-West
-        moves[i].0 |= (center << 6) & !FILE_H.0 & !FILE_G.0;
+        moves[i].0 |= center.shift(Direction::W).shift(Direction::NW).0;
 
         // North-North-West
-        moves[i].0 |= (center << 15) & !FILE_H.0;
+        moves[i].0 |= center.shift(Direction::N).shift(Direction::NW).0;
 
         i += 1;
         if i == 64 {
@@ -57,3 +64,62 @@ const fn compile_knight_moves() -> [BitBoard; 64] {
     }
     moves
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::pieces::Color;
+    use crate::game::position::Position;
+    use crate::game::Board;
+
+    /// The destinations of the white knight on the given square, in a board with only the kings.
+    fn knight_destinations(square: &str) -> Vec<Position> {
+        let board = Board::from_fen("3k4/8/8/8/8/8/8/3K4 w - - 0 1").unwrap();
+        super::get_knight_moves(&board, Color::White, square.into()).collect()
+    }
+
+    fn positions(squares: &[&str]) -> Vec<Position> {
+        let mut positions = squares
+            .iter()
+            .map(|s| Position::from(*s))
+            .collect::<Vec<Position>>();
+        positions.sort_by_key(|p| p.to_int());
+        positions
+    }
+
+    #[test]
+    fn test_corner_knight_moves() {
+        assert_eq!(knight_destinations("a1"), positions(&["c2", "b3"]));
+        assert_eq!(knight_destinations("h8"), positions(&["g6", "f7"]));
+        assert_eq!(knight_destinations("h1"), positions(&["f2", "g3"]));
+        assert_eq!(knight_destinations("a8"), positions(&["b6", "c7"]));
+    }
+
+    #[test]
+    fn test_edge_knight_moves() {
+        assert_eq!(knight_destinations("b1"), positions(&["d2", "a3", "c3"]));
+        assert_eq!(knight_destinations("g8"), positions(&["f6", "h6", "e7"]));
+        assert_eq!(
+            knight_destinations("a4"),
+            positions(&["b2", "c3", "c5", "b6"])
+        );
+        assert_eq!(
+            knight_destinations("h5"),
+            positions(&["g3", "f4", "f6", "g7"])
+        );
+    }
+
+    #[test]
+    fn test_central_knight_moves() {
+        assert_eq!(
+            knight_destinations("e4"),
+            positions(&["d2", "f2", "c3", "g3", "c5", "g5", "d6", "f6"])
+        );
+    }
+
+    #[test]
+    fn test_knight_cannot_capture_own_pieces() {
+        let board = Board::from_fen("3k4/8/8/8/8/8/2P5/N2K4 w - - 0 1").unwrap();
+        let moves = super::get_knight_moves(&board, Color::White, "a1".into());
+        assert_eq!(moves.collect::<Vec<Position>>(), positions(&["b3"]));
+    }
+}
@@ -102,6 +102,7 @@ pub const MAIN_DIAGONAL: BitBoard = BitBoard(9241421688590303745);
 ///   - index 8: a2 to g8
 ///     ...
 ///   - index 14: a8
+///
 /// A trick to get the diagonal matching a tile is to use the index `7 + rank - file` (0-indexed)
 /// Or just use the provided `get_main_diagonal`.
 #[allow(dead_code)]
@@ -136,6 +137,7 @@ pub const ANTI_DIAGONAL: BitBoard = BitBoard(72624976668147840);
 ///   - index 8: a2 to g8
 ///     ...
 ///   - index 14: h8
+///
 /// A trick to get the diagonal matching a tile is to use the index `rank + file` (0-indexed)
 /// Or just use the provided `get_anti_diagonal`.
 #[allow(dead_code)]
@@ -178,7 +180,30 @@ pub enum Pieces {
     King(Color, Position),
 }
 
+/// The type of a chess piece, without its color or position.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Rook,
+    Knight,
+    Bishop,
+    Queen,
+    King,
+}
+
 impl Pieces {
+    /// Get the type of the piece.
+    pub fn get_kind(&self) -> PieceKind {
+        match self {
+            Pieces::Pawn(_, _) => PieceKind::Pawn,
+            Pieces::Rook(_, _) => PieceKind::Rook,
+            Pieces::Knight(_, _) => PieceKind::Knight,
+            Pieces::Bishop(_, _) => PieceKind::Bishop,
+            Pieces::Queen(_, _) => PieceKind::Queen,
+            Pieces::King(_, _) => PieceKind::King,
+        }
+    }
+
     /// Get the color of the piece.
     pub fn get_color(&self) -> Color {
         match self {
@@ -308,6 +333,8 @@ pub fn is_pawn_two_step(pawn_move: &Move) -> bool {
 
 /// A chess piece set (white or black).
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Color {
     White,
     Black,
@@ -328,4 +355,37 @@ impl Color {
     pub fn is_white(&self) -> bool {
         *self == Color::White
     }
+
+    /// The index of the color in tables indexed by color: 0 for White, 1 for Black.
+    pub fn index(&self) -> usize {
+        match self {
+            Self::White => 0,
+            Self::Black => 1,
+        }
+    }
+
+    /// The color at the given index (see `index`).
+    /// Note that this function panics if the index is not 0 or 1.
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::White,
+            1 => Self::Black,
+            _ => panic!("invalid color index: {}", index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_index() {
+        assert_eq!(Color::White.index(), 0);
+        assert_eq!(Color::Black.index(), 1);
+        for color in [Color::White, Color::Black].iter() {
+            assert_eq!(Color::from_index(color.index()), *color);
+            assert_eq!(color.opposite().index(), 1 - color.index());
+        }
+    }
 }
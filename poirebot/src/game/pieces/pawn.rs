@@ -1,5 +1,5 @@
-use crate::bitboard::{BitBoard, EMPTY};
-use crate::game::pieces::{Color, FILE_A, FILE_H, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7};
+use crate::bitboard::{BitBoard, Direction, EMPTY};
+use crate::game::pieces::{Color, RANK_2, RANK_4, RANK_5, RANK_7};
 use crate::game::Board;
 
 /// Generates a bitboard with the moves that can be performed by the pawns in the given bitboard.
@@ -14,52 +14,48 @@ pub fn get_pawn_moves_and_attacks(board: &Board, color: Color, origin: &BitBoard
     let capturable_pieces = other_side.pieces | other_side.en_passant_target;
 
     if color.is_white() {
-        // Single steps (lshift by 8 bits for: rank +1)
-        result |= (origin << 8) & !all_pieces;
+        // Single steps (rank +1)
+        result |= origin.shift(Direction::N) & !all_pieces;
 
-        // Right-side attacks (lshift by 9 bits for: rank +1, file +1)
-        // 'H' file is excluded to prevent overflow
+        // Diagonal attacks (rank +1, file +/- 1)
         // Also checks possibility of en-passant
-        result |= (origin & !FILE_H) << 9 & capturable_pieces;
-
-        // Left-side attacks (lshift by 7 bits for: rank +1, file -1).
-        // 'A' file is excluded to prevent underflow
-        // Also checks possibility of en-passant
-        result |= (origin & !FILE_A) << 7 & capturable_pieces;
+        result |= get_pawn_attacks(color, origin) & capturable_pieces;
 
         // Double steps
         // 1. Only include pawns in rank 2
-        // 2. Ensure there are no pieces in rank 3
-        // 3. Ensure there are no pieces in rank 4
-        // 4. Add 2 ranks to the original position
-        result |=
-            (origin & RANK_2 & !(all_pieces & RANK_3) >> 8 & !(all_pieces & RANK_4) >> 16) << 16;
+        // 2. Step to rank 3, which must be empty
+        // 3. Step to rank 4, which must be empty
+        let single_steps = (origin & RANK_2).shift(Direction::N) & !all_pieces;
+        result |= single_steps.shift(Direction::N) & !all_pieces & RANK_4;
     } else {
-        // Single steps (rshift by 8 bits for: rank -1)
-        result |= (origin >> 8) & !all_pieces;
-
-        // Right-side attacks (rshift by 7 bits for: rank -1, file +1)
-        // 'H' file is excluded to prevent overflow
-        // Also checks possibility of en-passant
-        result |= (origin & !FILE_H) >> 7 & capturable_pieces;
+        // Single steps (rank -1)
+        result |= origin.shift(Direction::S) & !all_pieces;
 
-        // Left-side attacks (rshift by 9 bits for: rank -1, file -1).
-        // 'A' file is excluded to prevent underflow
+        // Diagonal attacks (rank -1, file +/- 1)
         // Also checks possibility of en-passant
-        result |= (origin & !FILE_A) >> 9 & capturable_pieces;
+        result |= get_pawn_attacks(color, origin) & capturable_pieces;
 
         // Double steps
         // 1. Only include pawns in rank 7
-        // 2. Ensure there are no pieces in rank 6
-        // 3. Ensure there are no pieces in rank 5
-        // 4. Remove 2 ranks to the original position
-        result |=
-            (origin & RANK_7 & !(all_pieces & RANK_6) << 8 & !(all_pieces & RANK_5) << 16) >> 16;
+        // 2. Step to rank 6, which must be empty
+        // 3. Step to rank 5, which must be empty
+        let single_steps = (origin & RANK_7).shift(Direction::S) & !all_pieces;
+        result |= single_steps.shift(Direction::S) & !all_pieces & RANK_5;
     }
 
     result
 }
 
+/// Generates a bitboard with the squares attacked by the pawns in the given bitboard: the
+/// diagonals in front of them, whether or not there is a piece to capture.
+pub fn get_pawn_attacks(color: Color, origin: &BitBoard) -> BitBoard {
+    if color.is_white() {
+        origin.shift(Direction::NE) | origin.shift(Direction::NW)
+    } else {
+        origin.shift(Direction::SE) | origin.shift(Direction::SW)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::bitboard::BitBoard;
@@ -67,6 +63,44 @@ mod tests {
     use crate::game::position::Position;
     use crate::game::Board;
 
+    #[test]
+    fn test_pawn_double_step_blockers() {
+        let moves = |fen: &str, color: Color, origin: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            super::get_pawn_moves_and_attacks(&board, color, &BitBoard::from_position(origin))
+                .collect::<Vec<Position>>()
+        };
+
+        // Blocker on the 3rd rank: no step at all
+        assert_eq!(
+            moves("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1", Color::White, "e2"),
+            vec![]
+        );
+        // Blocker on the 4th rank: single step only
+        assert_eq!(
+            moves("4k3/8/8/8/4n3/8/4P3/4K3 w - - 0 1", Color::White, "e2"),
+            vec![Position::from("e3")]
+        );
+        // Blockers on other files don't matter
+        assert_eq!(
+            moves("4k3/8/8/8/3n1n2/8/4P3/4K3 w - - 0 1", Color::White, "e2"),
+            vec![Position::from("e3"), Position::from("e4")]
+        );
+
+        assert_eq!(
+            moves("4k3/4p3/4N3/8/8/8/8/4K3 b - - 0 1", Color::Black, "e7"),
+            vec![]
+        );
+        assert_eq!(
+            moves("4k3/4p3/8/4N3/8/8/8/4K3 b - - 0 1", Color::Black, "e7"),
+            vec![Position::from("e6")]
+        );
+        assert_eq!(
+            moves("4k3/4p3/8/3N1N2/8/8/8/4K3 b - - 0 1", Color::Black, "e7"),
+            vec![Position::from("e5"), Position::from("e6")]
+        );
+    }
+
     #[test]
     fn test_get_pawn_moves_and_attacks_white() {
         let board =
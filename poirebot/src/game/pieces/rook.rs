@@ -8,7 +8,7 @@ use crate::game::Board;
 /// Note that multiple rooks can be passed in the bitboard; to get the moves for individual rooks,
 /// iterate over the rooks and call this function with the singular bitboard for each rook.
 pub fn get_rook_sliding_moves(board: &Board, color: Color, origin: &BitBoard) -> BitBoard {
-    get_sliding_straight_moves(&board, color, &origin)
+    get_sliding_straight_moves(board, color, origin)
 }
 
 #[cfg(test)]
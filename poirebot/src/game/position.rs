@@ -27,6 +27,17 @@ impl Position {
         Ok(Position { file_x, rank_y })
     }
 
+    /// Initialize a position from its `BitBoard` index (0 = a1, 63 = h8), the inverse of `to_int`.
+    /// Note that this function panics if the index is out of bounds.
+    #[inline]
+    pub fn from_int(i: u8) -> Position {
+        assert!(i < 64, "invalid square index: {}", i);
+        Position {
+            file_x: i & 7,
+            rank_y: i >> 3,
+        }
+    }
+
     /// Initialize a position from notation (for example: a8).
     pub fn from_notation(notation: &str) -> anyhow::Result<Position> {
         if notation.len() != 2 {
@@ -77,11 +88,52 @@ impl Position {
         self.forwards(color.opposite(), inc)
     }
 
+    /// Returns the position offset by the given number of files (towards H) and ranks (towards 8),
+    /// or `None` if it falls off the board.
+    pub fn offset(&self, file_delta: i8, rank_delta: i8) -> Option<Self> {
+        let file_x = (self.file_x as i8).checked_add(file_delta)?;
+        let rank_y = (self.rank_y as i8).checked_add(rank_delta)?;
+        if (0..8).contains(&file_x) && (0..8).contains(&rank_y) {
+            Some(Position {
+                file_x: file_x as u8,
+                rank_y: rank_y as u8,
+            })
+        } else {
+            None
+        }
+    }
+
     /// Returns the distance between 2 positions on the Y axis (rank).
     pub fn distance_rank(&self, other: &Position) -> u8 {
         let s_y = self.rank_y as i32;
         let o_y = other.rank_y as i32;
-        (s_y - o_y).abs() as u8
+        (s_y - o_y).unsigned_abs() as u8
+    }
+
+    /// Returns the number of king steps between 2 positions, from 0 to 7.
+    pub fn distance(&self, other: &Position) -> u8 {
+        let file_distance = (self.file_x as i32 - other.file_x as i32).unsigned_abs() as u8;
+        file_distance.max(self.distance_rank(other))
+    }
+
+    /// Returns the distance to the 4 central squares (d4, e4, d5, e5), from 0 to 3.
+    pub fn center_distance(&self) -> u8 {
+        let file_distance = if self.file_x < 4 {
+            3 - self.file_x
+        } else {
+            self.file_x - 4
+        };
+        let rank_distance = if self.rank_y < 4 {
+            3 - self.rank_y
+        } else {
+            self.rank_y - 4
+        };
+        file_distance.max(rank_distance)
+    }
+
+    /// Whether the square is light (e.g. h1), as opposed to dark (e.g. a1).
+    pub fn is_light_square(&self) -> bool {
+        (self.file_x + self.rank_y) % 2 == 1
     }
 
     /// Convert to `BitBoard` notation.
@@ -146,4 +198,77 @@ mod tests {
         let position = Position::from_notation("h8").unwrap();
         assert_eq!(format!("{}", position.flip()), "h1");
     }
+
+    #[test]
+    fn test_from_int() {
+        assert_eq!(Position::from_int(0), Position::from("a1"));
+        assert_eq!(Position::from_int(7), Position::from("h1"));
+        assert_eq!(Position::from_int(28), Position::from("e4"));
+        assert_eq!(Position::from_int(63), Position::from("h8"));
+        for i in 0..64 {
+            assert_eq!(Position::from_int(i).to_int(), i);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_int_out_of_bounds() {
+        Position::from_int(64);
+    }
+
+    #[test]
+    fn test_offset() {
+        let e4 = Position::from("e4");
+        assert_eq!(e4.offset(0, 0), Some(e4));
+        assert_eq!(e4.offset(0, 1), Some(Position::from("e5")));
+        assert_eq!(e4.offset(1, 1), Some(Position::from("f5")));
+        assert_eq!(e4.offset(1, 0), Some(Position::from("f4")));
+        assert_eq!(e4.offset(1, -1), Some(Position::from("f3")));
+        assert_eq!(e4.offset(0, -1), Some(Position::from("e3")));
+        assert_eq!(e4.offset(-1, -1), Some(Position::from("d3")));
+        assert_eq!(e4.offset(-1, 0), Some(Position::from("d4")));
+        assert_eq!(e4.offset(-1, 1), Some(Position::from("d5")));
+        assert_eq!(e4.offset(-4, 4), Some(Position::from("a8")));
+        assert_eq!(e4.offset(3, -3), Some(Position::from("h1")));
+        assert_eq!(e4.offset(-5, 0), None);
+        assert_eq!(e4.offset(0, 5), None);
+
+        // On the edge, only the directions towards the board
+        let a1 = Position::from("a1");
+        assert_eq!(a1.offset(0, 1), Some(Position::from("a2")));
+        assert_eq!(a1.offset(1, 1), Some(Position::from("b2")));
+        assert_eq!(a1.offset(1, 0), Some(Position::from("b1")));
+        assert_eq!(a1.offset(1, -1), None);
+        assert_eq!(a1.offset(0, -1), None);
+        assert_eq!(a1.offset(-1, -1), None);
+        assert_eq!(a1.offset(-1, 0), None);
+        assert_eq!(a1.offset(-1, 1), None);
+        assert_eq!(a1.offset(7, 7), Some(Position::from("h8")));
+        assert_eq!(a1.offset(8, 0), None);
+
+        // Large deltas don't overflow
+        let h8 = Position::from("h8");
+        assert_eq!(h8.offset(i8::MAX, 0), None);
+        assert_eq!(h8.offset(0, i8::MAX), None);
+        assert_eq!(a1.offset(i8::MIN, i8::MIN), None);
+    }
+
+    #[test]
+    fn test_center_distance() {
+        assert_eq!(Position::from("e4").center_distance(), 0);
+        assert_eq!(Position::from("d5").center_distance(), 0);
+        assert_eq!(Position::from("c6").center_distance(), 1);
+        assert_eq!(Position::from("f2").center_distance(), 2);
+        assert_eq!(Position::from("a1").center_distance(), 3);
+        assert_eq!(Position::from("h5").center_distance(), 3);
+    }
+
+    #[test]
+    fn test_is_light_square() {
+        assert!(!Position::from("a1").is_light_square());
+        assert!(Position::from("h1").is_light_square());
+        assert!(Position::from("e4").is_light_square());
+        assert!(!Position::from("d4").is_light_square());
+        assert!(!Position::from("h8").is_light_square());
+    }
 }
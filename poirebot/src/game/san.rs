@@ -0,0 +1,329 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Context;
+
+use crate::game::pieces::{get_castling_rook_move, Pieces};
+use crate::game::{Board, Move, Promotion};
+
+impl Board {
+    /// Convert a `Move` to Standard Algebraic Notation (SAN), in the context of this board
+    /// (i.e. the position before the move is played).
+    ///
+    /// Ref: https://www.chessprogramming.org/Algebraic_Chess_Notation#Standard_Algebraic_Notation_.28SAN.29
+    ///
+    /// For example: `Move(g1, f3)` in the starting position becomes `"Nf3"`.
+    pub fn to_san(&self, m: Move) -> String {
+        let Move(origin, destination, promotion) = m;
+        let piece = match self.get_piece(origin) {
+            Some(piece) => piece,
+            None => return m.to_pure_notation(),
+        };
+        let color = piece.get_color();
+
+        let mut san = if piece.is_king() && get_castling_rook_move(&m).is_some() {
+            if destination.file_x == 2 {
+                "O-O-O".to_owned()
+            } else {
+                "O-O".to_owned()
+            }
+        } else {
+            let is_capture = self.get_piece(destination).is_some()
+                || (piece.is_pawn() && origin.file_x != destination.file_x);
+            let origin_notation = origin.to_string();
+            let mut san = String::with_capacity(8);
+
+            if let Pieces::Pawn(_, _) = piece {
+                if is_capture {
+                    san.push_str(&origin_notation[..1]);
+                }
+            } else {
+                san.push(piece.to_letter_notation().to_ascii_uppercase());
+
+                // Disambiguate between pieces of the same type that can reach the destination
+                let ambiguous = self
+                    .legal_moves(color)
+                    .into_iter()
+                    .filter(|other| other.1 == destination && other.0 != origin)
+                    .filter(|other| {
+                        self.get_piece(other.0)
+                            .map(|p| p.to_letter_notation() == piece.to_letter_notation())
+                            .unwrap_or(false)
+                    })
+                    .collect::<Vec<Move>>();
+                if !ambiguous.is_empty() {
+                    if ambiguous
+                        .iter()
+                        .all(|other| other.0.file_x != origin.file_x)
+                    {
+                        san.push_str(&origin_notation[..1]);
+                    } else if ambiguous
+                        .iter()
+                        .all(|other| other.0.rank_y != origin.rank_y)
+                    {
+                        san.push_str(&origin_notation[1..]);
+                    } else {
+                        san.push_str(&origin_notation);
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&destination.to_string());
+
+            if self.is_promotion_move(m) && promotion != Promotion::None {
+                san.push('=');
+                san.push_str(&promotion.to_string().to_ascii_uppercase());
+            }
+            san
+        };
+
+        // A double check is written like a single one
+        let outcome = self.with_move(m);
+        if outcome.checkers(color.opposite()).popcnt() > 0 {
+            if outcome.is_checkmate(color.opposite()) {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+
+        san
+    }
+
+    /// Parse a move in Standard Algebraic Notation (SAN), in the context of this board (i.e. the
+    /// position before the move is played), for the side to move.
+    ///
+    /// The check/mate suffix and annotations (`!`, `?`) are optional, and castling may also be
+    /// written with zeros (`0-0`). For example: `"Nf3"` in the starting position becomes
+    /// `Move(g1, f3)`.
+    pub fn from_san(&self, san: &str) -> anyhow::Result<Move> {
+        let wanted = strip_san_suffix(san.trim()).replace('0', "O");
+        self.legal_moves(self.turn)
+            .into_iter()
+            .find(|m| strip_san_suffix(&self.to_san(*m)) == wanted)
+            .with_context(|| format!("no legal move matches SAN: {}", san))
+    }
+
+    /// Parses a move in SAN (see `from_san`) and plays it like `try_apply_move`.
+    ///
+    /// The board is left untouched when no legal move matches.
+    pub fn apply_san(&mut self, san: &str) -> anyhow::Result<()> {
+        let m = self.from_san(san)?;
+        self.try_apply_move(m)
+    }
+
+    /// Replays the moves from this board, and returns the SAN transcript with move numbers.
+    ///
+    /// For example: `"1. e4 e5 2. Nf3"`. When the first move is Black's, it is numbered `"1..."`.
+    pub fn san_line(&self, moves: &[Move]) -> String {
+        let mut board = *self;
+        let mut line = Vec::with_capacity(moves.len() * 3 / 2 + 1);
+
+        for (i, m) in moves.iter().enumerate() {
+            let color = board
+                .get_piece(m.0)
+                .map(|p| p.get_color())
+                .unwrap_or(board.turn);
+            if color.is_white() {
+                line.push(format!("{}.", board.fullmove_number));
+            } else if i == 0 {
+                line.push(format!("{}...", board.fullmove_number));
+            }
+            line.push(board.to_san(*m));
+            board.apply_move(*m);
+        }
+
+        line.join(" ")
+    }
+}
+
+/// Removes the check/mate suffix and annotations from a SAN move.
+fn strip_san_suffix(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// A move with the board it is played on (i.e. the position before the move), displayed in SAN
+/// for logging. `Move`'s `Debug` only has the coordinates to go on.
+///
+/// For example: `MoveWithBoard(Move(g1, f3), &Board::default())` is displayed as `Nf3`.
+pub struct MoveWithBoard<'a>(pub Move, pub &'a Board);
+
+impl Display for MoveWithBoard<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.1.to_san(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::pieces::Color;
+
+    fn moves(line: &str) -> Vec<Move> {
+        line.split(' ').map(Move::from_pure_notation).collect()
+    }
+
+    #[test]
+    fn test_to_san() {
+        let board = Board::default();
+        assert_eq!(board.to_san(("g1", "f3").into()), "Nf3");
+        assert_eq!(board.to_san(("e2", "e4").into()), "e4");
+
+        // The f1 knight blocks the h1 rook, but both knights can reach d2
+        let board = Board::from_fen("4k3/8/8/8/8/1N6/4K3/R4N1R w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("a1", "d1").into()), "Rd1");
+        assert_eq!(board.to_san(("f1", "d2").into()), "Nfd2");
+        let board = Board::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("a1", "d1").into()), "Rad1");
+        let board = Board::from_fen("4k3/R7/8/8/8/8/4K3/R7 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("a1", "a4").into()), "R1a4");
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(board.to_san(("e1", "g1").into()), "O-O");
+
+        // Promotion with capture and check
+        let board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.to_san(("a7", "b8", Promotion::Queen).into()),
+            "axb8=Q+"
+        );
+    }
+
+    #[test]
+    fn test_san_check_suffix() {
+        // Check
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("a1", "a8").into()), "Ra8+");
+        assert_eq!(board.to_san(("a1", "a7").into()), "Ra7");
+
+        // Back-rank mate
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("d1", "d8").into()), "Rd8#");
+
+        // Double check: the knight discovers the rook's check, and gives check itself
+        let board = Board::from_fen("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        let outcome = board.with_move(("e4", "d6").into());
+        assert_eq!(outcome.checkers(Color::Black).popcnt(), 2);
+        assert_eq!(board.to_san(("e4", "d6").into()), "Nd6+");
+        assert_eq!(board.to_san(("e4", "c5").into()), "Nc5+");
+
+        // Double checkmate
+        let board = Board::from_fen("3rkr2/3p1p2/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(("e4", "f6").into()), "Nf6#");
+        assert!(board
+            .with_move(("e4", "f6").into())
+            .is_checkmate(Color::Black));
+        assert!(!board.is_checkmate(Color::Black));
+    }
+
+    #[test]
+    fn test_move_with_board() {
+        let board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let castle = Move::from_pure_notation("e1g1");
+        assert_eq!(MoveWithBoard(castle, &board).to_string(), "O-O");
+        assert_eq!(format!("{:?}", castle), "e1g1");
+    }
+
+    #[test]
+    fn test_from_san() {
+        let board = Board::default();
+        assert_eq!(board.from_san("Nf3").unwrap(), ("g1", "f3").into());
+        assert_eq!(board.from_san("e4").unwrap(), ("e2", "e4").into());
+        assert!(board.from_san("e5").is_err());
+        assert!(board.from_san("Qxf7#").is_err());
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(board.from_san("O-O").unwrap(), ("e1", "g1").into());
+        assert_eq!(board.from_san("0-0").unwrap(), ("e1", "g1").into());
+    }
+
+    #[test]
+    fn test_san_promotions() {
+        let cases = [
+            // Capture-promotion without check
+            (
+                "3r4/4P2k/8/8/8/8/8/4K3 w - - 0 1",
+                ("e7", "d8", Promotion::Queen),
+                "exd8=Q",
+            ),
+            // Capture-promotion with check
+            (
+                "3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1",
+                ("e7", "d8", Promotion::Queen),
+                "exd8=Q+",
+            ),
+            // Under-promotion
+            (
+                "7k/4P3/8/8/8/8/8/4K3 w - - 0 1",
+                ("e7", "e8", Promotion::Knight),
+                "e8=N",
+            ),
+            // Capture-promotion with mate, by Black
+            (
+                "4k3/8/8/8/8/8/PP2p3/K2R4 b - - 0 1",
+                ("e2", "d1", Promotion::Queen),
+                "exd1=Q#",
+            ),
+        ];
+
+        for (fen, m, san) in cases.iter() {
+            let board = Board::from_fen(fen).unwrap();
+            let m: Move = (*m).into();
+            assert_eq!(board.to_san(m), *san, "{}", fen);
+            assert_eq!(board.from_san(san).unwrap(), m, "{}", fen);
+        }
+
+        // The other promotions are distinct moves
+        let board = Board::from_fen("7k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.from_san("e8=R+").unwrap(),
+            ("e7", "e8", Promotion::Rook).into()
+        );
+    }
+
+    #[test]
+    fn test_apply_uci_and_san() {
+        // Ruy Lopez, with castling
+        let mut by_uci = Board::default();
+        for m in "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1g1".split(' ') {
+            by_uci.apply_uci(m).unwrap();
+        }
+        let mut by_san = Board::default();
+        for san in "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O".split(' ') {
+            by_san.apply_san(san).unwrap();
+        }
+        assert_eq!(by_uci.to_fen(), by_san.to_fen());
+        assert_eq!(
+            by_san.to_fen(),
+            "r1bqkb1r/pppp1ppp/2n2n2/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4"
+        );
+
+        // Rejected moves leave the board untouched
+        let before = by_san;
+        assert!(by_san.apply_uci("e8e6").is_err());
+        assert!(by_san.apply_uci("z9").is_err());
+        assert!(by_san.apply_san("Qh4").is_err());
+        assert_eq!(by_san, before);
+    }
+
+    #[test]
+    fn test_san_line() {
+        // Scholar's mate
+        let board = Board::default();
+        assert_eq!(
+            board.san_line(&moves("e2e4 e7e5 f1c4 b8c6 d1h5 g8f6 h5f7")),
+            "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7#"
+        );
+
+        let board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(
+            board.san_line(&moves("e7e5 g1f3 b8c6")),
+            "1... e5 2. Nf3 Nc6"
+        );
+    }
+}
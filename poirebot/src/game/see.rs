@@ -0,0 +1,196 @@
+use crate::bitboard::{BitBoard, EMPTY};
+use crate::game::pieces::{king, knight, pawn, sliding, Color, PieceKind};
+use crate::game::position::Position;
+use crate::game::{Board, Move, PIECE_VALUES_CP};
+
+/// The kinds of pieces, from the least valuable to the most, to pick the attackers in an exchange.
+const ATTACKER_ORDER: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+impl Board {
+    /// Static exchange evaluation (SEE) of a capture: the material won (or lost, if negative) in
+    /// centipawns by the side playing it, once the pieces attacking the destination are exchanged.
+    /// Each side recaptures with its least valuable piece, and stops when it isn't worth it.
+    ///
+    /// The exchange is worked out with a swap list on the attackers of the square, removing them
+    /// from the occupancy as they capture (which uncovers the sliding pieces behind them), without
+    /// playing the moves on a board. Pins are not taken into account.
+    ///
+    /// Ref: https://www.chessprogramming.org/Static_Exchange_Evaluation
+    ///
+    /// Returns 0 for moves that don't capture.
+    pub fn static_exchange(&self, m: Move) -> i32 {
+        let Move(origin, square, promotion) = m;
+        let (mut color, kind) = match self.get_piece(origin) {
+            Some(piece) => (piece.get_color(), piece.get_kind()),
+            None => return 0,
+        };
+        let captured = match self.captured_value(m) {
+            Some(value) => value,
+            None => return 0,
+        };
+
+        let mut occupied = self.get_bitboard() & !BitBoard::from(origin);
+        if self.get_piece(square).is_none() {
+            // En-passant: the captured pawn is behind the destination
+            occupied &= !BitBoard::from(square.backwards(color, 1));
+        }
+        let mut on_square = PIECE_VALUES_CP[promotion.piece_kind().unwrap_or(kind) as usize];
+
+        // The material balance after each capture, for the side making it, if the exchange stopped
+        // there
+        let mut gains = Vec::with_capacity(32);
+        gains.push(captured);
+        loop {
+            color = color.opposite();
+            let attackers = self.attackers_to(square, occupied) & occupied;
+            let (attacker, attacker_kind) = match self.least_valuable_attacker(attackers, color) {
+                Some(attacker) => attacker,
+                None => break,
+            };
+            let remaining = attackers & !BitBoard::from(attacker);
+            if attacker_kind == PieceKind::King
+                && (remaining & self.get_side(color.opposite()).pieces) != EMPTY
+            {
+                // The king can't capture a defended piece
+                break;
+            }
+            gains.push(on_square - gains[gains.len() - 1]);
+            on_square = PIECE_VALUES_CP[attacker_kind as usize];
+            occupied &= !BitBoard::from(attacker);
+        }
+
+        // Each side only recaptures if it doesn't lose by it
+        while gains.len() > 1 {
+            let gain = gains.pop().unwrap_or_default();
+            let previous = gains.len() - 1;
+            gains[previous] = -(-gains[previous]).max(gain);
+        }
+        gains[0]
+    }
+
+    /// Whether the position is quiet for the given color: it is not in check, and has no capture
+    /// that wins or keeps the material even (see `static_exchange`).
+    pub fn is_quiet(&self, color: Color) -> bool {
+        !self.is_in_check(color)
+            && !self
+                .generate_captures(color)
+                .into_iter()
+                .any(|m| self.captured_value(m).is_some() && self.static_exchange(m) >= 0)
+    }
+
+    /// The pieces of both sides attacking the square, with the sliding pieces seeing through the
+    /// squares that aren't in the occupancy.
+    fn attackers_to(&self, square: Position, occupied: BitBoard) -> BitBoard {
+        let target = BitBoard::from(square);
+        let (white, black) = (&self.white, &self.black);
+        let straight = sliding::get_straight_attacks(occupied, square);
+        let diagonal = sliding::get_diagonal_attacks(occupied, square);
+
+        (pawn::get_pawn_attacks(Color::Black, &target) & white.pawns)
+            | (pawn::get_pawn_attacks(Color::White, &target) & black.pawns)
+            | (knight::get_knight_attacks(square) & (white.knights | black.knights))
+            | (king::get_king_attacks(square) & (white.king | black.king))
+            | (straight & (white.rooks | white.queens | black.rooks | black.queens))
+            | (diagonal & (white.bishops | white.queens | black.bishops | black.queens))
+    }
+
+    /// The least valuable of the given attackers belonging to the color, with its kind.
+    fn least_valuable_attacker(
+        &self,
+        attackers: BitBoard,
+        color: Color,
+    ) -> Option<(Position, PieceKind)> {
+        let side = self.get_side(color);
+        ATTACKER_ORDER.iter().find_map(|kind| {
+            let pieces = match kind {
+                PieceKind::Pawn => side.pawns,
+                PieceKind::Knight => side.knights,
+                PieceKind::Bishop => side.bishops,
+                PieceKind::Rook => side.rooks,
+                PieceKind::Queen => side.queens,
+                PieceKind::King => side.king,
+            };
+            (attackers & pieces)
+                .next()
+                .map(|attacker| (attacker, *kind))
+        })
+    }
+
+    /// The value of the piece captured by the move in centipawns, or `None` if it's not a capture.
+    fn captured_value(&self, m: Move) -> Option<i32> {
+        let piece = self.get_piece(m.0)?;
+        match self.get_piece(m.1) {
+            Some(captured) if captured.get_color() != piece.get_color() => {
+                Some(self.piece_value_cp(m.1))
+            }
+            // En-passant: a pawn moving diagonally to an empty square
+            None if piece.is_pawn() && m.0.file_x != m.1.file_x => Some(PIECE_VALUES_CP[0]),
+            _ => None,
+        }
+    }
+
+    /// The value of the piece on the square in centipawns, or 0 if there is none.
+    fn piece_value_cp(&self, position: Position) -> i32 {
+        self.get_piece_kind(position)
+            .map(|kind| PIECE_VALUES_CP[kind as usize])
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_exchange() {
+        // The knight on d5 is hanging
+        let board = Board::from_fen("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.static_exchange(("d1", "d5").into()), 320);
+        assert_eq!(board.static_exchange(("d1", "d4").into()), 0);
+
+        // The e5 pawn is defended by the c6 knight
+        let board =
+            Board::from_fen("r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        assert_eq!(board.static_exchange(("f3", "e5").into()), 100 - 320);
+
+        // Rook takes a defended pawn, but the defender is then taken by the second rook
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.static_exchange(("d2", "d5").into()), 100 - 500 + 100);
+
+        // The king takes the rook back, unless the bishop defends it
+        let board = Board::from_fen("3rk3/8/8/3p4/4K3/2N5/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.static_exchange(("c3", "d5").into()), 100);
+        let board = Board::from_fen("3rk3/5b2/8/3p4/4K3/2N5/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.static_exchange(("c3", "d5").into()), 100 - 320);
+
+        // En-passant
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(board.static_exchange(("e5", "d6").into()), 100);
+    }
+
+    #[test]
+    fn test_is_quiet() {
+        // Italian game: every capture loses material
+        let board =
+            Board::from_fen("r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        assert!(board.is_quiet(Color::White));
+        assert!(Board::default().is_quiet(Color::White));
+
+        // Hanging knight
+        let board = Board::from_fen("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert!(!board.is_quiet(Color::White));
+
+        // In check
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(!board.is_quiet(Color::White));
+    }
+}
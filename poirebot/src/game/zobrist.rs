@@ -0,0 +1,380 @@
+use std::hash::{Hash, Hasher};
+
+use crate::bitboard::BitBoard;
+use crate::game::pieces::Color;
+use crate::game::{Board, BoardSide, Move};
+
+/// The number of random keys: one per piece type, color and square, one for the side to move,
+/// four for the castling rights and eight for the en-passant files.
+const KEY_COUNT: usize = 12 * 64 + 1 + 4 + 8;
+
+/// The random keys, generated at compile-time.
+const KEYS: [u64; KEY_COUNT] = compile_keys();
+
+/// The offset of the side to move key.
+const BLACK_TO_MOVE: usize = 12 * 64;
+/// The offset of the castling rights keys (White kingside, White queenside, Black kingside,
+/// Black queenside).
+const CASTLING: usize = BLACK_TO_MOVE + 1;
+/// The offset of the en-passant file keys.
+const EN_PASSANT: usize = CASTLING + 4;
+
+impl BoardSide {
+    /// The piece bitboards of this side, in the order of the Zobrist keys.
+    fn piece_bitboards(&self) -> [BitBoard; 6] {
+        [
+            self.pawns,
+            self.rooks,
+            self.knights,
+            self.bishops,
+            self.queens,
+            self.king,
+        ]
+    }
+}
+
+impl Board {
+    /// Computes the Zobrist hash of the position: the placement of the pieces, the side to move,
+    /// the castling rights and the en-passant file. The move clocks are not part of the hash.
+    ///
+    /// Ref: https://www.chessprogramming.org/Zobrist_Hashing
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for side in [&self.white, &self.black].iter() {
+            for (kind_index, bitboard) in side.piece_bitboards().iter().enumerate() {
+                let offset = (side.color.index() * 6 + kind_index) * 64;
+                for position in *bitboard {
+                    hash ^= KEYS[offset + position.to_int() as usize];
+                }
+            }
+        }
+
+        if self.turn == Color::Black {
+            hash ^= KEYS[BLACK_TO_MOVE];
+        }
+
+        hash ^ self.castling_hash() ^ self.en_passant_hash()
+    }
+
+    /// Plays the move like `apply_move`, and returns the hash of the new position, updated
+    /// incrementally from the hash of the current one (see `zobrist_hash`).
+    ///
+    /// This is copy-make, not make/unmake: the whole board before the move is returned along with
+    /// the hash, to pass to `restore_hashed`. Boards are small and `Copy`, so copying one back is
+    /// cheaper than undoing the move piece by piece.
+    pub fn apply_move_hashed(&mut self, m: Move, hash: u64) -> (Board, u64) {
+        let previous = *self;
+        self.apply_move(m);
+        let hash = hash ^ previous.zobrist_delta(self);
+        debug_assert_eq!(hash, self.zobrist_hash(), "incremental hash after {:?}", m);
+        (previous, hash)
+    }
+
+    /// Passes the turn to the other side without moving (a null move), and returns the hash of
+    /// the new position like `apply_move_hashed`. The en-passant target is cleared for both sides,
+    /// since the right to capture en-passant is lost when the turn passes.
+    ///
+    /// Returns the board before the null move along with the hash, to pass to `restore_hashed`.
+    pub fn apply_null_move_hashed(&mut self, hash: u64) -> (Board, u64) {
+        let previous = *self;
+        self.white.en_passant_target = BitBoard::default();
+        self.black.en_passant_target = BitBoard::default();
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.halfmove_clock += 1;
+        self.turn = self.turn.opposite();
+
+        let hash = hash ^ previous.zobrist_delta(self);
+        debug_assert_eq!(
+            hash,
+            self.zobrist_hash(),
+            "incremental hash after null move"
+        );
+        (previous, hash)
+    }
+
+    /// Takes back a move played with `apply_move_hashed`, by copying back the board from before
+    /// it. Returns the hash of the restored position, updated incrementally from the given one.
+    pub fn restore_hashed(&mut self, previous: Board, hash: u64) -> u64 {
+        let hash = hash ^ self.zobrist_delta(&previous);
+        *self = previous;
+        debug_assert_eq!(hash, self.zobrist_hash(), "incremental hash after restore");
+        hash
+    }
+
+    /// The hash difference between two positions: the XOR of the keys that differ, which only
+    /// visits the squares that changed.
+    fn zobrist_delta(&self, other: &Board) -> u64 {
+        let mut delta = 0;
+
+        let sides = [(&self.white, &other.white), (&self.black, &other.black)];
+        for (side, other_side) in sides.iter() {
+            let (bitboards, other_bitboards) =
+                (side.piece_bitboards(), other_side.piece_bitboards());
+            for kind_index in 0..6 {
+                let offset = (side.color.index() * 6 + kind_index) * 64;
+                for position in bitboards[kind_index] ^ other_bitboards[kind_index] {
+                    delta ^= KEYS[offset + position.to_int() as usize];
+                }
+            }
+        }
+
+        if self.turn != other.turn {
+            delta ^= KEYS[BLACK_TO_MOVE];
+        }
+
+        delta
+            ^ self.castling_hash()
+            ^ other.castling_hash()
+            ^ self.en_passant_hash()
+            ^ other.en_passant_hash()
+    }
+
+    /// The part of the hash for the castling rights.
+    fn castling_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (i, right) in self.castling_rights().iter().enumerate() {
+            if *right {
+                hash ^= KEYS[CASTLING + i];
+            }
+        }
+        hash
+    }
+
+    /// The part of the hash for the en-passant file.
+    fn en_passant_hash(&self) -> u64 {
+        self.en_passant_square()
+            .map(|target| KEYS[EN_PASSANT + target.file_x as usize])
+            .unwrap_or(0)
+    }
+
+    /// Computes the Zobrist hash of the pawns only, with the same keys as `zobrist_hash`. It only
+    /// changes when a pawn moves, is captured or promotes, to cache the pawn-structure evaluation.
+    pub fn pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+        for side in [&self.white, &self.black].iter() {
+            for position in side.pawns {
+                hash ^= KEYS[side.color.index() * 6 * 64 + position.to_int() as usize];
+            }
+        }
+        hash
+    }
+
+    /// Whether both boards are the same position for the repetition rules: the same placement of
+    /// the pieces, side to move, castling rights and en-passant square. Unlike `==`, the move
+    /// clocks are ignored.
+    ///
+    /// This is the exact comparison behind equal Zobrist hashes (see `zobrist_hash`).
+    pub fn same_position(&self, other: &Board) -> bool {
+        let sides = [(&self.white, &other.white), (&self.black, &other.black)];
+        sides
+            .iter()
+            .all(|(side, other_side)| side.piece_bitboards() == other_side.piece_bitboards())
+            && self.turn == other.turn
+            && self.castling_rights() == other.castling_rights()
+            && self.en_passant_square() == other.en_passant_square()
+    }
+
+    /// The castling rights (White kingside, White queenside, Black kingside, Black queenside).
+    fn castling_rights(&self) -> [bool; 4] {
+        [
+            self.white.can_castle_with("h1".into()),
+            self.white.can_castle_with("a1".into()),
+            self.black.can_castle_with("h8".into()),
+            self.black.can_castle_with("a8".into()),
+        ]
+    }
+}
+
+/// Hashes the board with its Zobrist hash, so that positions can be used as keys in standard
+/// collections.
+///
+/// Equal boards always have the same hash. Boards that only differ by their move clocks also have
+/// the same hash, but they aren't equal.
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist_hash());
+    }
+}
+
+/// Generates the random keys with SplitMix64, from a fixed seed.
+/// Ref: https://prng.di.unimi.it/splitmix64.c
+const fn compile_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0u64; KEY_COUNT];
+    let mut state: u64 = 0x706f_6972_6562_6f74;
+    let mut i = 0usize;
+    loop {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+
+        i += 1;
+        if i == KEY_COUNT {
+            break;
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn play(line: &str) -> Board {
+        let mut board = Board::default();
+        for m in line.split(' ') {
+            board.apply_move(Move::from_pure_notation(m));
+        }
+        board
+    }
+
+    #[test]
+    fn test_zobrist_hash() {
+        let board = Board::default();
+        assert_eq!(board.zobrist_hash(), Board::default().zobrist_hash());
+        assert_ne!(board.zobrist_hash(), play("e2e4").zobrist_hash());
+
+        // Transpositions reach the same hash
+        assert_eq!(
+            play("g1f3 g8f6 b1c3").zobrist_hash(),
+            play("b1c3 g8f6 g1f3").zobrist_hash()
+        );
+
+        // The side to move, castling rights and en-passant file are part of the hash
+        let fen = |fen: &str| Board::from_fen(fen).unwrap().zobrist_hash();
+        assert_ne!(
+            fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            fen("4k3/8/8/8/8/8/8/4K2R b K - 0 1")
+        );
+        assert_ne!(
+            fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1")
+        );
+        assert_ne!(
+            fen("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1"),
+            fen("4k3/8/8/8/3Pp3/8/8/4K3 b - - 0 1")
+        );
+
+        // ... but the move clocks are not
+        assert_eq!(
+            fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            fen("4k3/8/8/8/8/8/8/4K2R w K - 12 40")
+        );
+    }
+
+    #[test]
+    fn test_apply_restore_move() {
+        // Walk the move tree of positions with castling, en-passant and promotions
+        fn walk(board: &mut Board, hash: u64, depth: usize) {
+            if depth == 0 {
+                return;
+            }
+            for m in board.legal_moves(board.turn) {
+                let before = *board;
+                let (previous, after) = board.apply_move_hashed(m, hash);
+                assert_eq!(previous, before);
+                assert_eq!(after, board.zobrist_hash(), "{:?}", m);
+                walk(board, after, depth - 1);
+                assert_eq!(board.restore_hashed(previous, after), hash, "{:?}", m);
+                assert_eq!(*board, before);
+            }
+        }
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ]
+        .iter()
+        {
+            let mut board = Board::from_fen(fen).unwrap();
+            let hash = board.zobrist_hash();
+            walk(&mut board, hash, 2);
+        }
+    }
+
+    #[test]
+    fn test_apply_restore_null_move() {
+        // White can capture en-passant on d6
+        let mut board =
+            Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+                .unwrap();
+        let before = board;
+        let hash = board.zobrist_hash();
+
+        let (previous, after) = board.apply_null_move_hashed(hash);
+        assert_eq!(previous, before);
+        assert_eq!(board.turn, Color::Black);
+        assert_eq!(board.white.en_passant_target, BitBoard::default());
+        assert_eq!(board.black.en_passant_target, BitBoard::default());
+        assert_eq!(after, board.zobrist_hash());
+        assert_eq!(
+            after,
+            Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3")
+                .unwrap()
+                .zobrist_hash()
+        );
+
+        assert_eq!(board.restore_hashed(previous, after), hash);
+        assert_eq!(board, before);
+        assert_eq!(board.black.en_passant_target, BitBoard::from_position("d6"));
+    }
+
+    #[test]
+    fn test_pawn_hash() {
+        let board = Board::default();
+        assert_eq!(board.pawn_hash(), play("g1f3 g8f6 f3g1").pawn_hash());
+        assert_ne!(board.pawn_hash(), play("e2e4").pawn_hash());
+        assert_ne!(board.pawn_hash(), board.zobrist_hash());
+    }
+
+    #[test]
+    fn test_same_position() {
+        // The knights go back and forth: same position, but not the same clocks
+        let board = Board::default();
+        let repeated = play("g1f3 g8f6 f3g1 f6g8");
+        assert!(board.same_position(&repeated));
+        assert_ne!(board, repeated);
+        assert_eq!(board.zobrist_hash(), repeated.zobrist_hash());
+        assert!(board.same_position(&board));
+
+        // Different side to move
+        assert!(!board.same_position(&play("g1f3 g8f6 f3g1")));
+        // Castling rights lost by the king walk
+        let castled = play("e2e4 e7e5 e1e2 e8e7 e2e1 e7e8");
+        assert!(!castled.same_position(&play("e2e4 e7e5")));
+        // En-passant square
+        let en_passant =
+            Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+                .unwrap();
+        let no_en_passant =
+            Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3").unwrap();
+        assert!(!en_passant.same_position(&no_en_passant));
+    }
+
+    #[test]
+    fn test_hash_set() {
+        let mut positions = HashSet::new();
+        assert!(positions.insert(Board::default()));
+        assert!(!positions.insert(Board::default()));
+        assert!(positions.insert(play("e2e4")));
+
+        // Same position and clocks, reached by a different move order
+        assert!(positions.insert(play("g1f3 g8f6 b1c3")));
+        assert!(!positions.insert(play("b1c3 g8f6 g1f3")));
+        assert_eq!(positions.len(), 3);
+
+        // Same position with different clocks: same hash, but not equal
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let later = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 12 40").unwrap();
+        assert!(positions.insert(board));
+        assert!(positions.insert(later));
+        assert_eq!(positions.len(), 5);
+    }
+}
@@ -1,27 +1,77 @@
-use std::cmp::{max, min, Ordering};
-use std::collections::BTreeSet;
-use std::ops::Neg;
+use std::cell::RefCell;
+use std::cmp::{max, Ordering, Reverse};
+use std::collections::HashMap;
+use std::ops::{Add, Neg};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use itertools::Itertools;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use tokio::sync::oneshot;
 
-use crate::bitboard::BitBoard;
-use crate::game::pieces;
-use crate::game::pieces::Color;
-use crate::game::position::Position;
-use crate::game::{Board, Move, Promotion};
+use crate::bitboard::{BitBoard, Direction, EMPTY};
+use crate::book::OpeningBook;
+use crate::game::phase::ENDGAME_PHASE;
+use crate::game::pieces::knight::get_knight_moves;
+use crate::game::pieces::{
+    Color, PieceKind, FILES, FILE_A, FILE_D, FILE_E, FILE_H, RANKS, RANK_1, RANK_4, RANK_5, RANK_8,
+};
+use crate::game::san::MoveWithBoard;
+use crate::game::{Board, Move, PIECE_VALUES_CP};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Brain {
     /// This brain's color.
     pub color: Color,
+    /// The board state at the start of the game.
+    initial: Board,
     /// The current board state.
     board: Board,
     /// The last move by the brain.
     pub last_move: Option<Move>,
     /// The last move by the opponent.
     pub opponent_last_move: Option<Move>,
+    /// The recent moves by the brain, oldest first (to detect oscillations).
+    recent_moves: [Option<Move>; 4],
+    /// The moves played since the initial position, oldest first.
+    moves: Vec<Move>,
+    /// The Zobrist hashes of the positions reached in the game, oldest first (to detect
+    /// repetitions).
+    history: Vec<u64>,
+    /// The opening book to play from, if any.
+    book: Option<Arc<OpeningBook>>,
+    /// The seed to vary the opening moves with, if any.
+    opening_seed: Option<u64>,
+    /// The search running during the opponent's turn, if any.
+    ponder: Option<Ponder>,
+    /// The difficulty level, from 0 to `MAX_LEVEL` (see `set_level`).
+    level: u8,
+    /// The number of moves at the start of the game to search deeper, and by how many plies (see
+    /// `with_opening_extension`).
+    opening_extension: Option<(usize, usize)>,
+    /// The maximum number of nodes to search for a move, if any (see `with_max_nodes`).
+    max_nodes: Option<u64>,
+}
+
+/// A search of the position after the opponent's predicted move, run during the opponent's turn.
+#[derive(Debug, Clone)]
+struct Ponder {
+    /// The predicted move by the opponent.
+    predicted: Move,
+    /// The Zobrist hash of the position after the predicted move.
+    hash: u64,
+    /// Whether the opponent played the predicted move.
+    hit: bool,
+    /// The result of the deepest completed iteration.
+    best: Arc<Mutex<Option<SearchResult>>>,
+    /// Set to stop the search, abandoning the current iteration.
+    stop: Arc<AtomicBool>,
+    /// Set by the search when it is over, which wakes up the move search waiting for it.
+    done: Arc<(Mutex<bool>, Condvar)>,
 }
 
 /// Describes a move that the brain could perform.
@@ -44,214 +94,1166 @@ impl Eq for BrainMove {}
 /// Implements the risk vs. reward scoring.
 impl PartialOrd for BrainMove {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.estimate.partial_cmp(&other.estimate)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for BrainMove {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.estimate.partial_cmp(&other.estimate).unwrap()
     }
 }
 
 type MoveCollection = Vec<BrainMove>;
 
+/// The outcome of a search for the brain's move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The chosen move.
+    pub best: Move,
+    /// The evaluation of the chosen move, from the brain's perspective.
+    pub eval: Evaluation,
+    /// The depth of the last completed iteration (in plies), or 0 if there was no time to search.
+    pub depth: usize,
+    /// The selective depth: the furthest ply reached by the search, over all iterations.
+    pub seldepth: usize,
+    /// The number of nodes visited, over all iterations.
+    pub nodes: u64,
+    /// The other counters of the search, over all iterations.
+    pub stats: SearchStats,
+    /// The principal variation, starting with the chosen move.
+    pub pv: Vec<Move>,
+    /// The time spent searching.
+    pub elapsed: Duration,
+}
+
+impl SearchResult {
+    /// The UCI `info` line reporting the search, e.g.
+    /// `info depth 4 seldepth 4 nodes 1200 nps 60000 time 20 score cp 35 pv e2e4 e7e5`.
+    ///
+    /// Ref: https://www.shredderchess.com/chess-features/uci-universal-chess-interface.html
+    pub fn uci_info(&self) -> String {
+        let millis = self.elapsed.as_millis() as u64;
+        let nps = self.nodes * 1000 / millis.max(1);
+        // Mates are counted in moves, from the length of the principal variation
+        let score = match self.eval {
+            Evaluation::Score(cp) => format!("cp {}", cp),
+            Evaluation::Best => format!("mate {}", self.pv.len().div_ceil(2)),
+            Evaluation::Worst => format!("mate -{}", self.pv.len() / 2),
+        };
+        let pv = self
+            .pv
+            .iter()
+            .map(Move::to_uci)
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!(
+            "info depth {} seldepth {} nodes {} nps {} time {} score {} pv {}",
+            self.depth, self.seldepth, self.nodes, nps, millis, score, pv
+        )
+    }
+
+    /// The effective branching factor: the average number of nodes searched under each node,
+    /// such that `nodes = ebf^depth`. Lower means better pruning and move ordering.
+    ///
+    /// Ref: https://www.chessprogramming.org/Branching_Factor
+    pub fn effective_branching_factor(&self) -> f64 {
+        if self.depth == 0 || self.nodes == 0 {
+            return 0.0;
+        }
+        (self.nodes as f64).powf(1.0 / self.depth as f64)
+    }
+}
+
+/// Counters of what happened during a search, to measure the pruning and the tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The number of nodes searched beyond the depth by the quiescence search (included in the
+    /// nodes of the search).
+    pub quiescence_nodes: u64,
+    /// The number of nodes where a move was good enough to skip the remaining ones (alpha-beta).
+    pub beta_cutoffs: u64,
+    /// The number of leaves whose evaluation was found in the evaluation table.
+    pub eval_table_hits: u64,
+    /// The number of leaves that had to be evaluated.
+    pub eval_table_misses: u64,
+}
+
+/// The counters shared by the threads of a search, over all of its iterations.
+#[derive(Debug, Default)]
+struct SearchCounters {
+    /// The number of nodes visited.
+    nodes: AtomicU64,
+    /// The furthest ply reached from the root.
+    seldepth: AtomicUsize,
+    /// The number of nodes after which the search stops, if any.
+    max_nodes: Option<u64>,
+    /// Set to stop the search from another thread, e.g. when pondering is over.
+    stop: Arc<AtomicBool>,
+    /// Whether to order the moves by the kind of piece only, without looking at captures and
+    /// checks (see `list_potential_moves`), to measure what the refinement saves.
+    #[cfg(test)]
+    basic_ordering: bool,
+    /// Whether to evaluate every leaf without the evaluation table (see `cached_evaluate`), to
+    /// measure what the table saves.
+    #[cfg(test)]
+    no_eval_table: bool,
+    /// See `SearchStats`.
+    quiescence_nodes: AtomicU64,
+    beta_cutoffs: AtomicU64,
+    eval_table_hits: AtomicU64,
+    eval_table_misses: AtomicU64,
+}
+
+impl SearchCounters {
+    /// Counters for a search that stops after the given number of nodes (if any).
+    fn with_max_nodes(max_nodes: Option<u64>) -> Self {
+        Self {
+            max_nodes,
+            ..Self::default()
+        }
+    }
+
+    /// Counters for a search that stops when the flag is set.
+    fn with_stop(stop: Arc<AtomicBool>) -> Self {
+        Self {
+            stop,
+            ..Self::default()
+        }
+    }
+
+    /// Counts a node visited at the given ply (the root being 0). Returns `false` without counting
+    /// it if the node budget is exhausted or the search is stopped, in which case the node must not
+    /// be searched.
+    fn visit(&self, ply: usize) -> bool {
+        if self.stop.load(AtomicOrdering::Relaxed) {
+            return false;
+        }
+        let visited = match self.max_nodes {
+            Some(max) => self
+                .nodes
+                .fetch_update(AtomicOrdering::Relaxed, AtomicOrdering::Relaxed, |nodes| {
+                    (nodes < max).then_some(nodes + 1)
+                })
+                .is_ok(),
+            None => {
+                self.nodes.fetch_add(1, AtomicOrdering::Relaxed);
+                true
+            }
+        };
+        if visited {
+            self.seldepth.fetch_max(ply, AtomicOrdering::Relaxed);
+        }
+        visited
+    }
+
+    /// Whether the node budget is exhausted (see `with_max_nodes`) or the search is stopped (see
+    /// `with_stop`): the current iteration was abandoned.
+    fn is_exhausted(&self) -> bool {
+        self.stop.load(AtomicOrdering::Relaxed)
+            || self
+                .max_nodes
+                .is_some_and(|max| self.nodes.load(AtomicOrdering::Relaxed) >= max)
+    }
+
+    /// Counts a leaf evaluation, found in the evaluation table or not.
+    fn evaluated(&self, hit: bool) {
+        let counter = if hit {
+            &self.eval_table_hits
+        } else {
+            &self.eval_table_misses
+        };
+        counter.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    /// Whether to order the moves by the kind of piece only (see `basic_ordering`), which is only
+    /// done by the tests.
+    #[cfg(test)]
+    fn basic_ordering(&self) -> bool {
+        self.basic_ordering
+    }
+
+    #[cfg(not(test))]
+    fn basic_ordering(&self) -> bool {
+        false
+    }
+
+    /// Whether to evaluate the leaves without the evaluation table (see `no_eval_table`), which is
+    /// only done by the tests.
+    #[cfg(test)]
+    fn no_eval_table(&self) -> bool {
+        self.no_eval_table
+    }
+
+    #[cfg(not(test))]
+    fn no_eval_table(&self) -> bool {
+        false
+    }
+
+    fn stats(&self) -> SearchStats {
+        SearchStats {
+            quiescence_nodes: self.quiescence_nodes.load(AtomicOrdering::Relaxed),
+            beta_cutoffs: self.beta_cutoffs.load(AtomicOrdering::Relaxed),
+            eval_table_hits: self.eval_table_hits.load(AtomicOrdering::Relaxed),
+            eval_table_misses: self.eval_table_misses.load(AtomicOrdering::Relaxed),
+        }
+    }
+}
+
+/// A root move with its evaluation and the line that leads to it.
+#[derive(Debug, Clone)]
+struct RootMove {
+    m: Move,
+    eval: Evaluation,
+    pv: Vec<Move>,
+}
+
+/// The maximum depth of the search (in plies).
+const SEARCH_DEPTH: usize = 4;
+
+/// The minimum number of knights and bishops (of both sides) on their back rank for the position
+/// to be an early opening (see `Brain::with_opening_extension`).
+const UNDEVELOPED_MIN_PIECES: u32 = 4;
+
+/// The highest difficulty level (full strength), like the levels of the Lichess AI (see
+/// `Brain::set_level`).
+pub const MAX_LEVEL: u8 = 20;
+
+/// The highest score (in centipawns, either way) of an `Evaluation::Score`. Mates are encoded apart
+/// (`Evaluation::Best` and `Evaluation::Worst`), and scores stay well within `i32` so that
+/// combining or negating them never overflows.
+pub const MAX_SCORE_CP: i32 = 100_000;
+
+/// How much worse (in centipawns) than the best move the played move may be, for each level below
+/// `MAX_LEVEL`.
+const LEVEL_BLUNDER_MARGIN: i32 = 15;
+
+/// The ordering bonus of the moves that give check (see `list_potential_moves`), in pawns.
+const CHECK_ORDERING_BONUS: f32 = 1.0;
+
+/// The bonus (in centipawns) for having bishops on both square colors.
+const BISHOP_PAIR_BONUS: i32 = 30;
+
+/// How much worse (in centipawns) than the best move an alternative may be, to be played instead
+/// of repeating an oscillation.
+const OSCILLATION_MARGIN: i32 = 50;
+
+/// The number of moves (plies) from the start of the game during which the brain varies its moves.
+const OPENING_VARIETY_PLIES: usize = 10;
+
+/// The number of top moves to pick from, when varying the opening.
+const OPENING_VARIETY_MOVES: usize = 3;
+
+/// How much worse (in centipawns) than the best move a move may be, to be picked when varying the
+/// opening.
+const OPENING_VARIETY_MARGIN: i32 = 30;
+
+/// The material advantage (in centipawns) from which a side tries to drive the enemy king to the
+/// edge in the endgame: enough to mate with a rook.
+const MOP_UP_MIN_ADVANTAGE: i32 = 400;
+
+/// The bonus (in centipawns) for each step between the enemy king and the center, when mopping up.
+const MOP_UP_EDGE_WEIGHT: i32 = 10;
+
+/// The bonus (in centipawns) for each step closer between the kings, when mopping up.
+const MOP_UP_PROXIMITY_WEIGHT: i32 = 4;
+
+/// The bonus (in centipawns) for a passed pawn in the endgame, by rank from its own side (the
+/// 2nd rank is index 1, the 7th rank is index 6).
+const PASSED_PAWN_BONUS: [i32; 8] = [0, 10, 20, 35, 60, 100, 150, 0];
+
+/// The bonus (in centipawns) for a passed pawn defended by another pawn, or next to a friendly
+/// pawn on an adjacent file.
+const PASSED_PAWN_SUPPORT_BONUS: i32 = 20;
+
+/// The bonus (in centipawns) for each step the own king is closer than the enemy king to the
+/// square in front of a passed pawn.
+const PASSED_PAWN_ESCORT_WEIGHT: i32 = 8;
+
+/// The central squares: d4, e4, d5 and e5.
+const CENTER: BitBoard = BitBoard((FILE_D.0 | FILE_E.0) & (RANK_4.0 | RANK_5.0));
+
+/// The bonus (in centipawns) for each square attacked by a side, in the opening.
+const SQUARE_CONTROL_WEIGHT: i32 = 2;
+
+/// The extra bonus (in centipawns) for each central square attacked by a side, in the opening.
+const CENTER_CONTROL_WEIGHT: i32 = 10;
+
+/// The penalty (in centipawns) for each piece pinned to its own king (see `Board::pinned_pieces`).
+const PINNED_PIECE_PENALTY: i32 = 15;
+
+/// The penalty (in centipawns) for a knight on the A or H file with few squares to go to.
+const TRAPPED_KNIGHT_PENALTY: i32 = 30;
+
+/// The most squares a knight on the rim can go to (not counting the ones attacked by enemy pawns)
+/// and still be trapped.
+const TRAPPED_KNIGHT_MOBILITY: u32 = 2;
+
+/// The penalty (in centipawns) for a bishop whose forward diagonals are blocked by its own pawns.
+const TRAPPED_BISHOP_PENALTY: i32 = 25;
+
+/// The penalty (in centipawns) for each extra pawn on a file.
+const DOUBLED_PAWN_PENALTY: i32 = 15;
+
+/// The penalty (in centipawns) for each pawn without friendly pawns on the adjacent files.
+const ISOLATED_PAWN_PENALTY: i32 = 10;
+
+/// The number of entries in the pawn hash table of each thread, after which it is cleared.
+const PAWN_TABLE_SIZE: usize = 1 << 16;
+
+/// The number of entries in the evaluation table of each thread.
+const EVAL_TABLE_SIZE: usize = 1 << 16;
+
+/// An entry of the evaluation table: the evaluation of a position (from White's perspective), and
+/// its full hash to tell it apart from the other positions sharing the same entry.
+#[derive(Debug, Clone, Copy)]
+struct EvalEntry {
+    hash: u64,
+    eval: Evaluation,
+}
+
+thread_local! {
+    /// The pawn hash table: the pawn-structure evaluation (from White's perspective), by the pawn
+    /// hash of the position. Each search thread has its own, so no locking is needed.
+    static PAWN_TABLE: RefCell<HashMap<u64, i32>> = RefCell::new(HashMap::new());
+
+    /// The evaluation table: the static evaluation of the leaves, so that transpositions are only
+    /// evaluated once. It has a fixed size: each position goes in the entry picked by its Zobrist
+    /// hash, and replaces the one that was there.
+    static EVAL_TABLE: RefCell<Vec<Option<EvalEntry>>> = RefCell::new(vec![None; EVAL_TABLE_SIZE]);
+}
+
 impl Brain {
     /// Create a new brain with the given board and color.
     pub fn new(board: Board, color: Color) -> Self {
         Self {
+            initial: board,
             board,
             color,
             last_move: None,
             opponent_last_move: None,
+            recent_moves: [None; 4],
+            moves: Vec::new(),
+            history: vec![board.zobrist_hash()],
+            book: None,
+            opening_seed: None,
+            ponder: None,
+            level: MAX_LEVEL,
+            opening_extension: None,
+            max_nodes: None,
         }
     }
 
-    /// Select a move for the brain.
-    pub fn choose_move(&self, sensor: oneshot::Sender<Option<Move>>) {
+    /// Sets the difficulty level, from 0 (weakest) to `MAX_LEVEL` (full strength, the default).
+    /// Higher levels are clamped to `MAX_LEVEL`.
+    ///
+    /// Lower levels search less deeply, spend less of the time budget, and play a random move out
+    /// of the ones that aren't much worse than the best one, with a wider margin at each level
+    /// below (see `LEVEL_BLUNDER_MARGIN`).
+    pub fn set_level(&mut self, level: u8) {
+        self.level = level.min(MAX_LEVEL);
+    }
+
+    /// The difficulty level (see `set_level`).
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Varies the moves in the opening with the given seed: one of the top moves is picked at
+    /// random (see `pick_varied_move`). The same seed and moves always lead to the same choice.
+    pub fn with_opening_seed(mut self, seed: u64) -> Self {
+        self.opening_seed = Some(seed);
+        self
+    }
+
+    /// Searches the given number of extra plies during the first moves of the game, while the
+    /// material is full and the minor pieces are mostly undeveloped (see `is_undeveloped`), to
+    /// avoid falling into short opening traps. The extra plies are only searched if the time
+    /// budget allows it, like any other iteration.
+    pub fn with_opening_extension(mut self, moves: usize, plies: usize) -> Self {
+        self.opening_extension = Some((moves, plies));
+        self
+    }
+
+    /// The maximum depth to search the current position at (in plies): the depth of the level,
+    /// plus the opening extension if it applies (see `with_opening_extension`).
+    fn max_depth(&self) -> usize {
+        let extension = match self.opening_extension {
+            Some((moves, plies))
+                if self.plies_played() < moves * 2 && is_undeveloped(&self.board) =>
+            {
+                plies
+            }
+            _ => 0,
+        };
+        level_depth(self.level) + extension
+    }
+
+    /// Limits the search for a move to the given number of nodes (if any), in addition to the
+    /// time budget. Unlike the time, the nodes don't depend on the hardware, so the same position
+    /// always leads to the same move.
+    ///
+    /// The iteration that exhausts the budget is stopped and discarded: the move comes from the
+    /// last completed iteration.
+    pub fn with_max_nodes(mut self, max_nodes: Option<u64>) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Plays from the given opening book (if any) while the position is in it.
+    pub fn with_book(mut self, book: Option<Arc<OpeningBook>>) -> Self {
+        self.book = book;
+        self
+    }
+
+    /// Create a new brain with the given color, from a board in FEN notation.
+    pub fn from_fen(fen: &str, color: Color) -> anyhow::Result<Self> {
+        Ok(Self::new(Board::from_fen(fen)?, color))
+    }
+
+    /// Reset the brain to the position reached after playing the given moves from the initial FEN.
+    /// An empty FEN or `"startpos"` is treated as the default starting position.
+    ///
+    /// Every move is validated against the engine's own rules. An illegal move (a bug in the move
+    /// generation, or a desync) is an error, and the brain is left untouched.
+    pub fn set_position(
+        &mut self,
+        fen: &str,
+        moves: &[Move],
+        own_color: Color,
+    ) -> anyhow::Result<()> {
+        let initial = parse_initial_fen(fen)?;
+        let board = replay_moves(initial, moves)?;
+
+        // The last move was the brain's if it's now the opponent's turn, and vice-versa
+        let mut last_moves = moves.iter().rev().copied();
+        let (latest, previous) = (last_moves.next(), last_moves.next());
+        let (last_move, opponent_last_move) = if board.turn == own_color {
+            (previous, latest)
+        } else {
+            (latest, previous)
+        };
+
+        // Moves alternate between the sides, starting with the side to move in the initial FEN
+        let mut recent_moves = [None; 4];
+        moves
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (i % 2 == 0) == (initial.turn == own_color))
+            .for_each(|(_, m)| push_recent_move(&mut recent_moves, *m));
+
+        let mut history = Vec::with_capacity(moves.len() + 1);
+        let mut replay = initial;
+        history.push(replay.zobrist_hash());
+        for m in moves {
+            replay.apply_move(*m);
+            history.push(replay.zobrist_hash());
+        }
+        self.stop_pondering();
+
+        *self = Self {
+            color: own_color,
+            initial,
+            board,
+            last_move,
+            opponent_last_move,
+            recent_moves,
+            moves: moves.to_vec(),
+            history,
+            book: self.book.take(),
+            opening_seed: self.opening_seed,
+            ponder: None,
+            level: self.level,
+            opening_extension: self.opening_extension,
+            max_nodes: self.max_nodes,
+        };
+        Ok(())
+    }
+
+    /// The current board state.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The board at the start of the game (before any move was played).
+    pub fn initial_board(&self) -> &Board {
+        &self.initial
+    }
+
+    /// Whether it is the brain's turn to move.
+    pub fn is_own_turn(&self) -> bool {
+        self.board.turn == self.color
+    }
+
+    /// Select a move for the brain, thinking for about the given time budget.
+    ///
+    /// The search deepens one ply at a time, up to the maximum depth. Another iteration is only
+    /// started if less than half of the budget has been spent, since it is expected to take longer
+    /// than all the previous ones combined.
+    ///
+    /// The result of the search is sent with the chosen move, or `None` if there is no legal move.
+    /// When the position is in the opening book, the book move is sent right away instead.
+    ///
+    /// After a ponder hit (see `ponder_hit`), the ongoing search on the position becomes the move
+    /// search: it continues within the budget, and keeps the iterations it already completed.
+    pub fn choose_move(&self, sensor: oneshot::Sender<Option<SearchResult>>, budget: Duration) {
+        if let Some(m) = self.book.as_ref().and_then(|book| book.lookup(&self.board)) {
+            info!("Book move: {}", MoveWithBoard(m, &self.board));
+            let outcome = self.board.with_move(m);
+            let result = SearchResult {
+                best: m,
+                eval: evaluate(self.color, &outcome),
+                depth: 0,
+                seldepth: 0,
+                nodes: 0,
+                stats: SearchStats::default(),
+                pv: vec![m],
+                elapsed: Duration::default(),
+            };
+            sensor
+                .send(Some(result))
+                .expect("Failed to dispatch Brain move");
+            return;
+        }
+
         let board = self.board;
         let brain_color = self.color;
+        let recent_moves = self.recent_moves;
+        let seed = self.move_seed();
+        let level = self.level;
+        let max_depth = self.max_depth();
+        let max_nodes = self.max_nodes;
+        let budget = level_budget(budget, level);
+        let ponder = self
+            .ponder
+            .clone()
+            .filter(|ponder| ponder.hit && Some(&ponder.hash) == self.history.last());
 
         rayon::spawn(move || {
-            let mut rng = thread_rng();
-            let best = negamax(
-                brain_color,
-                board,
-                4,
-                Evaluation::Worst,
-                Evaluation::Best,
-                brain_color,
-                vec![],
-            );
+            let start = Instant::now();
+            let counters = SearchCounters::with_max_nodes(max_nodes);
+            // Hold any legal move, so that running out of time never loses the game by resigning
+            let mut best = fallback_move(board, brain_color).map(|(m, eval)| SearchResult {
+                best: m,
+                eval,
+                depth: 0,
+                seldepth: 0,
+                nodes: 0,
+                stats: SearchStats::default(),
+                pv: vec![m],
+                elapsed: Duration::default(),
+            });
+            if let Some(ponder) = ponder {
+                // Let the ongoing search on this position continue, within the budget
+                let (lock, done) = &*ponder.done;
+                let timeout = (budget / 2).saturating_sub(start.elapsed());
+                drop(
+                    done.wait_timeout_while(lock.lock().unwrap(), timeout, |done| !*done)
+                        .unwrap(),
+                );
+                ponder.stop.store(true, AtomicOrdering::Relaxed);
+                if let Some(result) = ponder.best.lock().unwrap().clone() {
+                    best = Some(result);
+                }
+            } else {
+                for depth in 1..=max_depth {
+                    if start.elapsed() >= budget {
+                        debug!("No time left to search at depth {}", depth);
+                        break;
+                    }
+                    if let Some(result) = search_iteration(
+                        board,
+                        brain_color,
+                        depth,
+                        &recent_moves,
+                        seed,
+                        level,
+                        &counters,
+                        start,
+                    ) {
+                        if counters.is_exhausted() {
+                            debug!("Node budget exhausted at depth {}", depth);
+                            break;
+                        }
+                        debug!("{}", result.uci_info());
+                        best = Some(result);
+                    }
+                    if start.elapsed() * 2 > budget {
+                        debug!("Stopping search at depth {} ({:?})", depth, start.elapsed());
+                        break;
+                    }
+                }
+            }
 
-            info!("Best eval: {:?}", best);
-            sensor.send(Some(best.m)).expect("Failed to dispatch Brain move");
+            if let Some(result) = &best {
+                info!(
+                    "Best move: {} ({:?}, depth {}, {} nodes in {:?})",
+                    MoveWithBoard(result.best, &board),
+                    result.eval,
+                    result.depth,
+                    result.nodes,
+                    result.elapsed
+                );
+            }
+            sensor.send(best).expect("Failed to dispatch Brain move");
         })
     }
 
+    /// Searches the brain's move at the given depth (in plies), without a time budget. The move is
+    /// picked like in `choose_move`. Returns `None` if there is no legal move.
+    pub fn search_at(&self, depth: usize) -> Option<SearchResult> {
+        let counters = SearchCounters::default();
+        search_iteration(
+            self.board,
+            self.color,
+            depth,
+            &self.recent_moves,
+            self.move_seed(),
+            self.level,
+            &counters,
+            Instant::now(),
+        )
+    }
+
+    /// The seed to vary the next move with, during the opening only, or for every move below the
+    /// full-strength level (see `set_level`). It changes with every move.
+    fn move_seed(&self) -> Option<u64> {
+        let plies = self.plies_played();
+        let seed = if self.level < MAX_LEVEL {
+            // Without an opening seed, the position decides
+            Some(self.opening_seed.unwrap_or(self.history[0]))
+        } else {
+            self.opening_seed.filter(|_| plies < OPENING_VARIETY_PLIES)
+        };
+        seed.map(|seed| seed ^ (plies as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    /// Returns the top `n` moves for the brain with their evaluation, sorted best-first.
+    ///
+    /// All the moves come out of the same search, at the given depth (in plies).
+    pub fn best_moves(&self, n: usize, depth: usize) -> Vec<(Move, Evaluation)> {
+        search_root(self.board, self.color, depth, &SearchCounters::default())
+            .into_iter()
+            .take(n)
+            .map(|root| (root.m, root.eval))
+            .collect()
+    }
+
+    /// The moves played since the initial position, oldest first.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// The number of moves (plies) played since the initial position.
+    pub fn plies_played(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// Whether playing the move would reach the same position for the third time (or more).
+    pub fn is_threefold_repetition(&self, m: Move) -> bool {
+        let outcome = self.board.with_move(m);
+        let hash = outcome.zobrist_hash();
+        self.history.iter().filter(|h| **h == hash).count() >= 2
+    }
+
+    /// Brings the brain up to date with the full list of moves played since the initial position,
+    /// e.g. from a game state received after reconnecting. Only the moves that the brain hasn't
+    /// played yet are applied, so the same list can be received more than once. If the list
+    /// doesn't start with the moves the brain played, the position is reset instead.
+    ///
+    /// Returns the moves that were new to the brain, or an error if one of them is illegal (in
+    /// which case the brain is left untouched).
+    pub fn sync_moves(&mut self, moves: &[Move]) -> anyhow::Result<Vec<Move>> {
+        let played = self.moves.len();
+        if moves.len() >= played && moves[..played] == self.moves[..] {
+            let new_moves = moves[played..].to_vec();
+            replay_moves(self.board, &new_moves)?;
+            for m in &new_moves {
+                if self.is_own_turn() {
+                    self.own_move(*m);
+                } else {
+                    self.opponent_move(*m);
+                }
+            }
+            Ok(new_moves)
+        } else {
+            warn!(
+                "Received moves diverge from the {} moves played, resetting the position",
+                played
+            );
+            let fen = self.initial.to_fen();
+            self.set_position(&fen, moves, self.color)?;
+            Ok(moves.to_vec())
+        }
+    }
+
     /// Apply a move from the opponent.
     pub fn opponent_move(&mut self, m: Move) {
-        self.board.apply_move(m);
+        self.push_move(m);
         self.opponent_last_move = Some(m);
     }
 
-    /// Apply a move by the brain.
+    /// Apply a move by the brain. Any search from the opponent's turn is stopped.
     pub fn own_move(&mut self, m: Move) {
-        self.board.apply_move(m);
+        self.stop_pondering();
+        self.push_move(m);
         self.last_move = Some(m);
+        push_recent_move(&mut self.recent_moves, m);
+    }
+
+    /// Starts searching the position after the opponent's predicted move, during the opponent's
+    /// turn. Once the opponent moves, call `ponder_hit` if it played the predicted move, or
+    /// `ponder_miss` otherwise.
+    ///
+    /// Nothing is started if it is the brain's turn, or if the predicted move is illegal.
+    pub fn ponder(&mut self, predicted: Move) {
+        self.stop_pondering();
+        if self.is_own_turn() || !self.board.is_legal_move(predicted) {
+            return;
+        }
+
+        let mut future = self.clone();
+        future.opponent_move(predicted);
+        let ponder = Ponder {
+            predicted,
+            hash: *future.history.last().expect("empty history"),
+            hit: false,
+            best: Arc::new(Mutex::new(None)),
+            stop: Arc::new(AtomicBool::new(false)),
+            done: Arc::new((Mutex::new(false), Condvar::new())),
+        };
+        debug!("Pondering on {}", predicted.to_pure_notation());
+
+        let search = ponder.clone();
+        let seed = future.move_seed();
+        rayon::spawn(move || {
+            let start = Instant::now();
+            let counters = SearchCounters::with_stop(search.stop.clone());
+            for depth in 1..=level_depth(future.level) {
+                let result = search_iteration(
+                    future.board,
+                    future.color,
+                    depth,
+                    &future.recent_moves,
+                    seed,
+                    future.level,
+                    &counters,
+                    start,
+                );
+                if counters.is_exhausted() {
+                    break;
+                }
+                if result.is_some() {
+                    *search.best.lock().unwrap() = result;
+                }
+            }
+            let (lock, done) = &*search.done;
+            *lock.lock().unwrap() = true;
+            done.notify_all();
+        });
+        self.ponder = Some(ponder);
+    }
+
+    /// The opponent's predicted move that the brain is pondering on, if any.
+    pub fn pondered_move(&self) -> Option<Move> {
+        self.ponder.as_ref().map(|ponder| ponder.predicted)
+    }
+
+    /// The opponent played the predicted move: the search started by `ponder` keeps running, and
+    /// becomes the search of the next `choose_move`.
+    pub fn ponder_hit(&mut self) {
+        if let Some(ponder) = &mut self.ponder {
+            debug!("Ponder hit: {}", ponder.predicted.to_pure_notation());
+            ponder.hit = true;
+        }
+    }
+
+    /// The opponent didn't play the predicted move: the search started by `ponder` is discarded,
+    /// and the next `choose_move` searches from scratch.
+    pub fn ponder_miss(&mut self) {
+        if let Some(ponder) = &self.ponder {
+            debug!("Ponder miss: {}", ponder.predicted.to_pure_notation());
+        }
+        self.stop_pondering();
+    }
+
+    /// Stops the search started by `ponder`, if any.
+    fn stop_pondering(&mut self) {
+        if let Some(ponder) = self.ponder.take() {
+            ponder.stop.store(true, AtomicOrdering::Relaxed);
+        }
+    }
+
+    /// Plays a move on the board, and records the position, hashed incrementally.
+    fn push_move(&mut self, m: Move) {
+        let hash = *self.history.last().expect("empty history");
+        let (_, hash) = self.board.apply_move_hashed(m, hash);
+        self.moves.push(m);
+        self.history.push(hash);
     }
 }
 
-/// List the potential movements and attacks by all pieces by the given color in the given board.
-fn list_potential_moves(board: Board, color: Color) -> MoveCollection {
-    let side = board.get_side(color);
-    [
-        "pawn",
-        "rook:sliding",
-        "knight:step",
-        "bishop:sliding",
-        "queen:sliding",
-        "king:step",
-    ]
-    .iter()
-    .flat_map(|task| match *task {
-        "pawn" => side
-            .pawns
-            .map(|pawn| {
-                (
-                    pawn,
-                    pieces::pawn::get_pawn_moves_and_attacks(&board, color, &BitBoard::from(pawn)),
-                    true,
-                )
-            })
-            .collect(),
-        "rook:sliding" => side
-            .rooks
-            .map(|rook| {
-                (
-                    rook,
-                    pieces::rook::get_rook_sliding_moves(&board, color, &BitBoard::from(rook)),
-                    false,
-                )
-            })
-            .collect(),
-        "knight:step" => side
-            .knights
-            .map(|knight| {
-                (
-                    knight,
-                    pieces::knight::get_knight_moves(&board, color, knight),
-                    false,
-                )
-            })
-            .collect(),
-        "bishop:sliding" => side
-            .bishops
-            .map(|bishop| {
-                (
-                    bishop,
-                    pieces::bishop::get_bishop_sliding_moves(
-                        &board,
-                        color,
-                        &BitBoard::from(bishop),
-                    ),
-                    false,
-                )
-            })
-            .collect(),
-        "queen:sliding" => side
-            .queens
-            .map(|queen| {
-                (
-                    queen,
-                    pieces::queen::get_queen_sliding_moves(&board, color, &BitBoard::from(queen)),
-                    false,
-                )
-            })
-            .collect(),
-        "king:step" => side
-            .king
-            .map(|king| {
-                (
-                    king,
-                    pieces::king::get_king_steps(&board, color, king),
-                    false,
-                )
-            })
-            .collect(),
-        _ => Vec::with_capacity(0),
+/// Searches the best move of the given color on the board, at the given depth (in plies, at
+/// least 1). This is the search behind `Brain`, without the game bookkeeping.
+///
+/// Returns `None` if there is no legal move.
+pub fn search(board: &Board, color: Color, depth: usize) -> Option<SearchResult> {
+    let counters = SearchCounters::default();
+    search_iteration(
+        *board,
+        color,
+        depth,
+        &[None; 4],
+        None,
+        MAX_LEVEL,
+        &counters,
+        Instant::now(),
+    )
+}
+
+/// Searches the board at the given depth, and picks a move out of the root moves (see
+/// `pick_move`, or `pick_varied_move` with a seed and the level). The nodes are added to the given
+/// counters, and the elapsed time is measured from the given start of the search.
+#[allow(clippy::too_many_arguments)]
+fn search_iteration(
+    board: Board,
+    color: Color,
+    depth: usize,
+    recent_moves: &[Option<Move>; 4],
+    seed: Option<u64>,
+    level: u8,
+    counters: &SearchCounters,
+    start: Instant,
+) -> Option<SearchResult> {
+    let root_moves = search_root(board, color, depth, counters);
+    let scored = root_moves
+        .iter()
+        .map(|root| (root.m, root.eval))
+        .collect::<Vec<(Move, Evaluation)>>();
+    let (m, eval) = match seed {
+        Some(seed) => pick_varied_move(&scored, seed, level),
+        None => pick_move(&scored, recent_moves),
+    }?;
+    let pv = root_moves
+        .into_iter()
+        .find(|root| root.m == m)
+        .map(|root| root.pv)
+        .unwrap_or_else(|| vec![m]);
+    Some(SearchResult {
+        best: m,
+        eval,
+        depth,
+        seldepth: counters.seldepth.load(AtomicOrdering::Relaxed),
+        nodes: counters.nodes.load(AtomicOrdering::Relaxed),
+        stats: counters.stats(),
+        pv,
+        elapsed: start.elapsed(),
     })
-    .flat_map(|(origin, moves, can_promote)| {
-        moves.map(move |destination| {
-            if can_promote {
-                Move(origin, destination, promote(destination))
-            } else {
-                Move::from((origin, destination))
-            }
+}
+
+/// Records a move by the brain, forgetting the oldest one.
+fn push_recent_move(recent_moves: &mut [Option<Move>; 4], m: Move) {
+    recent_moves.rotate_left(1);
+    recent_moves[3] = Some(m);
+}
+
+/// Whether the recent moves oscillate between two moves (A, B, A, B).
+fn is_oscillating(recent_moves: &[Option<Move>; 4]) -> bool {
+    match recent_moves {
+        [Some(a1), Some(b1), Some(a2), Some(b2)] => a1 == a2 && b1 == b2 && a1 != b1,
+        _ => false,
+    }
+}
+
+/// Picks the best of the scored moves (sorted best-first).
+///
+/// When the brain is oscillating and the best move would repeat the cycle, a different move is
+/// played instead, as long as it isn't much worse. A flat evaluation otherwise keeps shuffling the
+/// same pieces back and forth, burning the clock without making progress.
+fn pick_move(
+    scored: &[(Move, Evaluation)],
+    recent_moves: &[Option<Move>; 4],
+) -> Option<(Move, Evaluation)> {
+    let best = *scored.first()?;
+    if !is_oscillating(recent_moves) || Some(best.0) != recent_moves[2] {
+        return Some(best);
+    }
+
+    let alternative = scored
+        .iter()
+        .skip(1)
+        .find(|(_, eval)| match (best.1, *eval) {
+            (Evaluation::Score(best), Evaluation::Score(eval)) => best - eval <= OSCILLATION_MARGIN,
+            _ => false,
+        });
+    match alternative {
+        Some(alternative) => {
+            debug!(
+                "Breaking oscillation with {:?} instead of {:?}",
+                alternative, best
+            );
+            Some(*alternative)
+        }
+        None => Some(best),
+    }
+}
+
+/// Picks one of the top scored moves (sorted best-first) at random, as long as it isn't much worse
+/// than the best one. The choice only depends on the seed, the level and the moves.
+///
+/// At full strength, this is one of the few top moves (see `OPENING_VARIETY_MOVES`); at lower
+/// levels, any move within a margin that widens at each level below (see `level_margin`).
+fn pick_varied_move(
+    scored: &[(Move, Evaluation)],
+    seed: u64,
+    level: u8,
+) -> Option<(Move, Evaluation)> {
+    let best = *scored.first()?;
+    let (moves, margin) = if level < MAX_LEVEL {
+        (
+            scored.len(),
+            level_margin(level).max(OPENING_VARIETY_MARGIN),
+        )
+    } else {
+        (OPENING_VARIETY_MOVES, OPENING_VARIETY_MARGIN)
+    };
+    let candidates = scored
+        .iter()
+        .take(moves)
+        .filter(|(_, eval)| match (best.1, *eval) {
+            (Evaluation::Score(best), Evaluation::Score(eval)) => best - eval <= margin,
+            (best, eval) => best == eval,
         })
-    })
-    .map(|m| {
-        let piece_type = board.get_piece(m.0).unwrap();
-        let mut estimate = 0.0;
-        if piece_type.is_pawn() {
-            estimate += 0.5;
+        .collect::<Vec<&(Move, Evaluation)>>();
+    let mut rng = StdRng::seed_from_u64(seed);
+    Some(*candidates[rng.gen_range(0..candidates.len())])
+}
+
+/// The maximum depth of the search (in plies) at the given level, from 1 at level 0 to
+/// `SEARCH_DEPTH` at `MAX_LEVEL`.
+fn level_depth(level: u8) -> usize {
+    1 + (SEARCH_DEPTH - 1) * level as usize / MAX_LEVEL as usize
+}
+
+/// The share of the time budget to spend at the given level, from 1/21 at level 0 to the whole
+/// budget at `MAX_LEVEL`.
+fn level_budget(budget: Duration, level: u8) -> Duration {
+    budget * (level as u32 + 1) / (MAX_LEVEL as u32 + 1)
+}
+
+/// How much worse (in centipawns) than the best move the played move may be at the given level.
+fn level_margin(level: u8) -> i32 {
+    (MAX_LEVEL - level) as i32 * LEVEL_BLUNDER_MARGIN
+}
+
+/// Whether the position looks like an early opening: all the non-pawn material is on the board,
+/// and at least `UNDEVELOPED_MIN_PIECES` knights and bishops are still on their back rank.
+fn is_undeveloped(board: &Board) -> bool {
+    let back_rank = |color: Color, rank: BitBoard| {
+        let side = board.get_side(color);
+        ((side.knights | side.bishops) & rank).popcnt()
+    };
+    board.phase_scalar() == 0
+        && back_rank(Color::White, RANK_1) + back_rank(Color::Black, RANK_8)
+            >= UNDEVELOPED_MIN_PIECES
+}
+
+/// Returns the first move by estimate (see `list_potential_moves`) with its static evaluation, to
+/// play when there is no time to search.
+fn fallback_move(board: Board, color: Color) -> Option<(Move, Evaluation)> {
+    let m = list_potential_moves(board, color, false).first()?.m;
+    let outcome = board.with_move(m);
+    Some((m, evaluate(color, &outcome)))
+}
+
+/// Parses the initial FEN of a game.
+/// An empty FEN or `"startpos"` is treated as the default starting position.
+fn parse_initial_fen(fen: &str) -> anyhow::Result<Board> {
+    let fen = fen.trim();
+    if fen.is_empty() || fen == "startpos" {
+        Ok(Board::default())
+    } else {
+        Board::from_fen(fen).with_context(|| format!("Invalid initial FEN: {}", fen))
+    }
+}
+
+/// Replays the moves on the initial board, validating each of them.
+///
+/// If a move is illegal according to the engine (a bug in the move generator, or a desync),
+/// returns an error with that move and the FEN of the position it was played in.
+fn replay_moves(mut board: Board, moves: &[Move]) -> anyhow::Result<Board> {
+    for m in moves {
+        if !board.is_legal_move(*m) {
+            anyhow::bail!(
+                "Illegal move {} in position: {}",
+                m.to_pure_notation(),
+                board.to_fen()
+            );
         }
-        BrainMove { estimate, m }
-    })
-    .sorted()
-    .rev()
-    .collect::<MoveCollection>()
+        board.apply_move(*m);
+    }
+    Ok(board)
 }
 
-/// The recursive MiniMax function, with alpha-beta pruning.
-fn negamax(
-    genius_color: Color,
+/// List the legal moves by the given color in the given board, ordered by estimate (best first).
+/// Moves with the same estimate keep the order of `Board::legal_moves`.
+///
+/// Unless the ordering is `basic`, captures are estimated by the material they win or lose (see
+/// `Board::static_exchange`), and checks get a bonus (see `CHECK_ORDERING_BONUS`). Neither plays
+/// the moves: both only look at the attacks on the board.
+fn list_potential_moves(board: Board, color: Color, basic: bool) -> MoveCollection {
+    board
+        .legal_moves(color)
+        .into_iter()
+        .map(|m| {
+            let piece_kind = board.get_side(color).get_piece_kind(m.0);
+            let mut estimate = 0.0;
+            if piece_kind == Some(PieceKind::Pawn) {
+                estimate += 0.5;
+            }
+            if !basic {
+                estimate += board.static_exchange(m) as f32 / PIECE_VALUES_CP[0] as f32;
+                if board.gives_check(m) {
+                    estimate += CHECK_ORDERING_BONUS;
+                }
+            }
+            BrainMove { estimate, m }
+        })
+        .sorted_by(|a, b| b.cmp(a))
+        .collect::<MoveCollection>()
+}
+
+/// Evaluates every legal move of the given color, searching at the given depth (in plies).
+/// Returns the moves with their evaluation and principal variation, sorted best-first.
+///
+/// Each root move is searched with a full window so that its evaluation is exact, which allows
+/// ranking all of them (not only the best one).
+fn search_root(
     board: Board,
+    color: Color,
+    depth: usize,
+    counters: &SearchCounters,
+) -> Vec<RootMove> {
+    let hash = board.zobrist_hash();
+    let mut scored = list_potential_moves(board, color, counters.basic_ordering())
+        .into_par_iter()
+        .map(|m| {
+            let mut outcome = board;
+            let (_, outcome_hash) = outcome.apply_move_hashed(m.m, hash);
+            let node = -negamax(
+                outcome,
+                outcome_hash,
+                depth.max(1) - 1,
+                Evaluation::Worst,
+                Evaluation::Best,
+                color.opposite(),
+                vec![m.m],
+                counters,
+            );
+            RootMove {
+                m: m.m,
+                eval: node.eval,
+                pv: node.line,
+            }
+        })
+        .collect::<Vec<RootMove>>();
+
+    // Stable sort, so equal moves keep the estimate ordering
+    scored.sort_by_key(|root| Reverse(root.eval));
+    scored
+}
+
+/// The recursive MiniMax function, with alpha-beta pruning. The hash is the Zobrist hash of the
+/// board, updated incrementally from move to move.
+#[allow(clippy::too_many_arguments)]
+fn negamax(
+    mut board: Board,
+    hash: u64,
     depth: usize,
     mut alpha: Evaluation,
-    mut beta: Evaluation,
+    beta: Evaluation,
     color: Color,
     previous_moves: Vec<Move>,
+    counters: &SearchCounters,
 ) -> Node {
-    let moves = list_potential_moves(board, color);
-    if depth == 0 || moves.is_empty() {
-        let eval = if moves.is_empty() && color == genius_color {
+    if !counters.visit(previous_moves.len()) {
+        // Out of nodes: the iteration is discarded
+        return Node {
+            eval: Evaluation::Score(0),
+            line: previous_moves,
+        };
+    }
+
+    // Neither side can win from here, whatever the moves
+    if board.is_dead_position() {
+        return Node {
+            eval: Evaluation::Score(0),
+            line: previous_moves,
+        };
+    }
+
+    // Leaves only need to know whether there is any move, not to list them
+    let moves = if depth == 0 {
+        Vec::new()
+    } else {
+        list_potential_moves(board, color, counters.basic_ordering())
+    };
+    let no_moves = if depth == 0 {
+        board.legal_move_count(color) == 0
+    } else {
+        moves.is_empty()
+    };
+
+    if depth == 0 || no_moves {
+        // The evaluation is from the perspective of the side to move
+        let eval = if no_moves && board.is_in_check(color) {
+            // Checkmate
             Evaluation::Worst
-        } else if moves.is_empty() && color != genius_color {
-            Evaluation::Best
+        } else if no_moves {
+            // Stalemate
+            Evaluation::Score(0)
         } else {
-            evaluate(genius_color, &board)
+            quiescence(
+                board,
+                hash,
+                alpha,
+                beta,
+                color,
+                previous_moves.len(),
+                counters,
+            )
         };
         info!("Moves: {:?} = {:?}", previous_moves, eval);
         Node {
             eval,
-            m: previous_moves[0],
+            line: previous_moves,
         }
     } else {
         let mut value = Node::default();
         for m in moves {
-            let mut outcome = board;
-            outcome.apply_move(m.m);
+            let (previous, outcome_hash) = board.apply_move_hashed(m.m, hash);
 
-            // Debugging
             let mut previous_moves = previous_moves.clone();
             previous_moves.push(m.m);
 
             value = max(
                 value,
                 -negamax(
-                    genius_color,
-                    outcome,
+                    board,
+                    outcome_hash,
                     depth - 1,
                     -beta,
                     -alpha,
                     color.opposite(),
                     previous_moves,
+                    counters,
                 ),
             );
+            board.restore_hashed(previous, outcome_hash);
 
             alpha = max(alpha, value.eval);
             if alpha >= beta {
+                counters.beta_cutoffs.fetch_add(1, AtomicOrdering::Relaxed);
                 break;
             }
         }
@@ -259,29 +1261,356 @@ fn negamax(
     }
 }
 
+/// The quiescence search, at the leaves of `negamax`: the captures and promotions that don't lose
+/// material (see `Board::static_exchange`) are searched further until the position is quiet, so
+/// that the evaluation doesn't stop in the middle of an exchange. The side to move can also
+/// "stand pat" on the static evaluation, since it isn't forced to capture.
+///
+/// Checks aren't looked at, so a mate at the end of the exchanges is not detected.
+///
+/// Ref: https://www.chessprogramming.org/Quiescence_Search
+fn quiescence(
+    mut board: Board,
+    hash: u64,
+    mut alpha: Evaluation,
+    beta: Evaluation,
+    color: Color,
+    ply: usize,
+    counters: &SearchCounters,
+) -> Evaluation {
+    let mut value = evaluate_leaf(color, &board, hash, counters);
+    if value >= beta {
+        return value;
+    }
+    alpha = max(alpha, value);
+
+    let captures = board
+        .generate_captures(color)
+        .into_iter()
+        .map(|m| (board.static_exchange(m), m))
+        .filter(|(exchange, _)| *exchange >= 0)
+        .sorted_by_key(|(exchange, _)| Reverse(*exchange));
+    for (_, m) in captures {
+        if !counters.visit(ply + 1) {
+            break;
+        }
+        counters
+            .quiescence_nodes
+            .fetch_add(1, AtomicOrdering::Relaxed);
+
+        let (previous, outcome_hash) = board.apply_move_hashed(m, hash);
+        value = max(
+            value,
+            -quiescence(
+                board,
+                outcome_hash,
+                -beta,
+                -alpha,
+                color.opposite(),
+                ply + 1,
+                counters,
+            ),
+        );
+        board.restore_hashed(previous, outcome_hash);
+        alpha = max(alpha, value);
+        if alpha >= beta {
+            counters.beta_cutoffs.fetch_add(1, AtomicOrdering::Relaxed);
+            break;
+        }
+    }
+    value
+}
+
+/// The static evaluation of a leaf from the given color's perspective, from the evaluation table
+/// unless it is disabled (see `SearchCounters::no_eval_table`). The hash is the Zobrist hash of the
+/// board, updated incrementally along the search.
+fn evaluate_leaf(color: Color, board: &Board, hash: u64, counters: &SearchCounters) -> Evaluation {
+    let (eval, hit) = if counters.no_eval_table() {
+        (evaluate(color, board), false)
+    } else {
+        cached_evaluate(color, board, hash)
+    };
+    counters.evaluated(hit);
+    eval
+}
+
+/// Evaluates the board from the given color's perspective, in centipawns.
 fn evaluate(color: Color, board: &Board) -> Evaluation {
-    let score = board.piecewise_score(color);
-    Evaluation::Score(score as i32)
+    let material = board.material_balance_cp(color);
+    let king_activity = king_activity(board, color) - king_activity(board, color.opposite());
+    let mop_up = mop_up(board, color) - mop_up(board, color.opposite());
+    let bishop_pair = bishop_pair(board, color) - bishop_pair(board, color.opposite());
+    let passed_pawns = passed_pawns(board, color) - passed_pawns(board, color.opposite());
+    let square_control = square_control(board, color) - square_control(board, color.opposite());
+    let pins = pins(board, color) - pins(board, color.opposite());
+    let trapped_pieces = trapped_pieces(board, color) - trapped_pieces(board, color.opposite());
+    let pawn_structure = if color.is_white() {
+        cached_pawn_structure(board)
+    } else {
+        -cached_pawn_structure(board)
+    };
+    Evaluation::from_terms(&[
+        material,
+        king_activity,
+        mop_up,
+        bishop_pair,
+        pawn_structure,
+        passed_pawns,
+        square_control,
+        pins,
+        trapped_pieces,
+    ])
+}
+
+/// Returns the static evaluation (see `evaluate`) from the evaluation table, computing it on a
+/// miss, along with whether it was found in the table. The hash must be the Zobrist hash of the
+/// board (see `Board::zobrist_hash`).
+fn cached_evaluate(color: Color, board: &Board, hash: u64) -> (Evaluation, bool) {
+    let (eval, hit) = EVAL_TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let entry = &mut table[hash as usize % EVAL_TABLE_SIZE];
+        match entry {
+            Some(cached) if cached.hash == hash => (cached.eval, true),
+            _ => {
+                let eval = evaluate(Color::White, board);
+                *entry = Some(EvalEntry { hash, eval });
+                (eval, false)
+            }
+        }
+    });
+    if color.is_white() {
+        (eval, hit)
+    } else {
+        (-eval, hit)
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Returns the pawn-structure evaluation (see `pawn_structure`) from the pawn hash table,
+/// computing it on a miss.
+fn cached_pawn_structure(board: &Board) -> i32 {
+    let key = board.pawn_hash();
+    PAWN_TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        if let Some(score) = table.get(&key) {
+            return *score;
+        }
+        if table.len() >= PAWN_TABLE_SIZE {
+            table.clear();
+        }
+        let score = pawn_structure(board);
+        table.insert(key, score);
+        score
+    })
+}
+
+/// Scores the pawn structure from White's perspective, in centipawns: doubled and isolated pawns
+/// are penalized. It only depends on the pawns, so it can be cached by the pawn hash.
+fn pawn_structure(board: &Board) -> i32 {
+    side_pawn_structure(board.white.pawns) - side_pawn_structure(board.black.pawns)
+}
+
+/// Scores the structure of a side's pawns, in centipawns (zero or less).
+fn side_pawn_structure(pawns: BitBoard) -> i32 {
+    let mut score = 0;
+    for (file, mask) in FILES.iter().enumerate() {
+        let count = (pawns & *mask).popcnt() as i32;
+        if count == 0 {
+            continue;
+        }
+        score -= DOUBLED_PAWN_PENALTY * (count - 1);
+
+        let mut neighbours = EMPTY;
+        if file > 0 {
+            neighbours |= FILES[file - 1];
+        }
+        if file < 7 {
+            neighbours |= FILES[file + 1];
+        }
+        if pawns & neighbours == EMPTY {
+            score -= ISOLATED_PAWN_PENALTY * count;
+        }
+    }
+    score
+}
+
+/// Scores the squares controlled by the given color in the opening, in centipawns: every square
+/// attacked by its pawns and pieces (see `Board::piece_attacks`) counts, and the central ones
+/// count more. This rewards developing the pieces towards the center, but not walking the king
+/// there. Tapered by the game phase.
+fn square_control(board: &Board, color: Color) -> i32 {
+    let attacks = board.piece_attacks(color);
+    let score = SQUARE_CONTROL_WEIGHT * attacks.popcnt() as i32
+        + CENTER_CONTROL_WEIGHT * (attacks & CENTER).popcnt() as i32;
+
+    let phase = board.phase_scalar() as i32;
+    let total = ENDGAME_PHASE as i32;
+    score * (total - phase) / total
+}
+
+/// Scores the passed pawns of the given color in the endgame, in centipawns: pawns without enemy
+/// pawns in front of them (on their file or the adjacent ones) are rewarded as they advance, when
+/// supported by other pawns, and when the own king is closer than the enemy king to escort them.
+/// Tapered by the game phase.
+fn passed_pawns(board: &Board, color: Color) -> i32 {
+    let side = board.get_side(color);
+    let enemy = board.get_side(color.opposite());
+    let (king, enemy_king) = match (
+        board.king_position(color),
+        board.king_position(color.opposite()),
+    ) {
+        (Some(king), Some(enemy_king)) if side.pawns != EMPTY => (king, enemy_king),
+        _ => return 0,
+    };
+    let defended = board.pawn_attacks(color);
+
+    let mut score = 0;
+    for pawn in side.pawns {
+        let file = pawn.file_x as usize;
+        let mut files = FILES[file];
+        let mut neighbours = EMPTY;
+        if file > 0 {
+            neighbours |= FILES[file - 1];
+        }
+        if file < 7 {
+            neighbours |= FILES[file + 1];
+        }
+        files |= neighbours;
+
+        let rank = pawn.rank_y as usize;
+        let (relative_rank, ahead) = if color.is_white() {
+            (rank, ((rank + 1)..8).fold(EMPTY, |bb, r| bb | RANKS[r]))
+        } else {
+            (7 - rank, (0..rank).fold(EMPTY, |bb, r| bb | RANKS[r]))
+        };
+        if enemy.pawns & files & ahead != EMPTY {
+            continue;
+        }
+
+        score += PASSED_PAWN_BONUS[relative_rank];
+
+        let adjacent_ranks =
+            (rank.max(1) - 1..=(rank + 1).min(7)).fold(EMPTY, |bb, r| bb | RANKS[r]);
+        if defended & BitBoard::from(pawn) != EMPTY
+            || side.pawns & neighbours & adjacent_ranks != EMPTY
+        {
+            score += PASSED_PAWN_SUPPORT_BONUS;
+        }
+
+        let front = pawn.forwards(color, 1);
+        score += PASSED_PAWN_ESCORT_WEIGHT
+            * (enemy_king.distance(&front) as i32 - king.distance(&front) as i32);
+    }
+    score * board.phase_scalar() as i32 / ENDGAME_PHASE as i32
+}
+
+/// The penalty (in centipawns, zero or less) for the pieces of a side pinned to its king.
+fn pins(board: &Board, color: Color) -> i32 {
+    -PINNED_PIECE_PENALTY * board.pinned_pieces(color).popcnt() as i32
+}
+
+/// The penalty (in centipawns, zero or less) for the minor pieces of a side that have nowhere to
+/// go: knights on the rim with few safe squares, and bishops buried behind their own pawns.
+fn trapped_pieces(board: &Board, color: Color) -> i32 {
+    let side = board.get_side(color);
+    let enemy_pawn_attacks = board.pawn_attacks(color.opposite());
+
+    let trapped_knights = (side.knights & (FILE_A | FILE_H))
+        .filter(|knight| {
+            let squares = get_knight_moves(board, color, *knight) & !enemy_pawn_attacks;
+            squares.popcnt() <= TRAPPED_KNIGHT_MOBILITY
+        })
+        .count() as i32;
+
+    let (east, west) = if color.is_white() {
+        (Direction::NE, Direction::NW)
+    } else {
+        (Direction::SE, Direction::SW)
+    };
+    let trapped_bishops = side
+        .bishops
+        .filter(|bishop| {
+            let bishop = BitBoard::from(*bishop);
+            let forward = bishop.shift(east) | bishop.shift(west);
+            forward != EMPTY && forward & !side.pawns == EMPTY
+        })
+        .count() as i32;
+
+    -TRAPPED_KNIGHT_PENALTY * trapped_knights - TRAPPED_BISHOP_PENALTY * trapped_bishops
+}
+
+/// The bonus (in centipawns) for a side with bishops on both square colors, which together
+/// cover the whole board.
+fn bishop_pair(board: &Board, color: Color) -> i32 {
+    let bishops = board.get_side(color).bishops;
+    let light = bishops.filter(|p| p.is_light_square()).count();
+    if light > 0 && light < bishops.popcnt() as usize {
+        BISHOP_PAIR_BONUS
+    } else {
+        0
+    }
+}
+
+/// Scores the placement of the king of the given color, in centipawns.
+///
+/// In the opening, the king should stay safe on its back rank. In the endgame, it becomes an
+/// active piece and should head for the center. The two are tapered by the game phase.
+fn king_activity(board: &Board, color: Color) -> i32 {
+    let king = match board.king_position(color) {
+        Some(king) => king,
+        None => return 0,
+    };
+
+    let back_rank: i32 = if color.is_white() { 0 } else { 7 };
+    let opening = -10 * (king.rank_y as i32 - back_rank).abs();
+    let endgame = 20 - 10 * king.center_distance() as i32;
+
+    let phase = board.phase_scalar() as i32;
+    let total = ENDGAME_PHASE as i32;
+    (opening * (total - phase) + endgame * phase) / total
+}
+
+/// Scores the herding of the enemy king by the given color, in centipawns, when that color is
+/// winning in the endgame against a side without pawns: the enemy king should be driven to the
+/// edge of the board, and the own king should come closer to it to help mate (e.g. with K+Q vs K).
+/// Tapered by the game phase.
+fn mop_up(board: &Board, color: Color) -> i32 {
+    if board.material_balance_cp(color) < MOP_UP_MIN_ADVANTAGE
+        || board.get_side(color.opposite()).pawns != EMPTY
+    {
+        return 0;
+    }
+    let (king, enemy_king) = match (
+        board.king_position(color),
+        board.king_position(color.opposite()),
+    ) {
+        (Some(king), Some(enemy_king)) => (king, enemy_king),
+        _ => return 0,
+    };
+
+    let edge = MOP_UP_EDGE_WEIGHT * enemy_king.center_distance() as i32;
+    let proximity = MOP_UP_PROXIMITY_WEIGHT * (7 - king.distance(&enemy_king) as i32);
+    (edge + proximity) * board.phase_scalar() as i32 / ENDGAME_PHASE as i32
+}
+
+/// A searched node, with the line of moves (from the root) that leads to its evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Node {
     eval: Evaluation,
-    m: Move,
+    line: Vec<Move>,
 }
 
 impl Default for Node {
     fn default() -> Self {
         Node {
             eval: Evaluation::Worst,
-            m: Move(Position::default(), Position::default(), Promotion::None),
+            line: Vec::new(),
         }
     }
 }
 
 impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.eval.cmp(&other.eval))
+        Some(self.cmp(other))
     }
 }
 
@@ -297,7 +1626,7 @@ impl Neg for Node {
     fn neg(self) -> Self::Output {
         Self {
             eval: -self.eval,
-            m: self.m,
+            line: self.line,
         }
     }
 }
@@ -313,6 +1642,32 @@ pub enum Evaluation {
     Best,
 }
 
+impl Evaluation {
+    /// The score of the sum of the evaluation terms (in centipawns), saturating instead of
+    /// overflowing, and clamped to `MAX_SCORE_CP` either way.
+    pub fn from_terms(terms: &[i32]) -> Self {
+        Evaluation::Score(0)
+            + terms
+                .iter()
+                .fold(0, |sum: i32, term| sum.saturating_add(*term))
+    }
+}
+
+/// Adding centipawns to a score saturates at `MAX_SCORE_CP` either way, so that it never reaches
+/// a mate. Mates are left as they are.
+impl Add<i32> for Evaluation {
+    type Output = Evaluation;
+    #[inline]
+    fn add(self, cp: i32) -> Evaluation {
+        match self {
+            Evaluation::Score(s) => {
+                Evaluation::Score(s.saturating_add(cp).clamp(-MAX_SCORE_CP, MAX_SCORE_CP))
+            }
+            mate => mate,
+        }
+    }
+}
+
 /// Negating an evaluation results in the corresponding one from the other
 /// player's perspective.
 impl Neg for Evaluation {
@@ -327,11 +1682,917 @@ impl Neg for Evaluation {
     }
 }
 
-/// Selects the promotion to get based on destination position.
-fn promote(destination: Position) -> Promotion {
-    if destination.rank_y == 0 || destination.rank_y == 7 {
-        Promotion::Queen
-    } else {
-        Promotion::None
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(line: &str) -> Vec<Move> {
+        line.split(' ').map(Move::from_pure_notation).collect()
+    }
+
+    #[test]
+    fn test_parse_initial_fen() {
+        assert_eq!(parse_initial_fen("").unwrap(), Board::default());
+        assert_eq!(parse_initial_fen("startpos").unwrap(), Board::default());
+
+        let fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2";
+        assert_eq!(
+            parse_initial_fen(fen).unwrap(),
+            Board::from_fen(fen).unwrap()
+        );
+
+        assert!(parse_initial_fen("rnbqkbnr/pppppppp w KQkq - 0 1").is_err());
+    }
+
+    #[test]
+    fn test_replay_moves() {
+        let board = replay_moves(Board::default(), &moves("e2e4 e7e5 g1f3")).unwrap();
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        // The king can't jump two squares forward
+        assert_eq!(
+            replay_moves(Board::default(), &moves("e2e4 e7e5 e1e3 b8c6"))
+                .unwrap_err()
+                .to_string(),
+            "Illegal move e1e3 in position: \
+             rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+    }
+
+    #[test]
+    fn test_set_position_own_turn() {
+        let mut brain = Brain::new(Board::default(), Color::White);
+        brain
+            .set_position("", &moves("e2e4 e7e5 g1f3 b8c6"), Color::White)
+            .unwrap();
+        assert!(brain.is_own_turn());
+        assert_eq!(brain.color, Color::White);
+        assert_eq!(brain.last_move, Some(("g1", "f3").into()));
+        assert_eq!(brain.opponent_last_move, Some(("b8", "c6").into()));
+
+        // Black to move from the initial FEN, no moves yet
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        brain.set_position(fen, &[], Color::Black).unwrap();
+        assert!(brain.is_own_turn());
+        assert_eq!(brain.last_move, None);
+        assert_eq!(brain.opponent_last_move, None);
+    }
+
+    #[test]
+    fn test_set_position_opponent_turn() {
+        let mut brain = Brain::new(Board::default(), Color::White);
+        brain
+            .set_position("startpos", &moves("e2e4 e7e5"), Color::Black)
+            .unwrap();
+        assert!(!brain.is_own_turn());
+        assert_eq!(brain.color, Color::Black);
+        assert_eq!(brain.last_move, Some(("e7", "e5").into()));
+        assert_eq!(brain.opponent_last_move, Some(("e2", "e4").into()));
+
+        brain
+            .set_position("", &moves("e2e4"), Color::White)
+            .unwrap();
+        assert!(!brain.is_own_turn());
+        assert_eq!(brain.last_move, Some(("e2", "e4").into()));
+        assert_eq!(brain.opponent_last_move, None);
+
+        assert!(brain
+            .set_position("8/8 w - - 0 1", &[], Color::White)
+            .is_err());
+    }
+
+    #[test]
+    fn test_endgame_king_activity() {
+        // In the endgame, the centralized king scores higher
+        let central = Board::from_fen("8/p7/8/8/4K3/8/7P/k7 w - - 0 1").unwrap();
+        let corner = Board::from_fen("8/p7/8/8/8/8/7P/k6K w - - 0 1").unwrap();
+        assert!(central.is_endgame());
+        assert!(evaluate(Color::White, &central) > evaluate(Color::White, &corner));
+
+        // In the opening, the king scores higher on its back rank
+        let safe =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let exposed =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/4K3/PPPPPPPP/RNBQ1BNR w kq - 0 1").unwrap();
+        assert!(!safe.is_endgame());
+        assert!(evaluate(Color::White, &safe) > evaluate(Color::White, &exposed));
+        assert_eq!(evaluate(Color::White, &safe), Evaluation::Score(0));
+        assert!(evaluate(Color::Black, &exposed) > Evaluation::Score(0));
+    }
+
+    #[test]
+    fn test_mop_up() {
+        // With K+Q vs K, the enemy king is better on the edge, and the own king closer to it
+        let center = Board::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").unwrap();
+        let edge = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let closer = Board::from_fen("4k3/8/4K3/8/8/8/8/3Q4 w - - 0 1").unwrap();
+        assert!(mop_up(&edge, Color::White) > mop_up(&center, Color::White));
+        assert!(mop_up(&closer, Color::White) > mop_up(&edge, Color::White));
+        assert_eq!(mop_up(&edge, Color::Black), 0);
+        assert!(evaluate(Color::White, &edge) > evaluate(Color::White, &center));
+
+        // Not while the material is even, or while the enemy has pawns
+        let even = Board::from_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(mop_up(&even, Color::White), 0);
+        let pawns = Board::from_fen("4k3/p7/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(mop_up(&pawns, Color::White), 0);
+
+        // The engine makes progress: the enemy king is pushed away from the center
+        let mut board = center;
+        let start = board.black.king.to_position().center_distance();
+        for _ in 0..8 {
+            let result = search(&board, board.turn, 3).unwrap();
+            board.apply_move(result.best);
+        }
+        let end = board.black.king.to_position().center_distance();
+        assert!(end > start, "{}", board.to_fen());
+    }
+
+    #[test]
+    fn test_dead_position_is_draw() {
+        // A bishop up, but it can't mate
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        assert!(evaluate(Color::White, &board) > Evaluation::Score(0));
+        let result = search(&board, Color::White, 2).unwrap();
+        assert_eq!(result.eval, Evaluation::Score(0));
+    }
+
+    #[test]
+    fn test_square_control() {
+        // 1. e4 controls d5 (and more squares for the bishop and queen) than 1. a3
+        let center =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let rim =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/P7/1PPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert!(square_control(&center, Color::White) > square_control(&rim, Color::White));
+        assert!(evaluate(Color::White, &center) > evaluate(Color::White, &rim));
+        assert_eq!(
+            square_control(&Board::default(), Color::White),
+            square_control(&Board::default(), Color::Black)
+        );
+
+        // Not in the endgame
+        let endgame = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(square_control(&endgame, Color::White), 0);
+    }
+
+    #[test]
+    fn test_pins() {
+        // The knight on e2 is pinned by the rook
+        let pinned = Board::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(pins(&pinned, Color::White), -PINNED_PIECE_PENALTY);
+        assert_eq!(pins(&pinned, Color::Black), 0);
+
+        let free = Board::from_fen("4k3/3r4/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(pins(&free, Color::White), 0);
+    }
+
+    #[test]
+    fn test_trapped_pieces() {
+        // A knight in the corner, with b6 covered by a pawn
+        let corner = Board::from_fen("N3k3/2p5/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            trapped_pieces(&corner, Color::White),
+            -TRAPPED_KNIGHT_PENALTY
+        );
+        let center = Board::from_fen("4k3/2p5/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(trapped_pieces(&center, Color::White), 0);
+        assert!(evaluate(Color::White, &center) > evaluate(Color::White, &corner));
+
+        // A bishop behind its own pawns, then freed by a pawn push
+        let buried = Board::from_fen("4k3/8/8/8/8/8/1P1P4/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(
+            trapped_pieces(&buried, Color::White),
+            -TRAPPED_BISHOP_PENALTY
+        );
+        let free = Board::from_fen("4k3/8/8/8/8/3P4/1P6/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(trapped_pieces(&free, Color::White), 0);
+
+        // Black's bishop looks down the board
+        let black = Board::from_fen("2b1k3/1p1p4/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            trapped_pieces(&black, Color::Black),
+            -TRAPPED_BISHOP_PENALTY
+        );
+    }
+
+    #[test]
+    fn test_passed_pawn_push() {
+        // K+P vs K: the pawn runs to promotion, escorted by the king
+        let mut board = Board::from_fen("8/8/8/8/7k/8/1P6/1K6 w - - 0 1").unwrap();
+        for _ in 0..6 {
+            let result = search(&board, board.turn, 3).unwrap();
+            board.apply_move(result.best);
+        }
+        let pawn = board.white.pawns.to_position();
+        assert!(pawn.rank_y >= 4, "{}", board.to_fen());
+
+        // Advanced and supported passers are worth more
+        let far = Board::from_fen("4k3/8/8/8/8/8/1P6/4K3 w - - 0 1").unwrap();
+        let advanced = Board::from_fen("4k3/8/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+        let supported = Board::from_fen("4k3/8/8/1P6/P7/8/8/4K3 w - - 0 1").unwrap();
+        let blocked = Board::from_fen("4k3/2p5/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(passed_pawns(&advanced, Color::White) > passed_pawns(&far, Color::White));
+        assert!(passed_pawns(&supported, Color::White) > passed_pawns(&advanced, Color::White));
+        assert_eq!(passed_pawns(&blocked, Color::White), 0);
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let mut brain = Brain::new(Board::default(), Color::White);
+        let (out, back): (Move, Move) = (("g1", "f3").into(), ("f3", "g1").into());
+        let (opponent_out, opponent_back): (Move, Move) =
+            (("g8", "f6").into(), ("f6", "g8").into());
+
+        // The starting position is reached a second time
+        brain.own_move(out);
+        brain.opponent_move(opponent_out);
+        brain.own_move(back);
+        brain.opponent_move(opponent_back);
+        assert!(!brain.is_threefold_repetition(out));
+
+        // ... and a third time
+        brain.own_move(out);
+        brain.opponent_move(opponent_out);
+        brain.own_move(back);
+        brain.opponent_move(opponent_back);
+        assert!(!brain.is_threefold_repetition(("b1", "c3").into()));
+        assert!(brain.is_threefold_repetition(out));
+
+        // The history is rebuilt when the position is set
+        let mut resumed = Brain::new(Board::default(), Color::White);
+        resumed
+            .set_position(
+                "startpos",
+                &moves("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8"),
+                Color::White,
+            )
+            .unwrap();
+        assert!(resumed.is_threefold_repetition(out));
+    }
+
+    #[test]
+    fn test_sync_moves() {
+        let mut brain = Brain::new(Board::default(), Color::Black);
+        assert_eq!(brain.sync_moves(&moves("e2e4")).unwrap(), moves("e2e4"));
+        assert_eq!(brain.opponent_last_move, Some(("e2", "e4").into()));
+
+        // After reconnecting, the full history comes with the moves that were missed
+        let full = moves("e2e4 e7e5 g1f3 b8c6");
+        assert_eq!(brain.sync_moves(&full).unwrap(), moves("e7e5 g1f3 b8c6"));
+        assert_eq!(brain.last_move, Some(("b8", "c6").into()));
+        assert_eq!(brain.opponent_last_move, Some(("g1", "f3").into()));
+        assert_eq!(brain.plies_played(), 4);
+        let mut expected = Board::default();
+        full.iter().for_each(|m| expected.apply_move(*m));
+        assert_eq!(*brain.board(), expected);
+
+        // The same history again is a no-op
+        assert!(brain.sync_moves(&full).unwrap().is_empty());
+        assert_eq!(*brain.board(), expected);
+
+        // A diverging history resets the position
+        let other = moves("d2d4 d7d5");
+        assert_eq!(brain.sync_moves(&other).unwrap(), other);
+        assert_eq!(brain.plies_played(), 2);
+        assert_eq!(brain.last_move, Some(("d7", "d5").into()));
+
+        // Illegal moves are rejected, whether new or replayed from scratch
+        let board = *brain.board();
+        assert!(brain.sync_moves(&moves("d2d4 d7d5 e1e3")).is_err());
+        assert!(brain.sync_moves(&moves("e2e4 e7e5 e1e3")).is_err());
+        assert_eq!(*brain.board(), board);
+        assert_eq!(brain.plies_played(), 2);
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        assert_eq!(pawn_structure(&Board::default()), 0);
+
+        // White has doubled and isolated c-pawns and an isolated h-pawn, Black an isolated g-pawn
+        let board = Board::from_fen("4k3/pp4p1/8/8/8/2P5/2P4P/4K3 w - - 0 1").unwrap();
+        let white = -DOUBLED_PAWN_PENALTY - 3 * ISOLATED_PAWN_PENALTY;
+        let black = -ISOLATED_PAWN_PENALTY;
+        assert_eq!(pawn_structure(&board), white - black);
+    }
+
+    #[test]
+    fn test_evaluation_symmetry() {
+        // The same position with the colors swapped
+        fn mirror(board: &Board) -> Board {
+            Board {
+                white: board.black.flip(),
+                black: board.white.flip(),
+                turn: board.turn.opposite(),
+                ..*board
+            }
+        }
+
+        // Positions from random self-play games, reproducible with the seeds
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut board = Board::default();
+            for _ in 0..100 {
+                let white = evaluate(Color::White, &board);
+                assert_eq!(white, -evaluate(Color::Black, &board), "{}", board.to_fen());
+                assert_eq!(
+                    white,
+                    evaluate(Color::Black, &mirror(&board)),
+                    "{}",
+                    board.to_fen()
+                );
+
+                let moves = board.legal_moves(board.turn);
+                if moves.is_empty() {
+                    break;
+                }
+                board.apply_move(moves[rng.gen_range(0..moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        let mut board = Board::from_fen("4k3/pp4p1/1n6/8/8/2P5/2P2N1P/4K3 w - - 0 1").unwrap();
+        let key = board.pawn_hash();
+        for m in moves("f2g4 b6d5 g4e5 d5c7 e1d2 e8d8") {
+            board.apply_move(m);
+            assert_eq!(board.pawn_hash(), key);
+            assert_eq!(cached_pawn_structure(&board), pawn_structure(&board));
+        }
+        PAWN_TABLE.with(|table| assert!(table.borrow().contains_key(&key)));
+
+        // A pawn move changes the key
+        board.apply_move(("c3", "c4").into());
+        assert_ne!(board.pawn_hash(), key);
+        assert_eq!(cached_pawn_structure(&board), pawn_structure(&board));
+    }
+
+    #[test]
+    fn test_eval_table() {
+        let board = Board::from_fen("4k3/pp4p1/1n6/8/8/2P5/2P2N1P/4K3 w - - 0 1").unwrap();
+        let hash = board.zobrist_hash();
+        let (eval, _) = cached_evaluate(Color::White, &board, hash);
+        assert_eq!(eval, evaluate(Color::White, &board));
+        for _ in 0..3 {
+            assert_eq!(cached_evaluate(Color::White, &board, hash), (eval, true));
+            assert_eq!(cached_evaluate(Color::Black, &board, hash), (-eval, true));
+        }
+
+        // A transposition hits the table too
+        let transposed = board
+            .with_move(("f2", "g4").into())
+            .with_move(("b6", "d5").into())
+            .with_move(("g4", "f2").into())
+            .with_move(("d5", "b6").into());
+        assert_eq!(
+            cached_evaluate(Color::White, &transposed, transposed.zobrist_hash()),
+            (eval, true)
+        );
+
+        // Another position in the same entry replaces it
+        let other = board.with_move(("e1", "d1").into());
+        let other_hash = hash + EVAL_TABLE_SIZE as u64;
+        assert!(!cached_evaluate(Color::White, &other, other_hash).1);
+        assert!(cached_evaluate(Color::White, &other, other_hash).1);
+        assert_eq!(cached_evaluate(Color::White, &board, hash), (eval, false));
+    }
+
+    #[test]
+    fn test_evaluation_terms() {
+        assert_eq!(Evaluation::from_terms(&[]), Evaluation::Score(0));
+        assert_eq!(
+            Evaluation::from_terms(&[300, -20, 5]),
+            Evaluation::Score(285)
+        );
+
+        // Large terms clamp instead of overflowing, and never reach a mate
+        let huge = Evaluation::from_terms(&[i32::MAX / 2; 10]);
+        assert_eq!(huge, Evaluation::Score(MAX_SCORE_CP));
+        assert!(huge < Evaluation::Best);
+        assert_eq!(
+            Evaluation::from_terms(&[i32::MIN, -1, -MAX_SCORE_CP]),
+            Evaluation::Score(-MAX_SCORE_CP)
+        );
+        assert_eq!(-huge, Evaluation::Score(-MAX_SCORE_CP));
+
+        assert_eq!(Evaluation::Score(MAX_SCORE_CP) + 1, huge);
+        assert_eq!(Evaluation::Score(10) + -25, Evaluation::Score(-15));
+        assert_eq!(Evaluation::Best + -1000, Evaluation::Best);
+        assert_eq!(Evaluation::Worst + 1000, Evaluation::Worst);
+    }
+
+    #[test]
+    fn test_search() {
+        // Back-rank mate
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        let result = search(&board, Color::White, 2).unwrap();
+        assert_eq!(result.best, ("d1", "d8").into());
+        assert_eq!(result.eval, Evaluation::Best);
+        assert_eq!(result.depth, 2);
+        assert!(result.nodes > 0);
+
+        // Checkmated: no move
+        let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        assert_eq!(search(&board, Color::Black, 2), None);
+    }
+
+    #[test]
+    fn test_quiescence() {
+        // The d5 pawn is defended: taking it loses the queen, which the search must see beyond
+        // its depth
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = search(&board, Color::White, 1).unwrap();
+        assert_ne!(result.best, ("d1", "d5").into());
+        assert!(result.stats.quiescence_nodes > 0);
+        assert!(result.seldepth > result.depth);
+
+        // Nothing to capture
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let result = search(&board, Color::White, 2).unwrap();
+        assert_eq!(result.stats.quiescence_nodes, 0);
+        assert_eq!(result.seldepth, 2);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        // Few pieces, so that transpositions reach the same leaves
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let result = search(&board, Color::White, 4).unwrap();
+        let stats = result.stats;
+        assert!(result.nodes > 0);
+        assert!(stats.beta_cutoffs > 0);
+        assert!(stats.eval_table_hits > 0);
+        assert!(stats.eval_table_hits + stats.eval_table_misses < result.nodes);
+        assert!(stats.quiescence_nodes < result.nodes);
+
+        // Without the evaluation table, every leaf is evaluated again
+        let board = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/3PP3/2N2N2/PPP2PPP/R1BQKB1R b KQkq d3 0 4",
+        )
+        .unwrap();
+        let stats = |no_eval_table: bool| {
+            let counters = SearchCounters {
+                no_eval_table,
+                ..SearchCounters::default()
+            };
+            search_root(board, Color::Black, 3, &counters);
+            counters.stats()
+        };
+        let (with_table, without_table) = (stats(false), stats(true));
+        assert!(with_table.eval_table_hits > without_table.eval_table_hits);
+        assert_eq!(without_table.eval_table_hits, 0);
+        assert!(without_table.eval_table_misses > with_table.eval_table_misses);
+        let ebf = result.effective_branching_factor();
+        assert!(ebf > 1.0 && ebf < 40.0, "{}", ebf);
+
+        // No search
+        let book = SearchResult {
+            depth: 0,
+            nodes: 0,
+            ..result
+        };
+        assert_eq!(book.effective_branching_factor(), 0.0);
+    }
+
+    #[test]
+    fn test_seldepth() {
+        // Plenty of captures in the center
+        let board = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/3PP3/2N2N2/PPP2PPP/R1BQKB1R b KQkq d3 0 4",
+        )
+        .unwrap();
+        let result = search(&board, Color::Black, 3).unwrap();
+        assert!(result.seldepth >= result.depth);
+        assert!(result.nodes > 0);
+
+        let info = result.uci_info();
+        assert!(
+            info.starts_with(&format!(
+                "info depth 3 seldepth {} nodes {} nps ",
+                result.seldepth, result.nodes
+            )),
+            "{}",
+            info
+        );
+        assert!(info.contains(&format!(" pv {}", result.best.to_uci())));
+
+        // Mate in 1, in moves
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        let result = search(&board, Color::White, 2).unwrap();
+        assert!(result.uci_info().contains(" score mate 1 pv d1d8"));
+    }
+
+    #[test]
+    fn test_book_move() {
+        let book =
+            OpeningBook::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -\tg1f3\t1\n");
+        let mut brain = Brain::new(Board::default(), Color::White).with_book(Some(Arc::new(book)));
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(60));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+        assert_eq!(result.best, ("g1", "f3").into());
+        assert_eq!(result.depth, 0);
+
+        // The book is kept when the position is reset
+        brain.set_position("startpos", &[], Color::White).unwrap();
+        assert!(brain.book.is_some());
+    }
+
+    #[test]
+    fn test_pick_varied_move() {
+        let scored = [
+            (("e2", "e4").into(), Evaluation::Score(40)),
+            (("d2", "d4").into(), Evaluation::Score(35)),
+            (("g1", "f3").into(), Evaluation::Score(20)),
+            (("c2", "c4").into(), Evaluation::Score(15)),
+            (("a2", "a3").into(), Evaluation::Score(-20)),
+        ];
+        let picks = (0..50)
+            .map(|seed| pick_varied_move(&scored, seed, MAX_LEVEL).unwrap().0)
+            .collect::<Vec<Move>>();
+
+        // Only the top 3 moves within the margin are picked
+        assert!(picks
+            .iter()
+            .all(|m| scored[..3].iter().any(|(top, _)| top == m)));
+        assert!(picks.iter().any(|m| *m != picks[0]));
+        assert_eq!(picks[7], pick_varied_move(&scored, 7, MAX_LEVEL).unwrap().0);
+
+        // A much better move is always picked
+        let scored = [
+            (("e2", "e4").into(), Evaluation::Best),
+            (("d2", "d4").into(), Evaluation::Score(35)),
+        ];
+        assert!((0..20)
+            .all(|seed| pick_varied_move(&scored, seed, MAX_LEVEL).unwrap().0 == scored[0].0));
+        assert_eq!(pick_varied_move(&[], 0, MAX_LEVEL), None);
+    }
+
+    #[test]
+    fn test_bishop_pair() {
+        let pair = Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").unwrap();
+        let bishop_knight =
+            Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KN2 w - - 0 1").unwrap();
+        assert!(evaluate(Color::White, &pair) > evaluate(Color::White, &bishop_knight));
+        assert_eq!(
+            evaluate(Color::White, &pair),
+            // Both bishops are still behind their pawns
+            Evaluation::Score(2 * 330 + BISHOP_PAIR_BONUS - 2 * TRAPPED_BISHOP_PENALTY)
+        );
+
+        // Two bishops on the same color aren't a pair
+        let same_color =
+            Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1K1B1 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(Color::White, &same_color),
+            Evaluation::Score(2 * 330 - 2 * TRAPPED_BISHOP_PENALTY)
+        );
+    }
+
+    #[test]
+    fn test_oscillation_is_broken() {
+        // The rook shuffles between a1 and b1, with a flat evaluation
+        let board = Board::from_fen("7k/8/8/8/8/8/8/R6K w - - 0 1").unwrap();
+        let mut brain = Brain::new(board, Color::White);
+        let (a, b): (Move, Move) = (("a1", "b1").into(), ("b1", "a1").into());
+        let scored = [
+            (a, Evaluation::Score(0)),
+            (("a1", "a2").into(), Evaluation::Score(-10)),
+        ];
+
+        for (own, opponent) in [(a, ("h8", "g8")), (b, ("g8", "h8"))].iter() {
+            assert_eq!(pick_move(&scored, &brain.recent_moves), Some(scored[0]));
+            brain.own_move(*own);
+            brain.opponent_move((*opponent).into());
+        }
+        assert!(!is_oscillating(&brain.recent_moves));
+        brain.own_move(a);
+        brain.opponent_move(("h8", "g8").into());
+        brain.own_move(b);
+        brain.opponent_move(("g8", "h8").into());
+        assert!(is_oscillating(&brain.recent_moves));
+
+        // Repeating a1b1 would continue the cycle, so the next best move is played
+        assert_eq!(pick_move(&scored, &brain.recent_moves), Some(scored[1]));
+
+        // ... unless the alternative is much worse
+        let scored = [
+            (a, Evaluation::Score(0)),
+            (("a1", "a2").into(), Evaluation::Score(-200)),
+        ];
+        assert_eq!(pick_move(&scored, &brain.recent_moves), Some(scored[0]));
+    }
+
+    #[test]
+    fn test_set_position_recent_moves() {
+        let mut brain = Brain::new(Board::default(), Color::White);
+        brain
+            .set_position(
+                "",
+                &moves("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8"),
+                Color::White,
+            )
+            .unwrap();
+        assert!(is_oscillating(&brain.recent_moves));
+        assert_eq!(brain.recent_moves[3], Some(("f3", "g1").into()));
+
+        brain
+            .set_position(
+                "",
+                &moves("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1"),
+                Color::Black,
+            )
+            .unwrap();
+        assert_eq!(brain.recent_moves[0], None);
+        assert_eq!(brain.recent_moves[3], Some(("g8", "f6").into()));
+    }
+
+    #[test]
+    fn test_from_fen() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let brain = Brain::from_fen(fen, Color::White).unwrap();
+        assert_eq!(brain.board().to_fen(), fen);
+        assert!(brain.is_own_turn());
+
+        // A tiny budget keeps the search short
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_millis(1));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+        assert!(brain.board().is_legal_move(result.best));
+
+        assert!(Brain::from_fen("not a fen", Color::White).is_err());
+    }
+
+    #[test]
+    fn test_zero_time_search_returns_move() {
+        let brain = Brain::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            Color::White,
+        )
+        .unwrap();
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(0));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+        assert!(brain.board().is_legal_move(result.best));
+        assert_eq!(result.depth, 0);
+        assert_eq!(result.nodes, 0);
+        assert_eq!(
+            result.best,
+            list_potential_moves(*brain.board(), Color::White, false)[0].m
+        );
+
+        // No legal moves (checkmated)
+        let brain = Brain::from_fen("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1", Color::Black).unwrap();
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(0));
+        assert_eq!(futures::executor::block_on(recv).unwrap(), None);
+    }
+
+    /// Waits for the search started by `Brain::ponder` to complete its first iteration.
+    fn wait_for_ponder(brain: &Brain) {
+        let ponder = brain.ponder.as_ref().unwrap();
+        while ponder.best.lock().unwrap().is_none() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_ponder_hit() {
+        let fen = "4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1";
+        let mut brain = Brain::from_fen(fen, Color::White).unwrap();
+        brain.own_move(("e2", "e4").into());
+        brain.ponder(("e8", "e7").into());
+        assert_eq!(brain.pondered_move(), Some(("e8", "e7").into()));
+        wait_for_ponder(&brain);
+
+        brain.opponent_move(("e8", "e7").into());
+        brain.ponder_hit();
+
+        // Without any time left, the move still comes out of the search done while pondering
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(0));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+        assert!(brain.board().is_legal_move(result.best));
+        assert!(result.depth >= 1);
+        assert!(result.nodes > 0);
+
+        brain.own_move(result.best);
+        assert_eq!(brain.pondered_move(), None);
+    }
+
+    #[test]
+    fn test_ponder_miss() {
+        let fen = "4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1";
+        let mut brain = Brain::from_fen(fen, Color::White).unwrap();
+        brain.own_move(("e2", "e4").into());
+        brain.ponder(("e8", "e7").into());
+
+        let done = brain.ponder.as_ref().unwrap().done.clone();
+
+        brain.opponent_move(("e8", "d7").into());
+        brain.ponder_miss();
+        assert_eq!(brain.pondered_move(), None);
+
+        // The search stops in the middle of its iteration
+        let (lock, condvar) = &*done;
+        let (_done, timeout) = condvar
+            .wait_timeout_while(lock.lock().unwrap(), Duration::from_secs(10), |done| !*done)
+            .unwrap();
+        assert!(!timeout.timed_out());
+
+        // The search restarts, and has no time to complete an iteration
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(0));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+        assert!(brain.board().is_legal_move(result.best));
+        assert_eq!(result.depth, 0);
+
+        // Nothing to ponder on during the brain's turn, or for an illegal move
+        brain.ponder(("d2", "d4").into());
+        assert_eq!(brain.pondered_move(), None);
+        brain.own_move(("d2", "d4").into());
+        brain.ponder(("d7", "d5").into());
+        assert_eq!(brain.pondered_move(), None);
+    }
+
+    #[test]
+    fn test_level() {
+        // Nc7+ forks the king and the rook
+        let board = Board::from_fen("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1").unwrap();
+        let fork = Move::from(("b5", "c7"));
+        let choose = |brain: &Brain| {
+            let (sensor, recv) = oneshot::channel();
+            brain.choose_move(sensor, Duration::from_secs(60));
+            futures::executor::block_on(recv).unwrap().unwrap()
+        };
+
+        let mut brain = Brain::new(board, Color::White);
+        assert_eq!(brain.level(), MAX_LEVEL);
+        assert_eq!(choose(&brain).best, fork);
+
+        // Level 0 only searches 1 ply (and the captures after it), and misses the fork
+        brain.set_level(0);
+        let results = (0..10)
+            .map(|seed| choose(&brain.clone().with_opening_seed(seed)))
+            .collect::<Vec<SearchResult>>();
+        assert!(results.iter().all(|result| result.depth == 1));
+        assert!(results.iter().any(|result| result.best != fork));
+        let full = Brain::new(board, Color::White).best_moves(usize::MAX, SEARCH_DEPTH);
+        let full_eval = |m: Move| full.iter().find(|(full_m, _)| *full_m == m).unwrap().1;
+        let score = |eval: Evaluation| match eval {
+            Evaluation::Score(score) => score,
+            _ => panic!("unexpected mate: {:?}", eval),
+        };
+        let worst = results
+            .iter()
+            .map(|result| score(full_eval(result.best)))
+            .min();
+        assert!(worst.unwrap() < score(full_eval(fork)) - 150);
+
+        brain.set_level(30);
+        assert_eq!(brain.level(), MAX_LEVEL);
+        assert_eq!(level_depth(0), 1);
+        assert_eq!(level_depth(MAX_LEVEL), SEARCH_DEPTH);
+        assert_eq!(
+            level_budget(Duration::from_secs(21), 0),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_opening_extension() {
+        let choose = |brain: &Brain| {
+            let (sensor, recv) = oneshot::channel();
+            brain.choose_move(sensor, Duration::from_secs(60));
+            futures::executor::block_on(recv).unwrap().unwrap()
+        };
+
+        // At level 0, the search is 1 ply deep
+        let mut brain = Brain::new(Board::default(), Color::White);
+        brain.set_level(0);
+        assert_eq!(choose(&brain).depth, 1);
+        let extended = brain.clone().with_opening_extension(4, 1);
+        assert_eq!(choose(&extended).depth, 2);
+        assert_eq!(brain.with_opening_extension(4, 2).max_depth(), 3);
+
+        // Not once the pieces are developed
+        let developed = Board::from_fen(
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 6 5",
+        )
+        .unwrap();
+        let brain = Brain::new(developed, Color::White).with_opening_extension(10, 2);
+        assert_eq!(brain.max_depth(), SEARCH_DEPTH);
+
+        // Nor after the first moves
+        let mut brain = Brain::new(Board::default(), Color::White).with_opening_extension(1, 2);
+        assert_eq!(brain.max_depth(), SEARCH_DEPTH + 2);
+        brain
+            .set_position("", &moves("g1f3 g8f6"), Color::White)
+            .unwrap();
+        assert_eq!(brain.max_depth(), SEARCH_DEPTH);
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let board = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/3PP3/2N2N2/PPP2PPP/R1BQKB1R b KQkq d3 0 4",
+        )
+        .unwrap();
+        let choose = |brain: &Brain| {
+            let (sensor, recv) = oneshot::channel();
+            brain.choose_move(sensor, Duration::from_secs(60));
+            futures::executor::block_on(recv).unwrap().unwrap()
+        };
+
+        for max_nodes in [1, 100, 2000].iter() {
+            let brain = Brain::new(board, Color::Black).with_max_nodes(Some(*max_nodes));
+            let result = choose(&brain);
+            assert!(board.is_legal_move(result.best));
+            assert!(result.nodes <= *max_nodes, "{:?}", result);
+            assert!(result.depth < SEARCH_DEPTH);
+
+            // The same budget always leads to the same move
+            assert_eq!(choose(&brain).best, result.best);
+        }
+    }
+
+    #[test]
+    fn test_move_ordering() {
+        for fen in [
+            // Plenty of captures in the center
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/3PP3/2N2N2/PPP2PPP/R1BQKB1R b KQkq d3 0 4",
+            // The knight on d5 is hanging
+            "4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1",
+            // Back-rank mate
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            let basic = SearchCounters {
+                basic_ordering: true,
+                ..SearchCounters::default()
+            };
+            let refined = SearchCounters::default();
+            let expected = search_root(board, board.turn, 3, &basic);
+            let result = search_root(board, board.turn, 3, &refined);
+
+            assert_eq!(result[0].m, expected[0].m, "{}", fen);
+            assert_eq!(result[0].eval, expected[0].eval, "{}", fen);
+            let nodes = |counters: &SearchCounters| counters.nodes.load(AtomicOrdering::Relaxed);
+            assert!(
+                nodes(&refined) < nodes(&basic),
+                "{}: {} nodes instead of {}",
+                fen,
+                nodes(&refined),
+                nodes(&basic)
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_result() {
+        let brain = Brain::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", Color::White).unwrap();
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(60));
+        let result = futures::executor::block_on(recv).unwrap().unwrap();
+
+        assert_eq!(result.best, ("a1", "a8").into());
+        assert_eq!(result.eval, Evaluation::Best);
+        assert_eq!(result.depth, SEARCH_DEPTH);
+        assert!(result.nodes > 0);
+        assert_eq!(result.pv.first(), Some(&result.best));
+        assert!(result.elapsed > Duration::default());
+
+        // The principal variation is playable
+        let mut board = *brain.board();
+        for m in result.pv.iter() {
+            assert!(board.is_legal_move(*m));
+            board.apply_move(*m);
+        }
+    }
+
+    #[test]
+    fn test_best_moves_matches_choose_move() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let brain = Brain::new(board, Color::White);
+
+        let best_moves = brain.best_moves(3, SEARCH_DEPTH);
+        assert_eq!(best_moves.len(), 3);
+        assert!(best_moves[0].1 >= best_moves[1].1);
+        assert!(best_moves[1].1 >= best_moves[2].1);
+
+        let (sensor, recv) = oneshot::channel();
+        brain.choose_move(sensor, Duration::from_secs(60));
+        let chosen = futures::executor::block_on(recv).unwrap();
+        let chosen = chosen.unwrap();
+        assert_eq!((chosen.best, chosen.eval), best_moves[0]);
+
+        // Back-rank mate
+        assert_eq!(best_moves[0], (("a1", "a8").into(), Evaluation::Best));
     }
 }
@@ -3,5 +3,11 @@
 extern crate log;
 
 pub mod bitboard;
+pub mod book;
+pub mod eco;
 pub mod game;
 pub mod genius;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod time;
+pub mod uci;
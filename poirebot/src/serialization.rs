@@ -0,0 +1,139 @@
+//! `serde` support, behind the `serde` feature.
+//!
+//! For compactness, `Position` serializes as its notation (`"e4"`), `Move` as its pure coordinate
+//! notation (`"e7e8q"`), and `Board` as its FEN.
+
+use std::fmt;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::game::position::Position;
+use crate::game::{Board, Move};
+
+/// Deserializes a value from a string, with the given parser.
+struct StrVisitor<F> {
+    expecting: &'static str,
+    parse: F,
+}
+
+impl<'de, T, F: FnOnce(&str) -> anyhow::Result<T>> Visitor<'de> for StrVisitor<F> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        (self.parse)(v).map_err(|e| E::custom(format!("{:#}", e)))
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor {
+            expecting: "a square in algebraic notation",
+            parse: Position::from_notation,
+        })
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_pure_notation())
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor {
+            expecting: "a move in pure coordinate notation",
+            parse: |notation: &str| {
+                if !(4..=5).contains(&notation.len()) || !notation.is_ascii() {
+                    return Err(anyhow::Error::msg("invalid move notation"));
+                }
+                let origin = Position::from_notation(&notation[0..2])?;
+                let destination = Position::from_notation(&notation[2..4])?;
+                Ok(Move(origin, destination, notation[4..].into()))
+            },
+        })
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor {
+            expecting: "a board in FEN",
+            parse: Board::from_fen,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::pieces::Color;
+    use crate::game::position::Position;
+    use crate::game::{Board, Move, Promotion};
+
+    #[test]
+    fn test_position_round_trip() {
+        let position = Position::from("e4");
+        let json = serde_json::to_string(&position).unwrap();
+        assert_eq!(json, "\"e4\"");
+        assert_eq!(serde_json::from_str::<Position>(&json).unwrap(), position);
+        assert!(serde_json::from_str::<Position>("\"z9\"").is_err());
+    }
+
+    #[test]
+    fn test_move_round_trip() {
+        let m = Move::from(("e7", "e8", Promotion::Queen));
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(json, "\"e7e8q\"");
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), m);
+
+        let m = Move::from(("g1", "f3"));
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(json, "\"g1f3\"");
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), m);
+        assert!(serde_json::from_str::<Move>("\"g1\"").is_err());
+    }
+
+    #[test]
+    fn test_promotion_round_trip() {
+        let json = serde_json::to_string(&Promotion::Knight).unwrap();
+        assert_eq!(json, "\"knight\"");
+        assert_eq!(
+            serde_json::from_str::<Promotion>(&json).unwrap(),
+            Promotion::Knight
+        );
+    }
+
+    #[test]
+    fn test_color_round_trip() {
+        let json = serde_json::to_string(&Color::Black).unwrap();
+        assert_eq!(json, "\"black\"");
+        assert_eq!(serde_json::from_str::<Color>(&json).unwrap(), Color::Black);
+    }
+
+    #[test]
+    fn test_board_round_trip() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{}\"", fen));
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+        assert!(serde_json::from_str::<Board>("\"8/8 w - - 0 1\"").is_err());
+    }
+}
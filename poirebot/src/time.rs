@@ -0,0 +1,282 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The think time per move when there is no clock, e.g. in correspondence or unlimited games.
+pub const DEFAULT_THINK_TIME: Duration = Duration::from_secs(10);
+
+/// The time to reserve from every move budget for the move to reach the server (network latency).
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(100);
+
+/// Below this much time left on the clock (without increment), the bot plays instantly to avoid
+/// losing on time.
+pub const LOW_TIME: Duration = Duration::from_secs(5);
+
+/// The share of the move budget to spend against each Stockfish level (1 to 8).
+/// Weak levels are played quickly, while strong levels get the full budget.
+pub const STOCKFISH_BUDGET_FACTORS: [f32; 8] = [0.1, 0.15, 0.25, 0.4, 0.6, 0.8, 1.0, 1.0];
+
+/// The state of a player's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// The time left on the clock.
+    pub remaining: Duration,
+    /// The time added to the clock after each move.
+    pub increment: Duration,
+}
+
+impl Clock {
+    /// Create a clock from the remaining time and increment, both in milliseconds.
+    pub fn from_millis(remaining: u64, increment: u64) -> Self {
+        Self {
+            remaining: Duration::from_millis(remaining),
+            increment: Duration::from_millis(increment),
+        }
+    }
+
+    /// Whether the clock is about to flag: less than `LOW_TIME` left, and no increment to make up
+    /// for the time spent searching.
+    pub fn is_low_time(&self) -> bool {
+        self.remaining < LOW_TIME && self.increment.is_zero()
+    }
+}
+
+/// The category of a time control, as defined by Lichess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeClass {
+    UltraBullet,
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+impl TimeClass {
+    /// All the time classes, fastest first.
+    pub const ALL: [TimeClass; 5] = [
+        TimeClass::UltraBullet,
+        TimeClass::Bullet,
+        TimeClass::Blitz,
+        TimeClass::Rapid,
+        TimeClass::Classical,
+    ];
+
+    /// Classifies a clock by its estimated game duration: the initial time plus 40 times the
+    /// increment (both in seconds).
+    ///
+    /// Ref: https://lichess.org/faq#time-controls
+    pub fn from_clock(initial_secs: u32, increment_secs: u32) -> Self {
+        let estimate = initial_secs + 40 * increment_secs;
+        if estimate < 30 {
+            TimeClass::UltraBullet
+        } else if estimate < 180 {
+            TimeClass::Bullet
+        } else if estimate < 480 {
+            TimeClass::Blitz
+        } else if estimate < 1500 {
+            TimeClass::Rapid
+        } else {
+            TimeClass::Classical
+        }
+    }
+}
+
+impl Display for TimeClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TimeClass::UltraBullet => "ultrabullet",
+            TimeClass::Bullet => "bullet",
+            TimeClass::Blitz => "blitz",
+            TimeClass::Rapid => "rapid",
+            TimeClass::Classical => "classical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for TimeClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimeClass::ALL
+            .iter()
+            .copied()
+            .find(|class| class.to_string() == s.trim().to_lowercase())
+            .ok_or_else(|| anyhow::Error::msg(format!("unknown time class: {}", s)))
+    }
+}
+
+/// Returns the time budget to think for the next move.
+///
+/// With a clock, the budget is a slice of the remaining time (assuming the game lasts a few dozen
+/// more moves) plus most of the increment, without ever going over half of the remaining time.
+/// Without a clock, the given fixed think time is used instead.
+///
+/// When the clock is critically low (see `is_low_time`), the budget is zero so that the bot moves
+/// without searching.
+pub fn move_budget(clock: Option<&Clock>, think_time: Duration) -> Duration {
+    match clock {
+        Some(clock) if clock.is_low_time() => Duration::ZERO,
+        Some(clock) => {
+            let budget = clock.remaining / 30 + clock.increment * 3 / 4;
+            budget.min(clock.remaining / 2)
+        }
+        None => think_time,
+    }
+}
+
+/// Reserves the move overhead (the time for the move to reach the server) from the move budget.
+/// The budget is zero if the overhead is larger.
+pub fn without_overhead(budget: Duration, overhead: Duration) -> Duration {
+    budget.saturating_sub(overhead)
+}
+
+/// Scales the move budget for a game against the given Stockfish level (1 to 8), using the
+/// factor of that level in the mapping. The factor is clamped between 0 and 1, so that the budget
+/// never grows.
+pub fn stockfish_level_budget(budget: Duration, level: u8, factors: &[f32; 8]) -> Duration {
+    let index = level.clamp(1, 8) as usize - 1;
+    let factor = factors[index];
+    if factor > 0.0 {
+        budget.mul_f32(factor.min(1.0))
+    } else {
+        Duration::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_clock_budget() {
+        assert_eq!(move_budget(None, DEFAULT_THINK_TIME), DEFAULT_THINK_TIME);
+        assert_eq!(
+            move_budget(None, Duration::from_secs(60)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn test_clock_budget() {
+        // 5+0: 10 seconds per move
+        let clock = Clock::from_millis(300_000, 0);
+        assert_eq!(
+            move_budget(Some(&clock), DEFAULT_THINK_TIME),
+            Duration::from_secs(10)
+        );
+
+        // 1+2 with 30 seconds left: 1 + 1.5 seconds per move
+        let clock = Clock::from_millis(30_000, 2_000);
+        assert_eq!(
+            move_budget(Some(&clock), DEFAULT_THINK_TIME),
+            Duration::from_millis(2_500)
+        );
+
+        // Never more than half of the remaining time
+        let clock = Clock::from_millis(1_000, 2_000);
+        assert_eq!(
+            move_budget(Some(&clock), DEFAULT_THINK_TIME),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[test]
+    fn test_low_time_budget() {
+        let clock = Clock::from_millis(4_000, 0);
+        assert!(clock.is_low_time());
+        assert_eq!(
+            move_budget(Some(&clock), DEFAULT_THINK_TIME),
+            Duration::ZERO
+        );
+
+        // The increment makes up for the search
+        let clock = Clock::from_millis(4_000, 2_000);
+        assert!(!clock.is_low_time());
+        assert!(move_budget(Some(&clock), DEFAULT_THINK_TIME) > Duration::ZERO);
+
+        let clock = Clock::from_millis(6_000, 0);
+        assert!(!clock.is_low_time());
+        assert_eq!(
+            move_budget(Some(&clock), DEFAULT_THINK_TIME),
+            Duration::from_millis(200)
+        );
+    }
+
+    #[test]
+    fn test_move_overhead() {
+        let clock = Clock::from_millis(300_000, 0);
+        let budget = move_budget(Some(&clock), DEFAULT_THINK_TIME);
+        assert_eq!(
+            without_overhead(budget, DEFAULT_MOVE_OVERHEAD),
+            Duration::from_millis(9_900)
+        );
+
+        // Never below zero
+        assert_eq!(
+            without_overhead(Duration::from_millis(50), DEFAULT_MOVE_OVERHEAD),
+            Duration::ZERO
+        );
+        assert_eq!(
+            without_overhead(Duration::ZERO, DEFAULT_MOVE_OVERHEAD),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_time_class() {
+        assert_eq!(TimeClass::from_clock(15, 0), TimeClass::UltraBullet);
+        assert_eq!(TimeClass::from_clock(60, 0), TimeClass::Bullet);
+        assert_eq!(TimeClass::from_clock(120, 1), TimeClass::Bullet);
+        assert_eq!(TimeClass::from_clock(180, 0), TimeClass::Blitz);
+        assert_eq!(TimeClass::from_clock(180, 2), TimeClass::Blitz);
+        assert_eq!(TimeClass::from_clock(300, 3), TimeClass::Blitz);
+        assert_eq!(TimeClass::from_clock(600, 0), TimeClass::Rapid);
+        assert_eq!(TimeClass::from_clock(900, 10), TimeClass::Rapid);
+        assert_eq!(TimeClass::from_clock(1800, 0), TimeClass::Classical);
+
+        assert_eq!("blitz".parse::<TimeClass>().unwrap(), TimeClass::Blitz);
+        assert_eq!(
+            " UltraBullet".parse::<TimeClass>().unwrap(),
+            TimeClass::UltraBullet
+        );
+        assert!("correspondence".parse::<TimeClass>().is_err());
+    }
+
+    #[test]
+    fn test_stockfish_level_budget() {
+        let budget = Duration::from_secs(10);
+        let budgets = (1..=8)
+            .map(|level| stockfish_level_budget(budget, level, &STOCKFISH_BUDGET_FACTORS))
+            .collect::<Vec<Duration>>();
+        assert_eq!(budgets[0], Duration::from_secs(1));
+        assert_eq!(budgets[7], budget);
+        assert!(budgets.windows(2).all(|w| w[0] <= w[1]));
+
+        // Out-of-range levels use the closest one
+        assert_eq!(
+            stockfish_level_budget(budget, 0, &STOCKFISH_BUDGET_FACTORS),
+            budgets[0]
+        );
+        assert_eq!(
+            stockfish_level_budget(budget, 20, &STOCKFISH_BUDGET_FACTORS),
+            budgets[7]
+        );
+
+        // Custom mapping
+        let factors = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5];
+        assert_eq!(stockfish_level_budget(budget, 1, &factors), budget);
+        assert_eq!(
+            stockfish_level_budget(budget, 8, &factors),
+            Duration::from_secs(5)
+        );
+
+        // Factors out of range are clamped, instead of growing the budget or panicking
+        let factors = [2.0, f32::MAX, f32::INFINITY, -1.0, f32::NAN, 0.0, 1.0, 0.5];
+        let budgets = (1..=8)
+            .map(|level| stockfish_level_budget(budget, level, &factors))
+            .collect::<Vec<Duration>>();
+        assert_eq!(budgets[..3], [budget; 3]);
+        assert_eq!(budgets[3..6], [Duration::ZERO; 3]);
+    }
+}
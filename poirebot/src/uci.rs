@@ -0,0 +1,60 @@
+/// The identity of the engine, as reported over UCI and in the Lichess chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineInfo {
+    /// The display name of the engine.
+    pub name: String,
+    /// The author(s) of the engine.
+    pub author: String,
+    /// The version of the engine.
+    pub version: String,
+}
+
+impl Default for EngineInfo {
+    fn default() -> Self {
+        Self {
+            name: "Poirebot".into(),
+            author: env!("CARGO_PKG_AUTHORS").into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+        }
+    }
+}
+
+impl EngineInfo {
+    /// The default identity, with another display name (the version is kept).
+    pub fn with_name(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The response to the UCI `uci` command: the engine's identity, then `uciok`.
+    ///
+    /// Ref: https://www.shredderchess.com/chess-features/uci-universal-chess-interface.html
+    pub fn uci_response(&self) -> String {
+        format!(
+            "id name {} {}\nid author {}\nuciok",
+            self.name, self.version, self.author
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uci_response() {
+        let info = EngineInfo::default();
+        let response = info.uci_response();
+        assert!(response.starts_with(&format!("id name Poirebot {}\n", info.version)));
+        assert!(response.contains("\nid author Aram Peres"));
+        assert!(response.ends_with("\nuciok"));
+
+        let info = EngineInfo::with_name("Pear");
+        assert_eq!(info.version, EngineInfo::default().version);
+        assert!(info
+            .uci_response()
+            .starts_with(&format!("id name Pear {}\n", info.version)));
+    }
+}
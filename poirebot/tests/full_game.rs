@@ -0,0 +1,101 @@
+//! Plays a full game between two brains from the starting position, at a shallow depth, to check
+//! that the move generation, the legality checks, the moves and the detection of the end of the
+//! game work together.
+
+use poirebot::game::pieces::Color;
+use poirebot::game::{Board, Move};
+use poirebot::genius::Brain;
+
+/// The depth of the search of both brains (in plies).
+const DEPTH: usize = 2;
+
+/// The number of moves (plies) after which the game is considered stuck.
+const MAX_PLIES: usize = 400;
+
+/// The opening seeds of White and Black (see `Brain::with_opening_seed`), so that the game is the
+/// same on every run.
+const SEEDS: [u64; 2] = [0x5eed_0001, 0x5eed_0002];
+
+/// How a game ended.
+#[derive(Debug, PartialEq)]
+enum Ending {
+    Checkmate(Color),
+    Stalemate,
+    DeadPosition,
+    FiftyMoveRule,
+    Repetition,
+}
+
+/// The way the game is over in the position, if it is, with the side to move.
+fn ending(board: &Board, repetition: bool) -> Option<Ending> {
+    let color = board.turn;
+    if board.legal_move_count(color) == 0 {
+        if board.is_in_check(color) {
+            Some(Ending::Checkmate(color))
+        } else {
+            Some(Ending::Stalemate)
+        }
+    } else if board.is_dead_position() {
+        Some(Ending::DeadPosition)
+    } else if board.halfmove_clock >= 100 {
+        Some(Ending::FiftyMoveRule)
+    } else if repetition {
+        Some(Ending::Repetition)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_full_game() {
+    let mut board = Board::default();
+    let mut brains = [
+        Brain::new(board, Color::White).with_opening_seed(SEEDS[0]),
+        Brain::new(board, Color::Black).with_opening_seed(SEEDS[1]),
+    ];
+    let mut moves: Vec<Move> = Vec::new();
+    let mut repetition = false;
+
+    let end = loop {
+        if let Some(end) = ending(&board, repetition) {
+            break end;
+        }
+        assert!(
+            moves.len() < MAX_PLIES,
+            "No result after {} plies: {}",
+            MAX_PLIES,
+            board.to_fen()
+        );
+
+        let brain = &brains[if board.turn.is_white() { 0 } else { 1 }];
+        assert_eq!(brain.board(), &board);
+        let m = brain
+            .search_at(DEPTH)
+            .unwrap_or_else(|| panic!("No move found in {}", board.to_fen()))
+            .best;
+        assert!(
+            board.is_legal_move(m),
+            "Illegal move {} in {}",
+            m.to_pure_notation(),
+            board.to_fen()
+        );
+        repetition = brain.is_threefold_repetition(m);
+
+        board.apply_move(m);
+        moves.push(m);
+        assert_eq!(board.is_legal_position(), Ok(()), "after {:?}", moves);
+        for brain in brains.iter_mut() {
+            assert_eq!(brain.sync_moves(&moves).unwrap(), vec![m]);
+        }
+    };
+
+    // The moves replay to the final position
+    let mut replay = Board::default();
+    for m in moves.iter() {
+        replay.apply_move(*m);
+    }
+    assert_eq!(replay, board);
+    if let Ending::Checkmate(color) = end {
+        assert!(board.is_checkmate(color));
+    }
+}
@@ -0,0 +1,89 @@
+//! Positions where the bot once played a bad move. Each case runs the search at a small fixed
+//! depth, and checks that the move played is not the known-bad one, or has the expected property.
+//!
+//! To add a case, append it to `CASES` with the FEN (the side to move is the bot's color).
+
+use poirebot::game::{Board, Move};
+use poirebot::genius::Brain;
+
+/// The depth of the search for every case (in plies).
+const DEPTH: usize = 3;
+
+/// What the move played in a case must satisfy.
+enum Expect {
+    /// The move must not be this one (in pure coordinate notation).
+    Not(&'static str),
+    /// The move must be this one (in pure coordinate notation).
+    Is(&'static str),
+    /// The move must start from this square, e.g. to move a piece out of danger.
+    From(&'static str),
+    /// The move must be legal in the position.
+    Legal,
+    /// The move must not stalemate the opponent.
+    NoStalemate,
+}
+
+/// The regression cases: (description, FEN, expectation).
+const CASES: &[(&str, &str, Expect)] = &[
+    (
+        "king walks into the rook's file",
+        "3rk3/8/8/8/8/8/8/4K3 w - - 0 1",
+        Expect::Legal,
+    ),
+    (
+        "castles through an attacked square",
+        "4kr2/8/8/8/8/8/8/4K2R w K - 0 1",
+        Expect::Not("e1g1"),
+    ),
+    (
+        "stalemates with a queen up",
+        "7k/5K2/4Q3/8/8/8/8/8 w - - 0 1",
+        Expect::NoStalemate,
+    ),
+    (
+        "misses the back-rank mate",
+        "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+        Expect::Is("d1d8"),
+    ),
+    (
+        "leaves the queen attacked by a pawn",
+        "4k3/8/2p5/3Q4/8/8/8/4K3 w - - 0 1",
+        Expect::From("d5"),
+    ),
+];
+
+fn check(description: &str, fen: &str, expect: &Expect) -> Result<(), String> {
+    let board = Board::from_fen(fen).map_err(|e| format!("{}: {:?}", description, e))?;
+    let brain = Brain::new(board, board.turn);
+    let m: Move = brain
+        .search_at(DEPTH)
+        .ok_or_else(|| format!("{}: no move found", description))?
+        .best;
+    let notation = m.to_pure_notation();
+
+    let opponent = board.turn.opposite();
+    let outcome = board.with_move(m);
+    let ok = match expect {
+        Expect::Not(bad) => notation != *bad,
+        Expect::Is(good) => notation == *good,
+        Expect::From(origin) => m.0.to_string() == *origin,
+        Expect::Legal => board.is_legal_move(m),
+        Expect::NoStalemate => {
+            outcome.legal_move_count(opponent) > 0 || outcome.is_in_check(opponent)
+        }
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("{}: played {} in {}", description, notation, fen))
+    }
+}
+
+#[test]
+fn test_regressions() {
+    let failures = CASES
+        .iter()
+        .filter_map(|(description, fen, expect)| check(description, fen, expect).err())
+        .collect::<Vec<String>>();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}